@@ -0,0 +1,41 @@
+use std::cell::RefCell;
+use std::env;
+use std::error::Error;
+use std::ffi::CString;
+use std::rc::Rc;
+use std::thread;
+
+use qmk_oled_api::pages::{Page, Pages};
+use qmk_oled_api::screen::OledScreen;
+use qmk_oled_api::sources::{Sources, SystemStats};
+use qmk_oled_api::widgets::{Sparkline, Widget};
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let device_path =
+        CString::new(env::var("DEVICE_PATH").expect("Missing required env var")).unwrap();
+
+    let mut screen = OledScreen::from_path(&device_path, 32, 128)?;
+
+    let mut sources = Sources::new();
+    sources.add(SystemStats::new());
+
+    let graph = Rc::new(RefCell::new(
+        Sparkline::new(0, 0, 32, 128)
+            .with_range(0.0, 100.0)
+            .with_labels(8.0),
+    ));
+    let pages = Pages::new(vec![Page::new("cpu").with_widget(graph.clone())]);
+
+    loop {
+        if sources.poll_due()? {
+            if let Some(cpu) = sources.get("system.cpu").and_then(|cpu| cpu.as_number()) {
+                graph.borrow_mut().push(cpu as f32);
+            }
+
+            pages.draw(&mut screen)?;
+            screen.send()?;
+        }
+
+        thread::sleep(sources.next_due());
+    }
+}
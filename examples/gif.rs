@@ -4,28 +4,25 @@ use std::ffi::CString;
 use std::fs::File;
 
 use image::codecs::gif::GifDecoder;
-use image::imageops::{dither, BiLevel};
-use image::{AnimationDecoder, DynamicImage};
-use qmk_oled_api::screen::{ImageSizing, OledScreen};
+use qmk_oled_api::animation::AnimationPlayer;
+use qmk_oled_api::screen::{Binarization, ImageSizing, OledScreen};
 
 fn main() -> Result<(), Box<dyn Error>> {
     let device_path =
         CString::new(env::var("DEVICE_PATH").expect("Missing required env var")).unwrap();
 
-    let gif_file = File::open("examples/rick.gif")?;
-    let gif_decoder = GifDecoder::new(gif_file)?;
-    let frames = gif_decoder.into_frames().collect_frames()?;
-
     let mut screen = OledScreen::from_path(&device_path, 32, 128)?;
 
-    loop {
-        frames.iter().for_each(|frame| {
-            let frame = frame;
-            let image = frame.buffer().clone();
-            let dynamic = DynamicImage::ImageRgba8(image);
-            dither(&mut dynamic.grayscale().into_luma8(), &BiLevel);
-            screen.draw_image(dynamic, 0, 0, &ImageSizing::Cover);
-            screen.send().unwrap();
-        });
-    }
+    let gif_file = File::open("examples/rick.gif")?;
+    let gif_decoder = GifDecoder::new(gif_file)?;
+    let player = AnimationPlayer::from_decoder(
+        gif_decoder,
+        screen.width() as u32,
+        screen.height() as u32,
+        &ImageSizing::Cover,
+        Binarization::Dither,
+    )?
+    .with_loop(true);
+    player.play(&mut screen)?;
+    Ok(())
 }
@@ -0,0 +1,219 @@
+#include QMK_KEYBOARD_H
+#include "raw_hid.h"
+#include <string.h>
+
+#include "oled_api.h"
+
+#define OLED_API_PAYLOAD_SIZE 32
+
+#ifdef OLED_API_REPORT_PREFIX
+#    define OLED_API_PREFIX_SIZE 1
+#else
+#    define OLED_API_PREFIX_SIZE 0
+#endif
+
+#define OLED_API_CHUNK_SIZE (OLED_API_PAYLOAD_SIZE - 2 - OLED_API_PREFIX_SIZE)
+// The size of the chunks colour and gray frames are split into, which is always whole pixels
+#define OLED_API_WIDE_CHUNK_SIZE ((OLED_API_PAYLOAD_SIZE - 3 - OLED_API_PREFIX_SIZE) & ~1)
+
+// Features advertised to the host in the handshake, see Capabilities on the host
+enum oled_api_capability {
+    OLED_API_BRIGHTNESS_CAPABILITY = 1 << 0,
+    OLED_API_EVENTS_CAPABILITY = 1 << 1,
+    OLED_API_COMPRESSION_CAPABILITY = 1 << 2,
+    OLED_API_MULTI_SCREEN_CAPABILITY = 1 << 3,
+    OLED_API_QUANTUM_PAINTER_CAPABILITY = 1 << 4,
+};
+
+#ifdef OLED_API_NO_EVENTS
+#    define OLED_API_EVENTS_CAPABILITIES 0
+#else
+#    define OLED_API_EVENTS_CAPABILITIES OLED_API_EVENTS_CAPABILITY
+#endif
+#ifdef OLED_API_QUANTUM_PAINTER
+#    define OLED_API_DISPLAY_CAPABILITIES OLED_API_QUANTUM_PAINTER_CAPABILITY
+#else
+#    define OLED_API_DISPLAY_CAPABILITIES 0
+#endif
+#define OLED_API_CAPABILITIES \
+    (OLED_API_BRIGHTNESS_CAPABILITY | OLED_API_EVENTS_CAPABILITIES | OLED_API_DISPLAY_CAPABILITIES)
+
+enum oled_api_report {
+    OLED_API_SCREEN_REPORT = 1,
+    OLED_API_EVENT_REPORT = 2,
+    OLED_API_CONTROL_REPORT = 3,
+    OLED_API_HANDSHAKE_REPORT = 4,
+    OLED_API_COLOR_REPORT = 5,
+    OLED_API_GRAY_REPORT = 6,
+};
+
+enum oled_api_command {
+    OLED_API_BRIGHTNESS_COMMAND = 1,
+    OLED_API_POWER_COMMAND = 2,
+    OLED_API_HEARTBEAT_COMMAND = 3,
+};
+
+#ifdef OLED_API_QUANTUM_PAINTER
+static painter_device_t painter_device = NULL;
+
+void oled_api_set_painter_device(painter_device_t device) {
+    painter_device = device;
+}
+
+// Draw big-endian RGB565 pixels, the first of which is `pixel` pixels along each row from the top
+// left of the screen, wrapping onto the next row where a row ends
+static void oled_api_draw_pixels(uint32_t pixel, const uint8_t *data, uint16_t count) {
+    while (painter_device && count > 0 && pixel < OLED_API_WIDTH * OLED_API_HEIGHT) {
+        uint16_t x   = pixel % OLED_API_WIDTH;
+        uint16_t y   = pixel / OLED_API_WIDTH;
+        uint16_t run = MIN(count, OLED_API_WIDTH - x);
+        qp_viewport(painter_device, x, y, x + run - 1, y);
+        qp_pixdata(painter_device, data, run);
+        pixel += run;
+        data += run * 2;
+        count -= run;
+    }
+}
+
+// Draw 2 bit gray pixels, four to a byte with the leftmost in the top bits and each row starting
+// on a new byte, starting `offset` bytes into the frame
+static void oled_api_draw_gray(uint32_t offset, const uint8_t *data, uint16_t length) {
+    const uint16_t stride = (OLED_API_WIDTH + 3) / 4;
+    for (uint16_t i = 0; i < length; i++) {
+        uint16_t y  = (offset + i) / stride;
+        uint16_t x  = (offset + i) % stride * 4;
+        uint8_t  pixels[8];
+        uint8_t  count = 0;
+        for (; count < 4 && x + count < OLED_API_WIDTH; count++) {
+            uint8_t  gray   = (data[i] >> (6 - 2 * count) & 0b11) * 85;
+            uint16_t rgb565 = (gray >> 3) << 11 | (gray >> 2) << 5 | gray >> 3;
+            pixels[count * 2]     = rgb565 >> 8;
+            pixels[count * 2 + 1] = rgb565 & 0xFF;
+        }
+        oled_api_draw_pixels((uint32_t)y * OLED_API_WIDTH + x, pixels, count);
+    }
+}
+#else
+static uint8_t current_screen[OLED_API_WIDTH * OLED_API_HEIGHT / 8];
+#endif
+
+// When the last report from the host arrived, if one ever has
+static uint32_t last_host_report;
+static bool host_seen = false;
+
+// Send a report to the host, starting with the report prefix if there is one
+static void oled_api_send(uint8_t report_id, const uint8_t *args, uint8_t length) {
+    uint8_t report[OLED_API_PAYLOAD_SIZE] = {0};
+    uint8_t *data = &report[OLED_API_PREFIX_SIZE];
+#ifdef OLED_API_REPORT_PREFIX
+    report[0] = OLED_API_REPORT_PREFIX;
+#endif
+
+    data[0] = report_id;
+    memcpy(&data[1], args, MIN(length, OLED_API_PAYLOAD_SIZE - OLED_API_PREFIX_SIZE - 1));
+    raw_hid_send(report, sizeof(report));
+}
+
+static bool oled_api_handle(uint8_t *data, uint8_t length) {
+    if (length < 2) {
+        return false;
+    }
+
+    switch (data[0]) {
+#ifdef OLED_API_QUANTUM_PAINTER
+        // | 5 | INDEX (u16 BE) | PIXELS... |, drawn as they arrive
+        case OLED_API_COLOR_REPORT:
+            if (length > 3) {
+                uint32_t offset = (uint32_t)(data[1] << 8 | data[2]) * OLED_API_WIDE_CHUNK_SIZE;
+                uint16_t size   = MIN(length - 3, OLED_API_WIDE_CHUNK_SIZE);
+                oled_api_draw_pixels(offset / 2, &data[3], size / 2);
+            }
+            return true;
+        // | 6 | INDEX (u16 BE) | PIXELS... |, the same as a colour report with 2 bits to a pixel
+        case OLED_API_GRAY_REPORT:
+            if (length > 3) {
+                uint32_t offset = (uint32_t)(data[1] << 8 | data[2]) * OLED_API_WIDE_CHUNK_SIZE;
+                uint16_t size   = MIN(length - 3, OLED_API_WIDE_CHUNK_SIZE);
+                oled_api_draw_gray(offset, &data[3], size);
+            }
+            return true;
+#else
+        // | 1 | INDEX | DATA... |, where INDEX is the chunk of the screen the data belongs to
+        case OLED_API_SCREEN_REPORT: {
+            uint16_t offset = data[1] * OLED_API_CHUNK_SIZE;
+            if (offset < sizeof(current_screen)) {
+                uint16_t size = MIN(sizeof(current_screen) - offset, OLED_API_CHUNK_SIZE);
+                size = MIN(size, length - 2);
+                memcpy(&current_screen[offset], &data[2], size);
+            }
+            return true;
+        }
+#endif
+        // | 3 | COMMAND | ARGS... |
+        case OLED_API_CONTROL_REPORT:
+            switch (data[1]) {
+#ifdef OLED_API_QUANTUM_PAINTER
+#    ifdef BACKLIGHT_ENABLE
+                case OLED_API_BRIGHTNESS_COMMAND: backlight_level_noeeprom(data[2] * BACKLIGHT_LEVELS / 255); break;
+#    endif
+                case OLED_API_POWER_COMMAND: if (painter_device) qp_power(painter_device, data[2]); break;
+#else
+                case OLED_API_BRIGHTNESS_COMMAND: oled_set_brightness(data[2]); break;
+                case OLED_API_POWER_COMMAND: data[2] ? oled_on() : oled_off(); break;
+#endif
+                // Only there to reset the timeout, which every report does
+                case OLED_API_HEARTBEAT_COMMAND: break;
+            }
+            return true;
+        // | 4 | MAJOR | MINOR |, answered with the version this module speaks and the screen's
+        // | WIDTH | HEIGHT | ROTATION | CAPABILITIES |, the size as big-endian 16 bit numbers
+        case OLED_API_HANDSHAKE_REPORT: {
+            const uint8_t answer[] = {
+                OLED_API_PROTOCOL_MAJOR, OLED_API_PROTOCOL_MINOR,
+                OLED_API_WIDTH >> 8, OLED_API_WIDTH & 0xFF,
+                OLED_API_HEIGHT >> 8, OLED_API_HEIGHT & 0xFF,
+                OLED_API_ROTATION,
+                OLED_API_CAPABILITIES,
+            };
+            oled_api_send(OLED_API_HANDSHAKE_REPORT, answer, sizeof(answer));
+            return true;
+        }
+    }
+    return false;
+}
+
+bool oled_api_receive(uint8_t *data, uint8_t length) {
+#ifdef OLED_API_REPORT_PREFIX
+    if (length < 1 || data[0] != OLED_API_REPORT_PREFIX) {
+        return false;
+    }
+    data++;
+    length--;
+#endif
+
+    if (!oled_api_handle(data, length)) {
+        return false;
+    }
+    last_host_report = timer_read32();
+    host_seen = true;
+    return true;
+}
+
+bool oled_api_host_connected(void) {
+    return host_seen && timer_elapsed32(last_host_report) < OLED_API_HOST_TIMEOUT;
+}
+
+void oled_api_render(void) {
+#ifdef OLED_API_QUANTUM_PAINTER
+    if (painter_device) {
+        qp_flush(painter_device);
+    }
+#else
+    oled_write_raw((const char *)current_screen, sizeof(current_screen));
+#endif
+}
+
+void oled_api_send_event(uint8_t type, uint8_t arg1, uint8_t arg2) {
+    const uint8_t args[] = {type, arg1, arg2};
+    oled_api_send(OLED_API_EVENT_REPORT, args, sizeof(args));
+}
@@ -0,0 +1,79 @@
+// Firmware side of qmk-oled-api: receives frames and control commands from the host over raw
+// HID, and sends keyboard events back to it.
+#pragma once
+
+#include <stdbool.h>
+#include <stdint.h>
+
+// The version of the protocol this module speaks. The host checks the major version during its
+// handshake, so bump it whenever the framing changes incompatibly
+#define OLED_API_PROTOCOL_MAJOR 1
+#define OLED_API_PROTOCOL_MINOR 3
+
+// Define OLED_API_NO_EVENTS if the keymap never calls oled_api_send_event, so that the host knows
+// not to wait for events
+
+// Define OLED_API_REPORT_PREFIX (e.g. as 0x6F, the host's VIA_REPORT_PREFIX) to share the raw HID
+// interface with VIA or Vial. Every report then starts with the prefix, matching
+// OledScreen::with_report_prefix, and oled_api_receive should be called from raw_hid_receive_kb
+
+// OLED_API_QUANTUM_PAINTER is defined (by setting it to yes in rules.mk) to draw on a display
+// driven by Quantum Painter instead of the OLED driver. Pass the display to
+// oled_api_set_painter_device once it has been set up. The
+// host then sends frames in colour (FrameProtocol::QuantumPainter, or a ColorScreen or GrayScreen),
+// which are drawn as they arrive
+#ifdef OLED_API_QUANTUM_PAINTER
+#    include "qp.h"
+#endif
+
+#ifndef OLED_API_WIDTH
+#    define OLED_API_WIDTH 32
+#endif
+#ifndef OLED_API_HEIGHT
+#    define OLED_API_HEIGHT 128
+#endif
+
+// The rotation returned from oled_init_user, reported to the host along with the size. The size
+// is that of the frames after rotating, e.g. 32x128 for a 128x32 screen rotated by 90 degrees
+#ifndef OLED_API_ROTATION
+#    ifdef OLED_API_QUANTUM_PAINTER
+#        define OLED_API_ROTATION QP_ROTATION_0
+#    else
+#        define OLED_API_ROTATION OLED_ROTATION_0
+#    endif
+#endif
+
+// How long to wait without hearing from the host before deciding it has gone, in milliseconds.
+// Matches the host's HEARTBEAT_TIMEOUT, which it sends heartbeats well within
+#ifndef OLED_API_HOST_TIMEOUT
+#    define OLED_API_HOST_TIMEOUT 3000
+#endif
+
+// Handle a report from raw_hid_receive. Returns false if the report isn't part of the protocol,
+// so that the keymap can handle it instead
+bool oled_api_receive(uint8_t *data, uint8_t length);
+
+// Draw the most recently received frame, call this from oled_task_user. With
+// OLED_API_QUANTUM_PAINTER frames are drawn as they arrive, and this flushes them to the display
+void oled_api_render(void);
+
+#ifdef OLED_API_QUANTUM_PAINTER
+// The display to draw frames on, e.g. the one returned by qp_st7789_make_spi_device
+void oled_api_set_painter_device(painter_device_t device);
+#endif
+
+// Whether the host has sent anything within OLED_API_HOST_TIMEOUT. When it hasn't, the last frame
+// it sent is stale and the keymap should draw its own screen instead
+bool oled_api_host_connected(void);
+
+// Send an event to the host, e.g. oled_api_send_event(OLED_API_KEYCODE_EVENT, keycode >> 8,
+// keycode & 0xFF) for a custom keycode
+void oled_api_send_event(uint8_t type, uint8_t arg1, uint8_t arg2);
+
+enum oled_api_event {
+    OLED_API_ENCODER_EVENT = 1,
+    OLED_API_KEYCODE_EVENT = 2,
+    OLED_API_KEYPRESS_EVENT = 3,
+    OLED_API_LAYER_EVENT = 4,
+    OLED_API_LOCK_EVENT = 5,
+};
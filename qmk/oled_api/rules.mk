@@ -0,0 +1,12 @@
+RAW_ENABLE = yes
+
+# Set OLED_API_QUANTUM_PAINTER = yes before including this file to draw on a Quantum Painter display
+# instead of an OLED. The display's driver still needs adding to QUANTUM_PAINTER_DRIVERS
+ifeq ($(strip $(OLED_API_QUANTUM_PAINTER)), yes)
+    QUANTUM_PAINTER_ENABLE = yes
+    OPT_DEFS += -DOLED_API_QUANTUM_PAINTER
+else
+    OLED_ENABLE = yes
+endif
+
+SRC += oled_api/oled_api.c
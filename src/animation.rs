@@ -0,0 +1,334 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+use hidapi::HidError;
+use image::{AnimationDecoder, DynamicImage, Frame, ImageError};
+
+use crate::screen::{prepare_images, Binarization, DitheredImage, ImageSizing, OledScreen};
+
+/// Frames with a delay this short are played at `DEFAULT_DELAY` instead. Browsers do the same, so
+/// plenty of gifs claim a delay of 0 and rely on it
+const MIN_DELAY: Duration = Duration::from_millis(10);
+const DEFAULT_DELAY: Duration = Duration::from_millis(100);
+
+/// Plays an animated image, such as a gif or apng, on the screen.
+///
+/// Every frame is resized and dithered up front so that playback only has to draw and send them.
+/// Which frame is shown is worked out from how long the animation has been playing rather than
+/// by stepping through them one by one, so if sending a frame takes longer than its delay the
+/// frames that should have been shown in the meantime are skipped and the animation keeps its
+/// speed. To drive the animation from your own loop instead, move it on with `advance` by however
+/// long has passed since the last frame and `draw` it:
+///
+/// ```no_run
+/// use std::ffi::CString;
+/// use std::fs::File;
+/// use image::codecs::gif::GifDecoder;
+/// use qmk_oled_api::animation::AnimationPlayer;
+/// use qmk_oled_api::screen::{Binarization, ImageSizing, OledScreen};
+///
+/// let path = CString::new("/dev/hidraw0").unwrap();
+/// let mut screen = OledScreen::from_path(&path, 32, 128).unwrap();
+///
+/// let decoder = GifDecoder::new(File::open("examples/rick.gif").unwrap()).unwrap();
+/// let (width, height) = (screen.width() as u32, screen.height() as u32);
+/// let sizing = ImageSizing::Cover;
+/// let player = AnimationPlayer::from_decoder(decoder, width, height, &sizing, Binarization::Dither)
+///     .unwrap()
+///     .with_loop(true);
+/// player.play(&mut screen).unwrap();
+/// ```
+pub struct AnimationPlayer {
+    frames: Vec<DitheredImage>,
+    /// When each frame stops being shown, relative to the start of the animation
+    ends: Vec<Duration>,
+    x: usize,
+    y: usize,
+    looping: bool,
+    /// How far into the animation `advance` has got
+    playhead: Duration,
+}
+
+impl AnimationPlayer {
+    /// Prepare already decoded frames to be played on a screen (or area of one) of the given size,
+    /// using the delays stored with them
+    pub fn new(
+        frames: Vec<Frame>,
+        width: u32,
+        height: u32,
+        sizing: &ImageSizing,
+        binarization: Binarization,
+    ) -> Self {
+        let delays: Vec<_> = frames
+            .iter()
+            .map(|frame| match Duration::from(frame.delay()) {
+                delay if delay <= MIN_DELAY => DEFAULT_DELAY,
+                delay => delay,
+            })
+            .collect();
+        let images = frames
+            .into_iter()
+            .map(|frame| DynamicImage::ImageRgba8(frame.into_buffer()))
+            .collect();
+
+        Self {
+            frames: prepare_images(images, width, height, sizing, binarization),
+            ends: ends(delays),
+            x: 0,
+            y: 0,
+            looping: false,
+            playhead: Duration::ZERO,
+        }
+    }
+
+    /// Decode every frame of an animation and prepare them to be played on a screen of the given
+    /// size. For an apng, pass `PngDecoder::apng()`
+    pub fn from_decoder<'a>(
+        decoder: impl AnimationDecoder<'a>,
+        width: u32,
+        height: u32,
+        sizing: &ImageSizing,
+        binarization: Binarization,
+    ) -> Result<Self, ImageError> {
+        let frames = decoder.into_frames().collect_frames()?;
+        Ok(Self::new(frames, width, height, sizing, binarization))
+    }
+
+    /// Show every frame for the same amount of time, ignoring the delays stored in the file
+    pub fn with_frame_time(mut self, frame_time: Duration) -> Self {
+        self.ends = ends(vec![frame_time; self.frames.len()]);
+        self
+    }
+
+    /// Draw the frames with their bottom left corner at the given position rather than at 0, 0
+    pub fn with_position(mut self, x: usize, y: usize) -> Self {
+        self.x = x;
+        self.y = y;
+        self
+    }
+
+    /// Start again from the first frame after the last one, instead of stopping
+    pub fn with_loop(mut self, looping: bool) -> Self {
+        self.looping = looping;
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// How long it takes to play every frame once
+    pub fn duration(&self) -> Duration {
+        self.ends.last().copied().unwrap_or_default()
+    }
+
+    /// How long the animation has been advanced by since it was created or rewound, wrapping
+    /// around to 0 each time a looping animation restarts
+    pub fn playhead(&self) -> Duration {
+        self.playhead
+    }
+
+    /// Move the animation on by `delta`, the time since it was last advanced. Frames which would
+    /// have been shown in between are skipped. Returns whether the animation is still playing
+    pub fn advance(&mut self, delta: Duration) -> bool {
+        self.playhead += delta;
+
+        let duration = self.duration();
+        if self.looping && !duration.is_zero() {
+            self.playhead =
+                Duration::from_nanos((self.playhead.as_nanos() % duration.as_nanos()) as u64);
+        }
+        self.frame_at(self.playhead).is_some()
+    }
+
+    /// Go back to the first frame
+    pub fn rewind(&mut self) {
+        self.playhead = Duration::ZERO;
+    }
+
+    /// Draw the frame at the playhead, see `draw_at`
+    pub fn draw(&self, screen: &mut OledScreen) -> bool {
+        self.draw_at(screen, self.playhead)
+    }
+
+    /// The index of the frame which should be on display once the animation has been playing for
+    /// `elapsed`. `None` once a non-looping animation has finished
+    pub fn frame_at(&self, elapsed: Duration) -> Option<usize> {
+        self.position(elapsed).map(|(index, _)| index)
+    }
+
+    /// Draw the frame which should be on display once the animation has been playing for
+    /// `elapsed`. Returns whether the animation is still playing, leaving the screen untouched if
+    /// it isn't
+    pub fn draw_at(&self, screen: &mut OledScreen, elapsed: Duration) -> bool {
+        match self.frame_at(elapsed) {
+            Some(index) => {
+                self.draw_frame(screen, index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Play the animation on the screen, sleeping between frames. Returns once the last frame
+    /// has been shown for its delay, or never if the animation is looping
+    pub fn play(&self, screen: &mut OledScreen) -> Result<(), HidError> {
+        let started = Instant::now();
+        let mut shown = None;
+
+        loop {
+            let elapsed = started.elapsed();
+            let Some((index, remaining)) = self.position(elapsed) else {
+                return Ok(());
+            };
+
+            if shown != Some(index) {
+                self.draw_frame(screen, index);
+                screen.send()?;
+                shown = Some(index);
+            }
+
+            // Sending took some of the frame's time, so only sleep for what's left of it
+            thread::sleep((elapsed + remaining).saturating_sub(started.elapsed()));
+        }
+    }
+
+    fn draw_frame(&self, screen: &mut OledScreen, index: usize) {
+        screen.clear();
+        screen.draw_dithered_image(&self.frames[index], self.x, self.y);
+    }
+
+    /// The frame on display after `elapsed`, along with how much longer it should stay there
+    fn position(&self, elapsed: Duration) -> Option<(usize, Duration)> {
+        let duration = self.duration();
+        if duration.is_zero() {
+            return None;
+        }
+
+        let into_loop = match self.looping {
+            true => Duration::from_nanos((elapsed.as_nanos() % duration.as_nanos()) as u64),
+            false if elapsed < duration => elapsed,
+            false => return None,
+        };
+
+        let index = self.ends.partition_point(|end| *end <= into_loop);
+        Some((index, self.ends[index] - into_loop))
+    }
+}
+
+/// The running total of the delays, i.e. when each frame ends
+fn ends(delays: Vec<Duration>) -> Vec<Duration> {
+    delays
+        .into_iter()
+        .scan(Duration::ZERO, |end, delay| {
+            *end += delay;
+            Some(*end)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockDevice;
+    use image::{Delay, RgbaImage};
+
+    fn player(delays_ms: &[u32]) -> AnimationPlayer {
+        let frames = delays_ms
+            .iter()
+            .enumerate()
+            .map(|(index, delay)| {
+                // Each frame lights a different pixel so they can be told apart
+                let mut image = RgbaImage::new(4, 4);
+                image.put_pixel(index as u32, 0, image::Rgba([255, 255, 255, 255]));
+                Frame::from_parts(image, 0, 0, Delay::from_numer_denom_ms(*delay, 1))
+            })
+            .collect();
+
+        AnimationPlayer::new(
+            frames,
+            32,
+            128,
+            &ImageSizing::Original,
+            Binarization::Threshold(128),
+        )
+    }
+
+    #[test]
+    fn test_frames_fit_the_screen() {
+        let frame = Frame::new(RgbaImage::new(64, 64));
+        let player = AnimationPlayer::new(
+            vec![frame],
+            128,
+            32,
+            &ImageSizing::Cover,
+            Binarization::Dither,
+        );
+        assert_eq!(
+            (player.frames[0].width(), player.frames[0].height()),
+            (128, 32)
+        );
+    }
+
+    #[test]
+    fn test_frame_at() {
+        let player = player(&[100, 50, 200]);
+        let at = |ms| player.frame_at(Duration::from_millis(ms));
+
+        assert_eq!(player.duration(), Duration::from_millis(350));
+        assert_eq!(
+            (at(0), at(99), at(100), at(149), at(150)),
+            (Some(0), Some(0), Some(1), Some(1), Some(2))
+        );
+        assert_eq!(at(350), None);
+
+        let player = player.with_loop(true);
+        assert_eq!(player.frame_at(Duration::from_millis(1140)), Some(0));
+        assert_eq!(player.frame_at(Duration::from_millis(1170)), Some(1));
+    }
+
+    #[test]
+    fn test_frame_time_and_short_delays() {
+        let player = player(&[0, 50]);
+        assert_eq!(player.duration(), DEFAULT_DELAY + Duration::from_millis(50));
+
+        let player = player.with_frame_time(Duration::from_millis(20));
+        assert_eq!(player.duration(), Duration::from_millis(40));
+        assert_eq!(player.frame_at(Duration::from_millis(25)), Some(1));
+    }
+
+    #[test]
+    fn test_advance() {
+        let mut player = player(&[100, 100]);
+        let mut screen = OledScreen::from_device(MockDevice::new(), 8, 8).unwrap();
+
+        // A slow frame skips straight past the first frame rather than stretching it out
+        assert!(player.advance(Duration::from_millis(30)));
+        assert!(player.advance(Duration::from_millis(150)));
+        assert!(player.draw(&mut screen));
+        assert!(screen.get_pixel(1, 4));
+        assert!(!player.advance(Duration::from_millis(20)));
+
+        let mut player = player.with_loop(true);
+        player.rewind();
+        assert!(player.advance(Duration::from_millis(450)));
+        assert_eq!(player.playhead(), Duration::from_millis(50));
+    }
+
+    #[test]
+    fn test_draw_at() {
+        let player = player(&[100, 100]);
+        let mut screen = OledScreen::from_device(MockDevice::new(), 8, 8).unwrap();
+
+        assert!(player.draw_at(&mut screen, Duration::from_millis(150)));
+        assert!(!screen.get_pixel(0, 4));
+        assert!(screen.get_pixel(1, 4));
+
+        assert!(!player.draw_at(&mut screen, Duration::from_millis(200)));
+        assert!(screen.get_pixel(1, 4));
+    }
+}
@@ -0,0 +1,125 @@
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+
+/// Options which don't take a value
+const FLAGS: &[&str] = &[
+    "autodetect",
+    "events",
+    "help",
+    "loop",
+    "no-preview",
+    "quantum-painter",
+    "simulator",
+    "via",
+    "write-raw",
+];
+
+/// Command line arguments split into a subcommand, positional arguments and `--options`
+#[derive(Debug, Default)]
+pub struct Args {
+    pub command: Option<String>,
+    pub positional: Vec<String>,
+    options: HashMap<String, String>,
+    flags: HashSet<String>,
+}
+
+impl Args {
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut parsed = Args::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            match arg.strip_prefix("--") {
+                Some(name) if FLAGS.contains(&name) => {
+                    parsed.flags.insert(name.to_string());
+                }
+                Some(name) => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| format!("Missing value for --{name}"))?;
+                    parsed.options.insert(name.to_string(), value);
+                }
+                None if parsed.command.is_none() => parsed.command = Some(arg),
+                None => parsed.positional.push(arg),
+            }
+        }
+
+        Ok(parsed)
+    }
+
+    pub fn flag(&self, name: &str) -> bool {
+        self.flags.contains(name)
+    }
+
+    /// The raw value of an option, if it was given
+    pub fn raw_option(&self, name: &str) -> Option<&str> {
+        self.options.get(name).map(String::as_str)
+    }
+
+    /// Parse the value of an option, falling back to a default if it wasn't given
+    pub fn option<T: FromStr>(&self, name: &str, default: T) -> Result<T, String> {
+        Ok(self.optional(name)?.unwrap_or(default))
+    }
+
+    /// Parse the value of an option, if it was given
+    pub fn optional<T: FromStr>(&self, name: &str) -> Result<Option<T>, String> {
+        self.raw_option(name)
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(|_| format!("Invalid value for --{name}: {value}"))
+            })
+            .transpose()
+    }
+
+    /// The positional argument at the given index, or an error naming what was expected
+    pub fn positional(&self, index: usize, name: &str) -> Result<&str, String> {
+        self.positional
+            .get(index)
+            .map(String::as_str)
+            .ok_or_else(|| format!("Missing required argument <{name}>"))
+    }
+}
+
+/// Parse a USB ID given in decimal or as hex with a `0x` prefix
+pub fn parse_id(value: &str) -> Result<u16, String> {
+    let parsed = match value.strip_prefix("0x") {
+        Some(hex) => u16::from_str_radix(hex, 16),
+        None => value.parse(),
+    };
+    parsed.map_err(|_| format!("Invalid USB ID: {value}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        Args::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let args = parse(&["text", "--size", "12", "Hello world", "--help"]).unwrap();
+
+        assert_eq!(args.command.as_deref(), Some("text"));
+        assert_eq!(args.positional(0, "text"), Ok("Hello world"));
+        assert_eq!(args.option("size", 8.0), Ok(12.0));
+        assert_eq!(args.option("x", 0), Ok(0));
+        assert!(args.flag("help"));
+
+        assert!(args.positional(1, "other").is_err());
+        assert!(parse(&["text", "--size"]).is_err());
+        assert!(parse(&["text", "--size", "big"])
+            .unwrap()
+            .option("size", 8.0)
+            .is_err());
+    }
+
+    #[test]
+    fn test_parse_id() {
+        assert_eq!(parse_id("0xFEED"), Ok(0xFEED));
+        assert_eq!(parse_id("4660"), Ok(0x1234));
+        assert!(parse_id("0xZZ").is_err());
+    }
+}
@@ -0,0 +1,448 @@
+mod args;
+mod stream;
+mod test_pattern;
+
+use std::env;
+use std::error::Error;
+use std::ffi::CString;
+use std::fs::{self, File};
+use std::io::{self, BufReader, Write};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use hidapi::HidApi;
+use image::codecs::gif::GifDecoder;
+use qmk_oled_api::animation::AnimationPlayer;
+use qmk_oled_api::codegen::Receiver;
+use qmk_oled_api::color::Rgb565;
+use qmk_oled_api::command::Command;
+use qmk_oled_api::data::VIA_REPORT_PREFIX;
+use qmk_oled_api::font_subset::FontSubset;
+use qmk_oled_api::handshake::Rotation;
+use qmk_oled_api::metrics::{Metrics, MetricsServer};
+use qmk_oled_api::recording::{read_capture, replay};
+use qmk_oled_api::screen::{
+    prepare_image_for, Binarization, BufferLayout, FrameProtocol, ImageSizing, OledScreen,
+    TextStyle,
+};
+use qmk_oled_api::simulator::TerminalSimulator;
+
+use args::{parse_id, Args};
+use stream::{read_frame, Format};
+
+/// The usage page QMK uses for its raw HID interface
+const QMK_RAW_USAGE_PAGE: u16 = 0xFF60;
+
+/// How long `--autodetect` waits for the firmware to report the size of the screen
+const AUTODETECT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How long each step of the test pattern pixel walk is shown for
+const PIXEL_WALK_STEP: Duration = Duration::from_millis(20);
+
+const REPL_HELP: &str = "Commands:
+  text <x> <y> <size> <text>
+  line <x1> <y1> <x2> <y2>
+  rect <min_x> <min_y> <max_x> <max_y> [filled]
+  img <x> <y> <contain|cover|original|stretch> <path>
+  clear
+  fill
+  send
+  help
+  quit";
+
+const USAGE: &str = "Draw to the OLED screen of a QMK keyboard
+
+Usage: qmk-oled <command> [arguments] [options]
+
+Commands:
+  list-devices          List connected HID devices
+  info                  Show which device the screen is drawn on
+  codegen               Print the QMK keymap code for receiving frames (--events, --via,
+                        --rotation 0|90|180|270)
+  subset-font <font>    Print a TTF or BDF font cut down to some characters as a Rust byte
+                        array (--chars, --size, --name)
+  clear                 Turn every pixel off
+  fill                  Turn every pixel on
+  text <text>           Draw text (--size, --x, --y, --font)
+  image <path>          Draw an image (--sizing contain|cover|original|stretch, --x, --y,
+                        --binarize dither|otsu|<threshold>)
+  gif <path>            Play a gif (--sizing, --binarize, --fps, --loop)
+  stream                Send frames read from stdin (--format pbm|raw)
+  replay <capture>      Play back a capture made with RecordingAdapter (--speed)
+  repl                  Draw interactively, previewing each change (--no-preview)
+  test-pattern          Cycle through patterns to check the wiring (--delay <ms>, --loop)
+
+Device options:
+  --device-path <path>  Path of the device to open (defaults to the DEVICE_PATH env var)
+  --vid <id>            Vendor ID of the device to open, used with --pid
+  --pid <id>            Product ID of the device to open, used with --vid
+  --usage-page <id>     Usage page of the raw HID interface [default: 0xFF60]
+  --simulator           Draw to the terminal instead of a device
+  --via                 Share the raw HID interface with VIA or Vial
+  --write-raw           Send frames in the layout oled_write_raw expects
+  --quantum-painter     Send frames in colour, for a display driven by Quantum Painter
+  --width <pixels>      Width of the screen [default: 32]
+  --height <pixels>     Height of the screen [default: 128]
+  --autodetect          Ask the firmware for the size of the screen instead
+  --metrics <address>   Serve frame and bandwidth metrics for Prometheus at the address (e.g.
+                        127.0.0.1:9185), under /metrics";
+
+fn main() {
+    let result = Args::parse(env::args().skip(1))
+        .map_err(|error| error.into())
+        .and_then(run);
+
+    if let Err(error) = result {
+        eprintln!("error: {error}");
+        std::process::exit(1);
+    }
+}
+
+fn run(args: Args) -> Result<(), Box<dyn Error>> {
+    let command = match args.command.as_deref() {
+        Some(command) if !args.flag("help") => command,
+        _ => {
+            println!("{USAGE}");
+            return Ok(());
+        }
+    };
+
+    match command {
+        "list-devices" => list_devices(),
+        "codegen" => {
+            let mut receiver =
+                Receiver::new(args.option("width", 32)?, args.option("height", 128)?)
+                    .with_events(args.flag("events"))
+                    .with_rotation(parse_rotation(&args)?);
+            if args.flag("via") {
+                receiver = receiver.with_report_prefix(VIA_REPORT_PREFIX);
+            }
+            print!("{}", receiver.generate());
+            Ok(())
+        }
+        "subset-font" => subset_font(&args),
+        "info" => {
+            println!("{}", open_screen(&args)?.device_info());
+            Ok(())
+        }
+        "clear" => {
+            let mut screen = open_screen(&args)?;
+            screen.clear();
+            Ok(screen.send()?)
+        }
+        "fill" => {
+            let mut screen = open_screen(&args)?;
+            screen.fill_all();
+            Ok(screen.send()?)
+        }
+        "text" => {
+            let text = args.positional(0, "text")?;
+            let mut screen = open_screen(&args)?;
+            screen.draw_text(
+                text,
+                args.option("x", 0)?,
+                args.option("y", 0)?,
+                &TextStyle::new(args.option("size", 8.0)?).with_font(args.raw_option("font")),
+            )?;
+            Ok(screen.send()?)
+        }
+        "image" => {
+            let image = image::open(args.positional(0, "path")?)?;
+            let mut screen = open_screen(&args)?;
+            let image = prepare_image_for(
+                image,
+                screen.width() as u32,
+                screen.height() as u32,
+                &parse_sizing(&args)?,
+                parse_binarization(&args)?,
+            );
+            screen.draw_dithered_image(&image, args.option("x", 0)?, args.option("y", 0)?);
+            Ok(screen.send()?)
+        }
+        "gif" => play_gif(&args),
+        "stream" => stream_frames(&args),
+        "replay" => {
+            let file = File::open(args.positional(0, "capture")?)?;
+            let writes = read_capture(BufReader::new(file))?;
+            let speed = args.option("speed", 1.0_f32)?;
+            if speed <= 0.0 {
+                return Err("--speed must be greater than 0".into());
+            }
+            let screen = open_screen(&args)?;
+            Ok(replay(&writes, screen.device(), speed)?)
+        }
+        "repl" => repl(&args),
+        "test-pattern" => show_test_patterns(&args),
+        other => Err(format!("Unknown command: {other}, see --help").into()),
+    }
+}
+
+fn parse_sizing(args: &Args) -> Result<ImageSizing, String> {
+    match args.raw_option("sizing").unwrap_or("contain") {
+        "contain" => Ok(ImageSizing::Contain),
+        "cover" => Ok(ImageSizing::Cover),
+        "original" => Ok(ImageSizing::Original),
+        "stretch" => Ok(ImageSizing::Stretch),
+        other => Err(format!("Unknown sizing: {other}")),
+    }
+}
+
+fn parse_rotation(args: &Args) -> Result<Rotation, String> {
+    match args.raw_option("rotation").unwrap_or("0") {
+        "0" => Ok(Rotation::R0),
+        "90" => Ok(Rotation::R90),
+        "180" => Ok(Rotation::R180),
+        "270" => Ok(Rotation::R270),
+        other => Err(format!("Unknown rotation: {other}")),
+    }
+}
+
+fn parse_binarization(args: &Args) -> Result<Binarization, String> {
+    match args.raw_option("binarize").unwrap_or("dither") {
+        "dither" => Ok(Binarization::Dither),
+        "otsu" => Ok(Binarization::Otsu),
+        other => other
+            .parse()
+            .map(Binarization::Threshold)
+            .map_err(|_| format!("Unknown binarization: {other}")),
+    }
+}
+
+/// Play a gif, using the delays stored in the file unless a frame rate is given
+fn play_gif(args: &Args) -> Result<(), Box<dyn Error>> {
+    let file = File::open(args.positional(0, "path")?)?;
+    let decoder = GifDecoder::new(file)?;
+    let mut screen = open_screen(args)?;
+    let mut player = AnimationPlayer::from_decoder(
+        decoder,
+        screen.width() as u32,
+        screen.height() as u32,
+        &parse_sizing(args)?,
+        parse_binarization(args)?,
+    )?
+    .with_loop(args.flag("loop"));
+    match args.optional::<f32>("fps")? {
+        Some(fps) if fps > 0.0 => {
+            player = player.with_frame_time(Duration::from_secs_f32(1.0 / fps))
+        }
+        Some(_) => return Err("--fps must be greater than 0".into()),
+        None => (),
+    };
+
+    player.play(&mut screen)?;
+    Ok(())
+}
+
+/// Send every frame read from stdin to the screen. White pixels are drawn as enabled pixels, with
+/// the first row of a frame being the top of the screen.
+fn stream_frames(args: &Args) -> Result<(), Box<dyn Error>> {
+    let format: Format = args.option("format", Format::Pbm)?;
+    let mut screen = open_screen(args)?;
+    let (width, height) = (screen.width(), screen.height());
+    let mut stdin = io::stdin().lock();
+
+    while let Some(frame) = read_frame(&mut stdin, format, width, height)? {
+        screen.clear();
+        for y in 0..frame.height.min(height) {
+            for x in 0..frame.width.min(width) {
+                screen.set_pixel(x, height - 1 - y, frame.get(x, y));
+            }
+        }
+        screen.send()?;
+    }
+    Ok(())
+}
+
+/// Read drawing commands from stdin, sending the screen after each one so that changes show up
+/// straight away
+fn repl(args: &Args) -> Result<(), Box<dyn Error>> {
+    let mut screen = open_screen(args)?;
+    let stdin = io::stdin();
+    let mut stdout = io::stdout();
+
+    println!("Type help for a list of commands");
+    loop {
+        print!("> ");
+        stdout.flush()?;
+
+        let mut line = String::new();
+        if stdin.read_line(&mut line)? == 0 {
+            println!();
+            return Ok(());
+        }
+
+        let result = match line.trim() {
+            "" => continue,
+            "quit" | "exit" => return Ok(()),
+            "help" => {
+                println!("{REPL_HELP}");
+                continue;
+            }
+            line => Command::parse(line)
+                .and_then(|command| command.apply(&mut screen))
+                .and_then(|()| screen.send().map_err(|error| error.to_string())),
+        };
+
+        match result {
+            Ok(()) if !args.flag("no-preview") => println!("{}", screen.to_braille()),
+            Ok(()) => (),
+            Err(error) => println!("error: {error}"),
+        }
+    }
+}
+
+/// Show each test pattern for the given delay, followed by a pixel walk around the edge
+fn show_test_patterns(args: &Args) -> Result<(), Box<dyn Error>> {
+    let delay = Duration::from_millis(args.option("delay", 2000)?);
+    let mut screen = open_screen(args)?;
+    let (width, height) = (screen.width(), screen.height());
+
+    loop {
+        for (name, pattern) in test_pattern::PATTERNS {
+            println!("{name}");
+            for x in 0..width {
+                for y in 0..height {
+                    screen.set_pixel(x, y, pattern(x, y, width, height));
+                }
+            }
+            screen.send()?;
+            thread::sleep(delay);
+        }
+
+        println!("pixel walk, starting at (0, 0)");
+        screen.clear();
+        let mut walk = test_pattern::pixel_walk(width, height).peekable();
+        let started = Instant::now();
+        let mut due = Duration::ZERO;
+        while walk.peek().is_some() {
+            // Light every pixel whose step has come around, so a slow send skips ahead instead
+            // of slowing the walk down
+            while due <= started.elapsed() {
+                match walk.next() {
+                    Some((x, y)) => screen.set_pixel(x, y, true),
+                    None => break,
+                }
+                due += PIXEL_WALK_STEP;
+            }
+            screen.send()?;
+            thread::sleep(due.saturating_sub(started.elapsed()));
+        }
+        thread::sleep(delay);
+
+        if !args.flag("loop") {
+            return Ok(());
+        }
+    }
+}
+
+fn list_devices() -> Result<(), Box<dyn Error>> {
+    let api = HidApi::new()?;
+
+    for device in api.device_list() {
+        println!(
+            "{}  {:04x}:{:04x}  usage page {:#06x}  {} {}",
+            device.path().to_string_lossy(),
+            device.vendor_id(),
+            device.product_id(),
+            device.usage_page(),
+            device.manufacturer_string().unwrap_or_default(),
+            device.product_string().unwrap_or_default(),
+        );
+    }
+    Ok(())
+}
+
+/// Print the characters given by --chars (printable ASCII by default) from a font as a Rust
+/// byte array, see `FontSubset`
+fn subset_font(args: &Args) -> Result<(), Box<dyn Error>> {
+    let path = args.positional(0, "font")?;
+    let ascii: String = (' '..='~').collect();
+    let characters = args.raw_option("chars").unwrap_or(&ascii);
+
+    let subset = if path.to_ascii_lowercase().ends_with(".bdf") {
+        FontSubset::from_bdf(&fs::read_to_string(path)?, characters)?
+    } else {
+        FontSubset::from_ttf(&fs::read(path)?, args.option("size", 8.0)?, characters)?
+    };
+    print!(
+        "{}",
+        subset.to_rust_array(args.raw_option("name").unwrap_or("FONT"))
+    );
+    Ok(())
+}
+
+/// Open the screen described by the device options
+fn open_screen(args: &Args) -> Result<OledScreen, Box<dyn Error>> {
+    let mut screen = open_device(args)?.with_layout(layout(args));
+    if args.flag("via") {
+        screen = screen.with_report_prefix(VIA_REPORT_PREFIX);
+    }
+    if args.flag("quantum-painter") {
+        screen = screen.with_protocol(FrameProtocol::QuantumPainter {
+            foreground: Rgb565::WHITE,
+            background: Rgb565::BLACK,
+        });
+    }
+    if args.flag("autodetect") {
+        screen.detect_size(AUTODETECT_TIMEOUT)?;
+    }
+    if let Some(address) = args.raw_option("metrics") {
+        let metrics = Metrics::new();
+        let server = MetricsServer::bind(address)?;
+        let served = metrics.clone();
+        thread::spawn(move || server.serve(&served));
+        screen = screen.with_metrics(&metrics);
+    }
+    Ok(screen)
+}
+
+fn layout(args: &Args) -> BufferLayout {
+    if args.flag("write-raw") {
+        BufferLayout::OledWriteRaw
+    } else {
+        BufferLayout::Columns
+    }
+}
+
+fn open_device(args: &Args) -> Result<OledScreen, Box<dyn Error>> {
+    let width = args.option("width", 32)?;
+    let height = args.option("height", 128)?;
+
+    if args.flag("simulator") {
+        if args.flag("quantum-painter") {
+            return Err("--simulator only shows OLED frames, not --quantum-painter ones".into());
+        }
+        let mut simulator = TerminalSimulator::new(width, height).with_layout(layout(args));
+        if args.flag("via") {
+            simulator = simulator.with_report_prefix(VIA_REPORT_PREFIX);
+        }
+        return Ok(OledScreen::from_device(simulator, width, height)?);
+    }
+
+    let device_path = args
+        .raw_option("device-path")
+        .map(str::to_string)
+        .or_else(|| env::var("DEVICE_PATH").ok());
+
+    match (device_path, args.raw_option("vid"), args.raw_option("pid")) {
+        (_, Some(vid), Some(pid)) => {
+            let usage_page = match args.raw_option("usage-page") {
+                Some(usage_page) => parse_id(usage_page)?,
+                None => QMK_RAW_USAGE_PAGE,
+            };
+            Ok(OledScreen::from_id(
+                parse_id(vid)?,
+                parse_id(pid)?,
+                usage_page,
+                width,
+                height,
+            )?)
+        }
+        (Some(device_path), _, _) => Ok(OledScreen::from_path(
+            &CString::new(device_path)?,
+            width,
+            height,
+        )?),
+        _ => Err("No device given, use --device-path or --vid and --pid".into()),
+    }
+}
@@ -0,0 +1,199 @@
+use std::io::{self, BufRead, ErrorKind, Read};
+use std::str::FromStr;
+
+/// The encoding of frames read by the `stream` command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// Binary PBM (`P4`) images, one after another. As with any other image, white pixels are
+    /// lit, so set (black) bits are drawn as unlit pixels
+    Pbm,
+    /// Headerless 1-bit bitmaps the size of the screen, as produced by ffmpeg's `monob` pixel
+    /// format
+    Raw,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format {
+            "pbm" => Ok(Format::Pbm),
+            "raw" => Ok(Format::Raw),
+            other => Err(format!("Unknown format: {other}")),
+        }
+    }
+}
+
+/// A 1-bit image stored row by row from the top, with each row padded to a whole byte, the
+/// most significant bit being the leftmost pixel and set bits being lit pixels
+#[derive(Debug, PartialEq, Eq)]
+pub struct Frame {
+    pub width: usize,
+    pub height: usize,
+    data: Vec<u8>,
+}
+
+impl Frame {
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        let byte = self.data[y * self.width.div_ceil(8) + x / 8];
+        byte & (0x80 >> (x % 8)) != 0
+    }
+}
+
+/// Read the next frame from a reader, returning `None` once the input has run out. Raw frames
+/// always have the given dimensions whereas PBM frames carry their own, which can be no larger
+/// than the given ones.
+pub fn read_frame(
+    reader: &mut impl BufRead,
+    format: Format,
+    width: usize,
+    height: usize,
+) -> io::Result<Option<Frame>> {
+    let (width, height) = match format {
+        Format::Raw => {
+            if reader.fill_buf()?.is_empty() {
+                return Ok(None);
+            }
+            (width, height)
+        }
+        Format::Pbm => match read_token(reader)? {
+            None => return Ok(None),
+            Some(magic) if magic == "P4" => {
+                let (pbm_width, pbm_height) = (read_dimension(reader)?, read_dimension(reader)?);
+                // Checked before allocating, so a bad header can't ask for any amount of memory
+                if pbm_width > width || pbm_height > height {
+                    return Err(io::Error::new(
+                        ErrorKind::InvalidData,
+                        format!(
+                            "The PBM image is {pbm_width}x{pbm_height}, which is larger than the \
+                             {width}x{height} screen"
+                        ),
+                    ));
+                }
+                (pbm_width, pbm_height)
+            }
+            Some(magic) => {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidData,
+                    format!("Expected a binary PBM (P4) image, found {magic:?}"),
+                ))
+            }
+        },
+    };
+
+    let mut data = vec![0; width.div_ceil(8) * height];
+    reader.read_exact(&mut data)?;
+    if format == Format::Pbm {
+        data.iter_mut().for_each(|byte| *byte = !*byte);
+    }
+
+    Ok(Some(Frame {
+        width,
+        height,
+        data,
+    }))
+}
+
+fn read_dimension(reader: &mut impl BufRead) -> io::Result<usize> {
+    let token = read_token(reader)?.ok_or_else(|| io::Error::from(ErrorKind::UnexpectedEof))?;
+    token.parse().map_err(|_| {
+        io::Error::new(
+            ErrorKind::InvalidData,
+            format!("Invalid PBM dimension: {token:?}"),
+        )
+    })
+}
+
+/// Read a whitespace separated token from a PBM header, skipping comments. The single
+/// whitespace character ending the token is consumed, as the format requires after the header.
+fn read_token(reader: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut token = String::new();
+    let mut in_comment = false;
+
+    for byte in reader.by_ref().bytes() {
+        match byte? {
+            b'\n' | b'\r' if in_comment => in_comment = false,
+            _ if in_comment => (),
+            b'#' if token.is_empty() => in_comment = true,
+            byte if byte.is_ascii_whitespace() => {
+                if !token.is_empty() {
+                    return Ok(Some(token));
+                }
+            }
+            byte => token.push(byte as char),
+        }
+    }
+
+    Ok((!token.is_empty()).then_some(token))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_read_raw_frames() {
+        let mut input: &[u8] = &[0b1000_0001, 0x00, 0x00, 0xFF];
+
+        let frame = read_frame(&mut input, Format::Raw, 16, 1).unwrap().unwrap();
+        assert!(frame.get(0, 0));
+        assert!(!frame.get(1, 0));
+        assert!(frame.get(7, 0));
+        assert!(!frame.get(8, 0));
+
+        let frame = read_frame(&mut input, Format::Raw, 16, 1).unwrap().unwrap();
+        assert!(!frame.get(7, 0));
+        assert!(frame.get(15, 0));
+
+        assert_eq!(read_frame(&mut input, Format::Raw, 16, 1).unwrap(), None);
+    }
+
+    #[test]
+    fn test_read_truncated_raw_frame() {
+        let mut input: &[u8] = &[0xFF];
+        let error = read_frame(&mut input, Format::Raw, 16, 1).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_read_pbm_frames() {
+        // Black pixels are set bits in PBM files, so these are the inverse of the pixels expected
+        let mut input = b"P4\n# a comment\n3 2\n\x5F\xBFP4 1 1\n\x7F\n".as_slice();
+
+        let frame = read_frame(&mut input, Format::Pbm, 32, 128)
+            .unwrap()
+            .unwrap();
+        assert_eq!((frame.width, frame.height), (3, 2));
+        assert!(frame.get(0, 0));
+        assert!(!frame.get(1, 0));
+        assert!(frame.get(2, 0));
+        assert!(frame.get(1, 1));
+
+        let frame = read_frame(&mut input, Format::Pbm, 32, 128)
+            .unwrap()
+            .unwrap();
+        assert_eq!((frame.width, frame.height), (1, 1));
+        assert!(frame.get(0, 0));
+
+        assert_eq!(read_frame(&mut input, Format::Pbm, 32, 128).unwrap(), None);
+    }
+
+    #[test]
+    fn test_read_oversized_pbm_is_rejected() {
+        let mut input = b"P4\n18446744073709551615 18446744073709551615\n".as_slice();
+        let error = read_frame(&mut input, Format::Pbm, 32, 128).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+
+        let mut input = b"P4\n33 1\n\0\0\0\0\0".as_slice();
+        let error = read_frame(&mut input, Format::Pbm, 32, 128).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_read_ascii_pbm_is_rejected() {
+        let mut input = b"P1\n1 1\n1\n".as_slice();
+        let error = read_frame(&mut input, Format::Pbm, 32, 128).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,129 @@
+/// A pattern described by whether each pixel is enabled, given its coordinates and the size of
+/// the screen
+pub type Pattern = fn(usize, usize, usize, usize) -> bool;
+
+/// The static patterns shown by the `test-pattern` command, in order
+pub const PATTERNS: &[(&str, Pattern)] = &[
+    ("checkerboard", checkerboard),
+    ("byte checkerboard", byte_checkerboard),
+    ("border", border),
+    ("horizontal gradient", horizontal_gradient),
+    ("vertical gradient", vertical_gradient),
+];
+
+/// Ordered dithering thresholds used to draw gradients in black and white
+const BAYER_4X4: [[usize; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+/// Alternating single pixels, showing any bits lost or duplicated along the way
+fn checkerboard(x: usize, y: usize, _width: usize, _height: usize) -> bool {
+    (x + y).is_multiple_of(2)
+}
+
+/// Alternating 8x8 squares lining up with the bytes sent to the device, showing whether bytes
+/// end up in the right place
+fn byte_checkerboard(x: usize, y: usize, _width: usize, _height: usize) -> bool {
+    (x / 8 + y / 8).is_multiple_of(2)
+}
+
+/// The outermost pixels of the screen, showing whether the configured size matches the panel
+fn border(x: usize, y: usize, width: usize, height: usize) -> bool {
+    x == 0 || y == 0 || x + 1 == width || y + 1 == height
+}
+
+fn horizontal_gradient(x: usize, y: usize, width: usize, _height: usize) -> bool {
+    dither(x, y, x * 16 / width)
+}
+
+fn vertical_gradient(x: usize, y: usize, _width: usize, height: usize) -> bool {
+    dither(x, y, y * 16 / height)
+}
+
+/// Whether a pixel is enabled in an area with the given brightness, from 0 to 16
+fn dither(x: usize, y: usize, brightness: usize) -> bool {
+    BAYER_4X4[y % 4][x % 4] < brightness
+}
+
+/// The coordinates visited by the pixel walk: clockwise around the edge of the screen starting
+/// at the origin, which shows the orientation of both axes
+pub fn pixel_walk(width: usize, height: usize) -> impl Iterator<Item = (usize, usize)> {
+    let (max_x, max_y) = (width.saturating_sub(1), height.saturating_sub(1));
+
+    (0..max_x)
+        .map(|x| (x, 0))
+        .chain((0..max_y).map(move |y| (max_x, y)))
+        .chain((1..=max_x).rev().map(move |x| (x, max_y)))
+        .chain((1..=max_y).rev().map(|y| (0, y)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn render(pattern: Pattern, width: usize, height: usize) -> Vec<String> {
+        (0..height)
+            .map(|y| {
+                (0..width)
+                    .map(|x| {
+                        if pattern(x, y, width, height) {
+                            '#'
+                        } else {
+                            '.'
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_checkerboard() {
+        assert_eq!(render(checkerboard, 4, 2), vec!["#.#.", ".#.#"]);
+    }
+
+    #[test]
+    fn test_byte_checkerboard() {
+        let rows = render(byte_checkerboard, 16, 16);
+        assert_eq!(rows[0], "########........");
+        assert_eq!(rows[7], "########........");
+        assert_eq!(rows[8], "........########");
+    }
+
+    #[test]
+    fn test_border() {
+        assert_eq!(render(border, 4, 3), vec!["####", "#..#", "####"]);
+    }
+
+    #[test]
+    fn test_gradient_brightens_along_axis() {
+        let lit = |x_start: usize| {
+            (x_start..x_start + 4)
+                .flat_map(|x| (0..4).map(move |y| (x, y)))
+                .filter(|&(x, y)| horizontal_gradient(x, y, 64, 4))
+                .count()
+        };
+
+        assert_eq!(lit(0), 0);
+        assert!(lit(0) < lit(28));
+        assert!(lit(28) < lit(60));
+        assert_eq!(lit(60), 15);
+    }
+
+    #[test]
+    fn test_pixel_walk() {
+        let walk: Vec<_> = pixel_walk(3, 3).collect();
+        assert_eq!(
+            walk,
+            vec![
+                (0, 0),
+                (1, 0),
+                (2, 0),
+                (2, 1),
+                (2, 2),
+                (1, 2),
+                (0, 2),
+                (0, 1)
+            ]
+        );
+    }
+}
@@ -0,0 +1,275 @@
+use crate::control::{
+    BRIGHTNESS_COMMAND, CONTROL_REPORT_ID, HEARTBEAT_COMMAND, HEARTBEAT_TIMEOUT, POWER_COMMAND,
+};
+use crate::data::{PAYLOAD_SIZE, SCREEN_REPORT_ID};
+use crate::events::{
+    ENCODER_EVENT, EVENT_REPORT_ID, KEYCODE_EVENT, KEYPRESS_EVENT, LAYER_EVENT, LOCK_EVENT,
+};
+use crate::handshake::{Capabilities, Rotation, HANDSHAKE_REPORT_ID, PROTOCOL_VERSION};
+
+const RECEIVER_TEMPLATE: &str = r#"// Generated by qmk-oled-api @VERSION@ for a @WIDTH@x@HEIGHT@ screen. Regenerate this rather than
+// editing it so that it stays in sync with the host.
+#include QMK_KEYBOARD_H
+#include "raw_hid.h"
+#include <string.h>
+
+#define OLED_API_PAYLOAD_SIZE @PAYLOAD_SIZE@
+#define OLED_API_CHUNK_SIZE (OLED_API_PAYLOAD_SIZE - 2 - @PREFIX_SIZE@)
+
+static uint8_t current_screen[@BUFFER_SIZE@];
+
+// When the last report from the host arrived, if one ever has. The host is treated as gone once
+// nothing has arrived for @HOST_TIMEOUT@ms, so that the keyboard's own screen is shown instead
+static uint32_t last_host_report;
+static bool host_seen = false;
+
+void @RECEIVE_FUNCTION@(uint8_t *data, uint8_t length) {
+@PREFIX_CHECK@    if (length < 2) {
+        return;
+    }
+    last_host_report = timer_read32();
+    host_seen = true;
+
+    switch (data[0]) {
+        // | @SCREEN_REPORT_ID@ | INDEX | DATA... |, where INDEX is the chunk of the screen the data belongs to
+        case @SCREEN_REPORT_ID@: {
+            uint16_t offset = data[1] * OLED_API_CHUNK_SIZE;
+            if (offset >= sizeof(current_screen)) {
+                return;
+            }
+
+            uint16_t size = MIN(sizeof(current_screen) - offset, OLED_API_CHUNK_SIZE);
+            size = MIN(size, length - 2);
+            memcpy(&current_screen[offset], &data[2], size);
+            break;
+        }
+        // | @CONTROL_REPORT_ID@ | COMMAND | ARGS... |
+        case @CONTROL_REPORT_ID@:
+            switch (data[1]) {
+                case @BRIGHTNESS_COMMAND@: oled_set_brightness(data[2]); break;
+                case @POWER_COMMAND@: data[2] ? oled_on() : oled_off(); break;
+                case @HEARTBEAT_COMMAND@: break; // Only resets the timeout
+            }
+            break;
+        // | @HANDSHAKE_REPORT_ID@ | MAJOR | MINOR |, answered with the protocol version spoken here and the
+        // | WIDTH | HEIGHT | ROTATION | of the screen and the | CAPABILITIES | of the receiver, the size as
+        // big-endian 16 bit numbers
+        case @HANDSHAKE_REPORT_ID@: {
+            uint8_t response[OLED_API_PAYLOAD_SIZE] = {@PREFIX@@HANDSHAKE_REPORT_ID@, @PROTOCOL_MAJOR@, @PROTOCOL_MINOR@, @GEOMETRY@};
+            raw_hid_send(response, sizeof(response));
+            break;
+        }
+    }
+}
+
+bool oled_task_user(void) {
+    if (!host_seen || timer_elapsed32(last_host_report) >= @HOST_TIMEOUT@) {
+        return true;
+    }
+    oled_write_raw((const char *)current_screen, sizeof(current_screen));
+    return false;
+}
+"#;
+
+const EVENTS_TEMPLATE: &str = r#"
+// Events are sent to the host as | @EVENT_REPORT_ID@ | TYPE | ARGS... |. Call
+// send_host_event(@KEYCODE_EVENT@, keycode >> 8, keycode & 0xFF) to send a custom keycode.
+static void send_host_event(uint8_t type, uint8_t arg1, uint8_t arg2) {
+    uint8_t data[OLED_API_PAYLOAD_SIZE] = {@PREFIX@@EVENT_REPORT_ID@, type, arg1, arg2};
+    raw_hid_send(data, sizeof(data));
+}
+
+bool process_record_user(uint16_t keycode, keyrecord_t *record) {
+    if (record->event.pressed) {
+        send_host_event(@KEYPRESS_EVENT@, 0, 0);
+    }
+    return true;
+}
+
+layer_state_t layer_state_set_user(layer_state_t state) {
+    send_host_event(@LAYER_EVENT@, get_highest_layer(state), 0);
+    return state;
+}
+
+bool led_update_user(led_t led_state) {
+    send_host_event(@LOCK_EVENT@, led_state.raw, 0);
+    return true;
+}
+
+bool encoder_update_user(uint8_t index, bool clockwise) {
+    send_host_event(@ENCODER_EVENT@, index, clockwise);
+    return false;
+}
+"#;
+
+/// Generates the firmware side of the protocol, as C to be added to a QMK keymap. Generating it
+/// from the same constants the host uses keeps the two in sync, unlike copying the snippet from
+/// the README by hand:
+///
+/// ```
+/// use qmk_oled_api::codegen::Receiver;
+///
+/// let keymap_code = Receiver::new(32, 128).with_events(true).generate();
+/// ```
+///
+/// The receiver passes frames straight to `oled_write_raw`, so send them with
+/// `OledScreen::with_layout(BufferLayout::OledWriteRaw)`.
+#[derive(Debug, Clone)]
+pub struct Receiver {
+    width: usize,
+    height: usize,
+    rotation: Rotation,
+    events: bool,
+    report_prefix: Option<u8>,
+}
+
+impl Receiver {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            rotation: Rotation::default(),
+            events: false,
+            report_prefix: None,
+        }
+    }
+
+    /// Report that the screen is rotated, matching what the keymap returns from `oled_init_user`.
+    /// The width and height are still those of the frames, after rotating
+    pub fn with_rotation(mut self, rotation: Rotation) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// Also send keypresses, layer changes, lock LEDs and encoder turns to the host as events.
+    /// Off by default, as the generated hooks (e.g. `process_record_user`) clash with any the
+    /// keymap already defines
+    pub fn with_events(mut self, events: bool) -> Self {
+        self.events = events;
+        self
+    }
+
+    /// Expect every report to start with the given prefix, to match
+    /// `OledScreen::with_report_prefix`. With VIA or Vial enabled the receiver is generated as
+    /// `raw_hid_receive_kb`, which they pass reports they don't recognise on to
+    pub fn with_report_prefix(mut self, prefix: u8) -> Self {
+        self.report_prefix = Some(prefix);
+        self
+    }
+
+    /// The C code for the receiver
+    pub fn generate(&self) -> String {
+        let mut code = RECEIVER_TEMPLATE.to_string();
+        if self.events {
+            code.push_str(EVENTS_TEMPLATE);
+        }
+
+        let (receive_function, prefix_check, prefix) = match self.report_prefix {
+            Some(prefix) => (
+                "raw_hid_receive_kb",
+                format!(
+                    "    if (length < 1 || data[0] != {prefix:#04x}) {{\n        \
+                     return;\n    }}\n    data++;\n    length--;\n\n"
+                ),
+                format!("{prefix:#04x}, "),
+            ),
+            None => ("raw_hid_receive", String::new(), String::new()),
+        };
+
+        let [width_high, width_low] = (self.width as u16).to_be_bytes();
+        let [height_high, height_low] = (self.height as u16).to_be_bytes();
+        let capabilities = Capabilities {
+            brightness: true,
+            events: self.events,
+            compression: false,
+            multi_screen: false,
+            quantum_painter: false,
+        };
+        let geometry = format!(
+            "{width_high}, {width_low}, {height_high}, {height_low}, {}, {}",
+            self.rotation as u8,
+            capabilities.to_byte()
+        );
+
+        let replacements = [
+            ("@VERSION@", env!("CARGO_PKG_VERSION").to_string()),
+            ("@RECEIVE_FUNCTION@", receive_function.to_string()),
+            ("@PREFIX_CHECK@", prefix_check),
+            (
+                "@PREFIX_SIZE@",
+                usize::from(self.report_prefix.is_some()).to_string(),
+            ),
+            ("@PREFIX@", prefix),
+            ("@WIDTH@", self.width.to_string()),
+            ("@HEIGHT@", self.height.to_string()),
+            ("@BUFFER_SIZE@", (self.width * self.height / 8).to_string()),
+            ("@PAYLOAD_SIZE@", PAYLOAD_SIZE.to_string()),
+            ("@SCREEN_REPORT_ID@", SCREEN_REPORT_ID.to_string()),
+            ("@CONTROL_REPORT_ID@", CONTROL_REPORT_ID.to_string()),
+            ("@BRIGHTNESS_COMMAND@", BRIGHTNESS_COMMAND.to_string()),
+            ("@POWER_COMMAND@", POWER_COMMAND.to_string()),
+            ("@HEARTBEAT_COMMAND@", HEARTBEAT_COMMAND.to_string()),
+            ("@HOST_TIMEOUT@", HEARTBEAT_TIMEOUT.as_millis().to_string()),
+            ("@HANDSHAKE_REPORT_ID@", HANDSHAKE_REPORT_ID.to_string()),
+            ("@PROTOCOL_MAJOR@", PROTOCOL_VERSION.major.to_string()),
+            ("@PROTOCOL_MINOR@", PROTOCOL_VERSION.minor.to_string()),
+            ("@GEOMETRY@", geometry),
+            ("@EVENT_REPORT_ID@", EVENT_REPORT_ID.to_string()),
+            ("@ENCODER_EVENT@", ENCODER_EVENT.to_string()),
+            ("@KEYCODE_EVENT@", KEYCODE_EVENT.to_string()),
+            ("@KEYPRESS_EVENT@", KEYPRESS_EVENT.to_string()),
+            ("@LAYER_EVENT@", LAYER_EVENT.to_string()),
+            ("@LOCK_EVENT@", LOCK_EVENT.to_string()),
+        ];
+        for (placeholder, value) in replacements {
+            code = code.replace(placeholder, &value);
+        }
+        code
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_receiver() {
+        let code = Receiver::new(128, 32).generate();
+
+        assert!(code.contains("for a 128x32 screen"));
+        assert!(code.contains("static uint8_t current_screen[512];"));
+        assert!(code.contains("#define OLED_API_PAYLOAD_SIZE 32"));
+        assert!(code.contains("case 1: {"));
+        assert!(code.contains("case 3:"));
+        assert!(code
+            .contains("uint8_t response[OLED_API_PAYLOAD_SIZE] = {4, 1, 3, 0, 128, 0, 32, 0, 1};"));
+        assert!(code.contains("timer_elapsed32(last_host_report) >= 3000"));
+        assert!(!code.contains("send_host_event"));
+        assert!(!code.contains('@'), "unreplaced placeholder in:\n{code}");
+    }
+
+    #[test]
+    fn test_generate_receiver_with_prefix() {
+        let code = Receiver::new(32, 128)
+            .with_events(true)
+            .with_rotation(Rotation::R270)
+            .with_report_prefix(0x6F)
+            .generate();
+
+        assert!(code.contains("void raw_hid_receive_kb(uint8_t *data, uint8_t length) {"));
+        assert!(code.contains("if (length < 1 || data[0] != 0x6f) {"));
+        assert!(code.contains("(OLED_API_PAYLOAD_SIZE - 2 - 1)"));
+        assert!(code.contains("= {0x6f, 4, 1, 3, 0, 32, 0, 128, 3, 3};"));
+        assert!(code.contains("= {0x6f, 2, type, arg1, arg2};"));
+        assert!(!code.contains('@'), "unreplaced placeholder in:\n{code}");
+    }
+
+    #[test]
+    fn test_generate_receiver_with_events() {
+        let code = Receiver::new(32, 128).with_events(true).generate();
+
+        assert!(code.contains("uint8_t data[OLED_API_PAYLOAD_SIZE] = {2, type, arg1, arg2};"));
+        assert!(code.contains("send_host_event(4, get_highest_layer(state), 0);"));
+        assert!(!code.contains('@'), "unreplaced placeholder in:\n{code}");
+    }
+}
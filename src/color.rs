@@ -0,0 +1,224 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::framebuffer::Framebuffer;
+use crate::geometry::{line_points, outline, Point, Rect};
+
+/// A colour packed into 16 bits, 5 for red, 6 for green and 5 for blue. This is what the LCDs
+/// Quantum Painter drives (e.g. the ST7789 and GC9A01) take natively, so frames can be passed
+/// straight on to the panel
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Rgb565(pub u16);
+
+impl Rgb565 {
+    pub const BLACK: Self = Self::from_rgb(0, 0, 0);
+    pub const WHITE: Self = Self::from_rgb(255, 255, 255);
+    pub const RED: Self = Self::from_rgb(255, 0, 0);
+    pub const GREEN: Self = Self::from_rgb(0, 255, 0);
+    pub const BLUE: Self = Self::from_rgb(0, 0, 255);
+
+    /// The nearest colour to an 8 bit per channel one, dropping the low bits of each channel
+    pub const fn from_rgb(red: u8, green: u8, blue: u8) -> Self {
+        Self(((red as u16 >> 3) << 11) | ((green as u16 >> 2) << 5) | (blue as u16 >> 3))
+    }
+
+    /// The colour with 8 bits per channel, repeating the high bits of each channel into the low
+    /// ones so that white stays white
+    pub const fn to_rgb(self) -> (u8, u8, u8) {
+        let red = (self.0 >> 11) as u8 & 0x1F;
+        let green = (self.0 >> 5) as u8 & 0x3F;
+        let blue = self.0 as u8 & 0x1F;
+        (
+            (red << 3) | (red >> 2),
+            (green << 2) | (green >> 4),
+            (blue << 3) | (blue >> 2),
+        )
+    }
+}
+
+/// The pixels of a colour screen and the primitives for drawing them, the colour counterpart to
+/// `Framebuffer`. `ColorScreen` draws with one of these, and like `Framebuffer` it can be used
+/// without the standard library along with `data::wide_frame_packets`
+///
+/// The origin is in the bottom left, the same as `Framebuffer`. Pixels are stored the way they
+/// are sent: rows from the top of the screen to the bottom, each from left to right, which is the
+/// order a Quantum Painter viewport is filled in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColorFramebuffer {
+    width: usize,
+    height: usize,
+    pixels: Vec<Rgb565>,
+}
+
+impl ColorFramebuffer {
+    /// A framebuffer for a screen of the given size with every pixel black
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![Rgb565::BLACK; width * height],
+        }
+    }
+
+    /// The width of the screen in pixels
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// The height of the screen in pixels
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// The pixels as they are stored, from the top left of the screen
+    pub fn pixels(&self) -> &[Rgb565] {
+        &self.pixels
+    }
+
+    /// Write the pixels into a buffer of twice as many bytes in the order they are sent, with
+    /// each pixel big endian as the panels expect
+    pub fn encode_into(&self, out: &mut [u8]) {
+        for (bytes, pixel) in out.chunks_exact_mut(2).zip(&self.pixels) {
+            bytes.copy_from_slice(&pixel.0.to_be_bytes());
+        }
+    }
+
+    /// Set every pixel to the given colour
+    pub fn fill(&mut self, color: Rgb565) {
+        self.pixels.fill(color);
+    }
+
+    /// Paint a square region
+    pub fn paint_region(&mut self, region: Rect, color: Rgb565) {
+        for x in region.min_x()..region.max_x().min(self.width) {
+            for y in region.min_y()..region.max_y().min(self.height) {
+                self.set_pixel(x, y, color)
+            }
+        }
+    }
+
+    /// Draw a one pixel wide straight line between two points, including both ends
+    pub fn draw_line(&mut self, from: Point, to: Point, color: Rgb565) {
+        for point in line_points(from, to) {
+            self.set_pixel(point.x, point.y, color);
+        }
+    }
+
+    /// Draw the outline of a square region, use `paint_region` to fill it in instead
+    pub fn draw_rect(&mut self, region: Rect, color: Rgb565) {
+        if region.is_empty() {
+            return;
+        }
+
+        for (from, to) in outline(region) {
+            self.draw_line(from, to, color);
+        }
+    }
+
+    /// Paint the lit pixels of a monochrome framebuffer in a colour, with its bottom left corner
+    /// at `at`. Unlit pixels are left as they are. This is how anything drawn in black and white
+    /// (e.g. text on an `OledScreen`) ends up on a colour screen
+    pub fn draw_mask(&mut self, mask: &Framebuffer, at: Point, color: Rgb565) {
+        for x in 0..mask.width() {
+            for y in 0..mask.height() {
+                if mask.get_pixel(x, y) {
+                    self.set_pixel(at.x + x, at.y + y, color);
+                }
+            }
+        }
+    }
+
+    /// Get the current colour of a pixel. Pixels outside of the screen read as black
+    pub fn get_pixel(&self, x: usize, y: usize) -> Rgb565 {
+        match self.index(x, y) {
+            Some(index) => self.pixels[index],
+            None => Rgb565::BLACK,
+        }
+    }
+
+    /// Set the colour of a pixel. Pixels outside of the screen are ignored
+    pub fn set_pixel(&mut self, x: usize, y: usize, color: Rgb565) {
+        if let Some(index) = self.index(x, y) {
+            self.pixels[index] = color;
+        }
+    }
+
+    /// Where a pixel is stored, flipping y as rows are stored from the top
+    fn index(&self, x: usize, y: usize) -> Option<usize> {
+        (x < self.width && y < self.height).then(|| (self.height - 1 - y) * self.width + x)
+    }
+}
+
+/// Write a black and white framebuffer into a buffer in the same format as
+/// `ColorFramebuffer::encode_into`, with lit pixels in `foreground` and the rest in `background`.
+/// This is how `OledScreen` sends frames to a colour screen, see `FrameProtocol`
+pub fn encode_monochrome_into(
+    framebuffer: &Framebuffer,
+    foreground: Rgb565,
+    background: Rgb565,
+    out: &mut [u8],
+) {
+    let (width, height) = (framebuffer.width(), framebuffer.height());
+    for (index, bytes) in out.chunks_exact_mut(2).take(width * height).enumerate() {
+        let (x, y) = (index % width, height - 1 - index / width);
+        let color = match framebuffer.get_pixel(x, y) {
+            true => foreground,
+            false => background,
+        };
+        bytes.copy_from_slice(&color.0.to_be_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rgb565() {
+        assert_eq!(Rgb565::WHITE, Rgb565(0xFFFF));
+        assert_eq!(Rgb565::RED, Rgb565(0xF800));
+        assert_eq!(Rgb565::from_rgb(0, 255, 0), Rgb565(0x07E0));
+        assert_eq!(Rgb565::WHITE.to_rgb(), (255, 255, 255));
+        assert_eq!(Rgb565::from_rgb(200, 100, 50).to_rgb(), (206, 101, 49));
+    }
+
+    #[test]
+    fn test_color_framebuffer() {
+        let mut framebuffer = ColorFramebuffer::new(4, 3);
+        framebuffer.set_pixel(1, 2, Rgb565::RED);
+        framebuffer.set_pixel(4, 0, Rgb565::RED);
+        framebuffer.draw_line(Point::new(0, 0), Point::new(3, 0), Rgb565::BLUE);
+
+        assert_eq!(framebuffer.get_pixel(1, 2), Rgb565::RED);
+        assert_eq!(framebuffer.pixels()[1], Rgb565::RED);
+        assert_eq!(framebuffer.pixels()[8..], [Rgb565::BLUE; 4]);
+
+        let mut encoded = vec![0; 24];
+        framebuffer.encode_into(&mut encoded);
+        assert_eq!(encoded[..4], [0, 0, 0xF8, 0]);
+        assert_eq!(encoded[16..18], [0, 0x1F]);
+
+        let mut mask = Framebuffer::new(8, 1);
+        mask.set_pixel(0, 0, true);
+        framebuffer.draw_mask(&mask, Point::new(2, 1), Rgb565::GREEN);
+        assert_eq!(framebuffer.get_pixel(2, 1), Rgb565::GREEN);
+        assert_eq!(framebuffer.get_pixel(3, 1), Rgb565::BLACK);
+    }
+
+    #[test]
+    fn test_encode_monochrome() {
+        let mut mono = Framebuffer::new(8, 2);
+        mono.set_pixel(1, 1, true);
+        mono.set_pixel(0, 0, true);
+
+        let mut color = ColorFramebuffer::new(8, 2);
+        color.fill(Rgb565::BLUE);
+        color.draw_mask(&mono, Point::new(0, 0), Rgb565::WHITE);
+
+        let (mut encoded, mut expected) = (vec![0; 32], vec![0; 32]);
+        encode_monochrome_into(&mono, Rgb565::WHITE, Rgb565::BLUE, &mut encoded);
+        color.encode_into(&mut expected);
+        assert_eq!(encoded, expected);
+        assert_eq!(encoded[2..4], [0xFF, 0xFF]);
+    }
+}
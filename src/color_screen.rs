@@ -0,0 +1,141 @@
+use crate::color::{ColorFramebuffer, Rgb565};
+use crate::data::COLOR_REPORT_ID;
+use crate::geometry::{Point, Rect};
+use crate::wide_screen::{WideFramebuffer, WideScreen};
+
+/// A colour screen, such as a 128x128 LCD driven by Quantum Painter, the colour counterpart to
+/// `OledScreen`. Frames are sent as RGB565 in `COLOR_REPORT_ID` reports, and like `OledScreen`
+/// only the parts which changed since the last frame are sent:
+///
+/// ```no_run
+/// use std::ffi::CString;
+/// use qmk_oled_api::color::Rgb565;
+/// use qmk_oled_api::color_screen::ColorScreen;
+/// use qmk_oled_api::geometry::Rect;
+///
+/// let path = CString::new("/dev/hidraw0").unwrap();
+/// let mut screen = ColorScreen::from_path(&path, 128, 128).unwrap();
+/// screen.paint_region(Rect::new(16, 16, 96, 96), Rgb565::from_rgb(255, 128, 0));
+/// screen.send().unwrap();
+/// ```
+///
+/// Anything drawn in black and white, such as text, can be drawn on a `Framebuffer` and painted
+/// on in a colour with `ColorFramebuffer::draw_mask`.
+pub type ColorScreen = WideScreen<ColorFramebuffer>;
+
+impl WideFramebuffer for ColorFramebuffer {
+    type Pixel = Rgb565;
+
+    const REPORT_ID: u8 = COLOR_REPORT_ID;
+
+    fn new(width: usize, height: usize) -> Self {
+        ColorFramebuffer::new(width, height)
+    }
+
+    fn width(&self) -> usize {
+        ColorFramebuffer::width(self)
+    }
+
+    fn height(&self) -> usize {
+        ColorFramebuffer::height(self)
+    }
+
+    fn encode<'a>(&'a self, buffer: &'a mut Vec<u8>) -> &'a [u8] {
+        buffer.resize(self.pixels().len() * 2, 0);
+        self.encode_into(buffer);
+        buffer
+    }
+
+    fn pixel_from_rgb(red: u8, green: u8, blue: u8) -> Rgb565 {
+        Rgb565::from_rgb(red, green, blue)
+    }
+
+    fn fill(&mut self, color: Rgb565) {
+        ColorFramebuffer::fill(self, color);
+    }
+
+    fn paint_region(&mut self, region: Rect, color: Rgb565) {
+        ColorFramebuffer::paint_region(self, region, color);
+    }
+
+    fn draw_line(&mut self, from: Point, to: Point, color: Rgb565) {
+        ColorFramebuffer::draw_line(self, from, to, color);
+    }
+
+    fn draw_rect(&mut self, region: Rect, color: Rgb565) {
+        ColorFramebuffer::draw_rect(self, region, color);
+    }
+
+    fn get_pixel(&self, x: usize, y: usize) -> Rgb565 {
+        ColorFramebuffer::get_pixel(self, x, y)
+    }
+
+    fn set_pixel(&mut self, x: usize, y: usize, color: Rgb565) {
+        ColorFramebuffer::set_pixel(self, x, y, color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::VIA_REPORT_PREFIX;
+    use crate::testing::MockDevice;
+    use image::{DynamicImage, Rgba, RgbaImage};
+
+    #[test]
+    fn test_color_screen_send() {
+        let device = MockDevice::new();
+        let mut screen = ColorScreen::from_device(device.clone(), 128, 128).unwrap();
+
+        screen.send().unwrap();
+        let writes = device.take_writes();
+        assert_eq!(writes.len(), (128 * 128 * 2_usize).div_ceil(28));
+        assert_eq!(writes[1][..3], [COLOR_REPORT_ID, 0, 1]);
+
+        // The top left pixel is the first one sent
+        screen.set_pixel(0, 127, Rgb565::RED);
+        screen.send().unwrap();
+        assert_eq!(
+            device.take_writes(),
+            [[&[COLOR_REPORT_ID, 0, 0, 0xF8, 0][..], &[0; 27]].concat()]
+        );
+
+        screen.set_pixel(127, 0, Rgb565::WHITE);
+        screen.send().unwrap();
+        let writes = device.take_writes();
+        assert_eq!(writes.len(), 1);
+        assert_eq!(writes[0][1..3], 1170_u16.to_be_bytes());
+    }
+
+    #[test]
+    fn test_color_screen_report_prefix() {
+        let device = MockDevice::new();
+        let mut screen = ColorScreen::from_device(device.clone(), 14, 1)
+            .unwrap()
+            .with_report_prefix(VIA_REPORT_PREFIX);
+        screen.fill(Rgb565::BLUE);
+        screen.send().unwrap();
+
+        let writes = device.take_writes();
+        assert_eq!(writes.len(), 1);
+        assert_eq!(
+            writes[0][..6],
+            [VIA_REPORT_PREFIX, COLOR_REPORT_ID, 0, 0, 0, 0x1F]
+        );
+        assert_eq!(writes[0][30..], [0, 0x1F]);
+    }
+
+    #[test]
+    fn test_color_screen_draw_image() {
+        let mut image = RgbaImage::new(2, 2);
+        image.put_pixel(0, 0, Rgba([255, 0, 0, 255]));
+        image.put_pixel(1, 1, Rgba([0, 255, 0, 255]));
+
+        let mut screen = ColorScreen::from_device(MockDevice::new(), 4, 4).unwrap();
+        screen.fill(Rgb565::BLUE);
+        screen.draw_image(&DynamicImage::ImageRgba8(image), 1, 1);
+        assert_eq!(screen.get_pixel(1, 2), Rgb565::RED);
+        assert_eq!(screen.get_pixel(2, 1), Rgb565::GREEN);
+        assert_eq!(screen.get_pixel(1, 1), Rgb565::BLUE);
+    }
+}
@@ -0,0 +1,220 @@
+use std::path::PathBuf;
+
+use crate::geometry::{Point, Rect};
+use crate::screen::{ImageSizing, OledScreen, TextStyle};
+
+/// A single drawing command, as used by the draw server's line protocol and the command line
+/// REPL
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    /// `clear`
+    Clear,
+    /// `fill`
+    Fill,
+    /// `text <x> <y> <size> <text...>`
+    Text {
+        x: usize,
+        y: usize,
+        size: f32,
+        text: String,
+    },
+    /// `image <x> <y> <contain|cover|original|stretch> <path>`
+    Image {
+        x: usize,
+        y: usize,
+        sizing: ImageSizing,
+        path: PathBuf,
+    },
+    /// `line <x1> <y1> <x2> <y2>`
+    Line {
+        x1: usize,
+        y1: usize,
+        x2: usize,
+        y2: usize,
+    },
+    /// `rect <min_x> <min_y> <max_x> <max_y> [filled]`
+    Rect {
+        min_x: usize,
+        min_y: usize,
+        max_x: usize,
+        max_y: usize,
+        filled: bool,
+    },
+    /// `send`
+    Send,
+}
+
+impl Command {
+    /// Parse a line of the protocol, returning a human readable message if it is invalid
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let line = line.trim();
+        let (name, args) = line.split_once(' ').unwrap_or((line, ""));
+
+        match name {
+            "clear" => Ok(Command::Clear),
+            "fill" => Ok(Command::Fill),
+            "send" => Ok(Command::Send),
+            "text" => {
+                let mut args = args.splitn(4, ' ');
+                Ok(Command::Text {
+                    x: parse_arg(args.next(), "x")?,
+                    y: parse_arg(args.next(), "y")?,
+                    size: parse_arg(args.next(), "size")?,
+                    text: args.next().unwrap_or_default().to_string(),
+                })
+            }
+            "line" => {
+                let mut args = args.split_whitespace();
+                Ok(Command::Line {
+                    x1: parse_arg(args.next(), "x1")?,
+                    y1: parse_arg(args.next(), "y1")?,
+                    x2: parse_arg(args.next(), "x2")?,
+                    y2: parse_arg(args.next(), "y2")?,
+                })
+            }
+            "rect" => {
+                let mut args = args.split_whitespace();
+                let min_x = parse_arg(args.next(), "min_x")?;
+                let min_y = parse_arg(args.next(), "min_y")?;
+                let max_x = parse_arg(args.next(), "max_x")?;
+                let max_y = parse_arg(args.next(), "max_y")?;
+                let filled = match args.next() {
+                    None => false,
+                    Some("filled") => true,
+                    Some(other) => return Err(format!("unexpected argument '{other}'")),
+                };
+                Ok(Command::Rect {
+                    min_x,
+                    min_y,
+                    max_x,
+                    max_y,
+                    filled,
+                })
+            }
+            "image" | "img" => {
+                let mut args = args.splitn(4, ' ');
+                let x = parse_arg(args.next(), "x")?;
+                let y = parse_arg(args.next(), "y")?;
+                let sizing = match args.next() {
+                    Some("contain") => ImageSizing::Contain,
+                    Some("cover") => ImageSizing::Cover,
+                    Some("original") => ImageSizing::Original,
+                    Some("stretch") => ImageSizing::Stretch,
+                    _ => return Err("sizing must be contain, cover, original or stretch".into()),
+                };
+                let path = match args.next() {
+                    Some(path) if !path.is_empty() => PathBuf::from(path),
+                    _ => return Err("missing image path".into()),
+                };
+                Ok(Command::Image { x, y, sizing, path })
+            }
+            "" => Err("empty command".into()),
+            _ => Err(format!("unknown command '{name}'")),
+        }
+    }
+
+    /// Apply the command to a screen
+    pub fn apply(self, screen: &mut OledScreen) -> Result<(), String> {
+        match self {
+            Command::Clear => screen.clear(),
+            Command::Fill => screen.fill_all(),
+            Command::Text { x, y, size, text } => screen
+                .draw_text(&text, x, y, &TextStyle::new(size))
+                .map_err(|error| error.to_string())?,
+            Command::Line { x1, y1, x2, y2 } => {
+                screen.draw_line(Point::new(x1, y1), Point::new(x2, y2))
+            }
+            Command::Rect {
+                min_x,
+                min_y,
+                max_x,
+                max_y,
+                filled,
+            } => {
+                if filled {
+                    screen.paint_region(
+                        Rect::from_corners(Point::new(min_x, min_y), Point::new(max_x, max_y)),
+                        true,
+                    )
+                } else {
+                    screen.draw_rect(Rect::from_corners(
+                        Point::new(min_x, min_y),
+                        Point::new(max_x, max_y),
+                    ))
+                }
+            }
+            Command::Image { x, y, sizing, path } => {
+                let image = image::open(&path).map_err(|error| error.to_string())?;
+                screen.draw_image(image, x, y, &sizing)
+            }
+            Command::Send => screen.send().map_err(|error| error.to_string())?,
+        }
+        Ok(())
+    }
+}
+
+fn parse_arg<T: std::str::FromStr>(arg: Option<&str>, name: &str) -> Result<T, String> {
+    arg.and_then(|arg| arg.parse().ok())
+        .ok_or_else(|| format!("invalid or missing {name}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockDevice;
+
+    #[test]
+    fn test_parse_commands() {
+        assert_eq!(Command::parse("clear\n"), Ok(Command::Clear));
+        assert_eq!(
+            Command::parse("text 1 2 8.5 Hello world"),
+            Ok(Command::Text {
+                x: 1,
+                y: 2,
+                size: 8.5,
+                text: "Hello world".into()
+            })
+        );
+        assert_eq!(
+            Command::parse("rect 0 0 4 8 filled"),
+            Ok(Command::Rect {
+                min_x: 0,
+                min_y: 0,
+                max_x: 4,
+                max_y: 8,
+                filled: true
+            })
+        );
+        assert_eq!(
+            Command::parse("img 0 0 cover a.png"),
+            Ok(Command::Image {
+                x: 0,
+                y: 0,
+                sizing: ImageSizing::Cover,
+                path: "a.png".into()
+            })
+        );
+        assert!(Command::parse("text 1 two 8 Hi").is_err());
+        assert!(Command::parse("line 0 0 4").is_err());
+        assert!(Command::parse("rect 0 0 4 8 hollow").is_err());
+        assert!(Command::parse("image 0 0 tile a.png").is_err());
+        assert!(Command::parse("explode").is_err());
+    }
+
+    #[test]
+    fn test_apply_rect() {
+        let mut screen = OledScreen::from_device(MockDevice::new(), 32, 128).unwrap();
+        Command::parse("rect 0 0 4 4")
+            .unwrap()
+            .apply(&mut screen)
+            .unwrap();
+        assert!(screen.get_pixel(3, 3));
+        assert!(!screen.get_pixel(1, 1));
+
+        Command::parse("rect 0 0 4 4 filled")
+            .unwrap()
+            .apply(&mut screen)
+            .unwrap();
+        assert!(screen.get_pixel(1, 1));
+    }
+}
@@ -0,0 +1,222 @@
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use hidapi::HidError;
+
+use crate::data::PAYLOAD_SIZE;
+use crate::screen::OledScreen;
+
+/// The first byte of a report sent by the host to control the display rather than draw to it.
+/// Screen payloads start with 1 and keyboard events with 2
+pub const CONTROL_REPORT_ID: u8 = 3;
+
+pub(crate) const BRIGHTNESS_COMMAND: u8 = 1;
+pub(crate) const POWER_COMMAND: u8 = 2;
+pub(crate) const HEARTBEAT_COMMAND: u8 = 3;
+
+/// How often `Heartbeat` and `BackgroundSender` let the firmware know the host is still running
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long the firmware waits without hearing from the host before deciding it has gone and
+/// drawing its own screen instead of the last frame it was sent. Any report resets the timeout, so
+/// a host only needs to send heartbeats while it has nothing else to send
+pub const HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// A command sent from the host to the keyboard to change how the display behaves.
+///
+/// Commands are encoded as `| 3 | COMMAND | ARGS... |`, see the client snippet in the README for
+/// the firmware side
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControlMessage {
+    /// Set the OLED brightness, from 0 to 255
+    Brightness(u8),
+    /// Turn the display on or off
+    Power(bool),
+    /// Does nothing but reset the firmware's timeout, see `HEARTBEAT_TIMEOUT`
+    Heartbeat,
+}
+
+impl ControlMessage {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = match self {
+            Self::Brightness(level) => vec![CONTROL_REPORT_ID, BRIGHTNESS_COMMAND, *level],
+            Self::Power(on) => vec![CONTROL_REPORT_ID, POWER_COMMAND, *on as u8],
+            Self::Heartbeat => vec![CONTROL_REPORT_ID, HEARTBEAT_COMMAND],
+        };
+        bytes.resize(PAYLOAD_SIZE, 0);
+        bytes
+    }
+}
+
+/// Changes the display brightness at configured times of day, e.g. dimming it in the evening.
+///
+/// Times are read using a fixed offset from UTC, set with `with_utc_offset`
+#[derive(Default)]
+pub struct BrightnessSchedule {
+    /// Seconds since midnight and the brightness to switch to at that time, in order
+    entries: Vec<(u32, u8)>,
+    utc_offset: i64,
+    current: Option<u8>,
+}
+
+impl BrightnessSchedule {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Switch to the given brightness (0 to 255) at the given time of day
+    pub fn at(mut self, hour: u32, minute: u32, brightness: u8) -> Self {
+        let time = (hour % 24) * 3600 + (minute % 60) * 60;
+        self.entries.retain(|(existing, _)| *existing != time);
+        self.entries.push((time, brightness));
+        self.entries.sort_unstable();
+        self
+    }
+
+    /// Read times as being at the given offset from UTC, in minutes (e.g. 60 for UTC+1)
+    pub fn with_utc_offset(mut self, minutes: i64) -> Self {
+        self.utc_offset = minutes * 60;
+        self
+    }
+
+    /// The brightness that should be in effect at the given number of seconds after midnight.
+    /// Before the first entry of the day, the last entry from the previous day applies
+    pub fn brightness_at(&self, seconds_of_day: u32) -> Option<u8> {
+        self.entries
+            .iter()
+            .rev()
+            .find(|(time, _)| *time <= seconds_of_day)
+            .or_else(|| self.entries.last())
+            .map(|(_, brightness)| *brightness)
+    }
+
+    /// Send a brightness command to the screen's device if the scheduled brightness has changed
+    /// since the last call. Call this regularly (e.g. once a minute). Returns whether a command
+    /// was sent
+    pub fn update(&mut self, screen: &OledScreen) -> Result<bool, HidError> {
+        let unix_seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since_epoch| since_epoch.as_secs() as i64);
+        let seconds_of_day = (unix_seconds + self.utc_offset).rem_euclid(86400) as u32;
+
+        self.update_at(screen, seconds_of_day)
+    }
+
+    fn update_at(&mut self, screen: &OledScreen, seconds_of_day: u32) -> Result<bool, HidError> {
+        let brightness = self.brightness_at(seconds_of_day);
+        if brightness == self.current {
+            return Ok(false);
+        }
+
+        if let Some(level) = brightness {
+            screen.send_control(&ControlMessage::Brightness(level))?;
+        }
+        self.current = brightness;
+        Ok(brightness.is_some())
+    }
+}
+
+/// Lets the firmware know the host is still running by sending a heartbeat every second, so that
+/// it can fall back to drawing its own screen if the host stops (see `HEARTBEAT_TIMEOUT`). Only
+/// needed when frames aren't sent through a `BackgroundSender`, which does this itself
+pub struct Heartbeat {
+    interval: Duration,
+    last_sent: Option<Instant>,
+}
+
+impl Default for Heartbeat {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Heartbeat {
+    pub fn new() -> Self {
+        Self {
+            interval: HEARTBEAT_INTERVAL,
+            last_sent: None,
+        }
+    }
+
+    /// Send heartbeats at a different interval. Keep it comfortably below `HEARTBEAT_TIMEOUT`
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Send a heartbeat to the screen's device if one is due. Call this regularly (e.g. once per
+    /// frame, or whenever waiting for input times out). Returns whether a heartbeat was sent
+    pub fn update(&mut self, screen: &OledScreen) -> Result<bool, HidError> {
+        self.update_at(screen, Instant::now())
+    }
+
+    fn update_at(&mut self, screen: &OledScreen, now: Instant) -> Result<bool, HidError> {
+        let due = self
+            .last_sent
+            .is_none_or(|last_sent| now.saturating_duration_since(last_sent) >= self.interval);
+        if !due {
+            return Ok(false);
+        }
+
+        screen.send_control(&ControlMessage::Heartbeat)?;
+        self.last_sent = Some(now);
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockDevice;
+
+    #[test]
+    fn test_brightness_at() {
+        let schedule = BrightnessSchedule::new()
+            .at(22, 0, 25)
+            .at(7, 30, 255)
+            .at(22, 0, 20);
+
+        assert_eq!(schedule.brightness_at(0), Some(20));
+        assert_eq!(schedule.brightness_at(7 * 3600 + 1800), Some(255));
+        assert_eq!(schedule.brightness_at(21 * 3600), Some(255));
+        assert_eq!(schedule.brightness_at(23 * 3600), Some(20));
+        assert_eq!(BrightnessSchedule::new().brightness_at(0), None);
+    }
+
+    #[test]
+    fn test_update_only_sends_changes() {
+        let screen = OledScreen::from_device(MockDevice::new(), 32, 128).unwrap();
+        let mut schedule = BrightnessSchedule::new().at(8, 0, 255).at(22, 0, 25);
+
+        assert!(schedule.update_at(&screen, 9 * 3600).unwrap());
+        assert!(!schedule.update_at(&screen, 10 * 3600).unwrap());
+        assert!(schedule.update_at(&screen, 23 * 3600).unwrap());
+    }
+
+    #[test]
+    fn test_heartbeat() {
+        let device = MockDevice::new();
+        let screen = OledScreen::from_device(device.clone(), 32, 128).unwrap();
+        let mut heartbeat = Heartbeat::new();
+        let start = Instant::now();
+
+        assert!(heartbeat.update_at(&screen, start).unwrap());
+        assert!(!heartbeat
+            .update_at(&screen, start + Duration::from_millis(999))
+            .unwrap());
+        assert!(heartbeat
+            .update_at(&screen, start + HEARTBEAT_INTERVAL)
+            .unwrap());
+
+        let writes = device.writes();
+        assert_eq!(writes.len(), 2);
+        assert_eq!(writes[0][..3], [CONTROL_REPORT_ID, HEARTBEAT_COMMAND, 0]);
+    }
+
+    #[test]
+    fn test_control_message_bytes() {
+        let bytes = ControlMessage::Brightness(128).to_bytes();
+
+        assert_eq!(bytes.len(), PAYLOAD_SIZE);
+        assert_eq!(bytes[..3], [CONTROL_REPORT_ID, BRIGHTNESS_COMMAND, 128]);
+    }
+}
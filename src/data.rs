@@ -1,18 +1,116 @@
+#[cfg(feature = "std")]
 use std::any::Any;
+#[cfg(feature = "std")]
+use std::fmt::Display;
 
+#[cfg(feature = "std")]
 use hidapi::{HidDevice, HidError};
 
+#[cfg(feature = "std")]
 pub trait HidAdapter {
     fn write(&self, data: &[u8]) -> Result<usize, HidError>;
 
+    /// Read a report sent by the device, waiting at most `timeout` milliseconds (-1 to block).
+    /// Returns the number of bytes read, which is 0 if nothing arrived in time. Adapters which
+    /// cannot receive data never return anything
+    fn read_timeout(&self, _buf: &mut [u8], _timeout: i32) -> Result<usize, HidError> {
+        Ok(0)
+    }
+
+    /// Called once every report making up a frame has been written, for adapters which act on
+    /// whole frames rather than single reports (e.g. `TerminalSimulator` redrawing the terminal)
+    fn end_frame(&self) -> Result<(), HidError> {
+        Ok(())
+    }
+
+    /// Whatever the adapter knows about the device it writes to
+    fn device_info(&self) -> DeviceInfo {
+        DeviceInfo::default()
+    }
+
     fn as_any(&self) -> &dyn Any;
 }
 
+/// Details of a device for telling keyboards apart, e.g. in logs. Anything that isn't known, such
+/// as the path of a device which isn't a real keyboard, is left as `None`
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct DeviceInfo {
+    pub manufacturer: Option<String>,
+    pub product: Option<String>,
+    pub serial_number: Option<String>,
+    pub vendor_id: Option<u16>,
+    pub product_id: Option<u16>,
+    pub path: Option<String>,
+}
+
+#[cfg(feature = "std")]
+impl From<&hidapi::DeviceInfo> for DeviceInfo {
+    fn from(info: &hidapi::DeviceInfo) -> Self {
+        Self {
+            manufacturer: info.manufacturer_string().map(str::to_string),
+            product: info.product_string().map(str::to_string),
+            serial_number: info.serial_number().map(str::to_string),
+            vendor_id: Some(info.vendor_id()),
+            product_id: Some(info.product_id()),
+            path: Some(info.path().to_string_lossy().into_owned()),
+        }
+    }
+}
+
+/// e.g. `Keychron Q1 (3434:0110, serial 1234) at /dev/hidraw3`, or `unknown device`
+#[cfg(feature = "std")]
+impl Display for DeviceInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = [&self.manufacturer, &self.product]
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join(" ");
+        let mut details = vec![];
+        if let (Some(vid), Some(pid)) = (self.vendor_id, self.product_id) {
+            details.push(format!("{vid:04x}:{pid:04x}"));
+        }
+        if let Some(serial_number) = &self.serial_number {
+            details.push(format!("serial {serial_number}"));
+        }
+
+        let mut parts = vec![];
+        match (name.is_empty(), details.is_empty()) {
+            (true, true) => parts.push("unknown device".to_string()),
+            (true, false) => parts.push(details.join(", ")),
+            (false, true) => parts.push(name),
+            (false, false) => parts.push(format!("{name} ({})", details.join(", "))),
+        }
+        if let Some(path) = &self.path {
+            parts.push(format!("at {path}"));
+        }
+        f.write_str(&parts.join(" "))
+    }
+}
+
+#[cfg(feature = "std")]
 impl HidAdapter for HidDevice {
     fn write(&self, data: &[u8]) -> Result<usize, HidError> {
         self.write(data)
     }
 
+    fn read_timeout(&self, buf: &mut [u8], timeout: i32) -> Result<usize, HidError> {
+        self.read_timeout(buf, timeout)
+    }
+
+    /// Only the strings can be queried from an open device, see `OledScreen::device_info` for the
+    /// rest
+    fn device_info(&self) -> DeviceInfo {
+        DeviceInfo {
+            manufacturer: self.get_manufacturer_string().ok().flatten(),
+            product: self.get_product_string().ok().flatten(),
+            serial_number: self.get_serial_number_string().ok().flatten(),
+            ..DeviceInfo::default()
+        }
+    }
+
     fn as_any(&self) -> &dyn Any {
         self
     }
@@ -21,31 +119,266 @@ impl HidAdapter for HidDevice {
 /// The number of bytes in a payload. Typically this is 32.
 pub const PAYLOAD_SIZE: usize = 32;
 
-#[derive(PartialEq, Clone)]
-pub(crate) struct DataPacket {
-    index: u8,
-    payload: [u8; PAYLOAD_SIZE - 2],
+/// The first byte of a report carrying part of the screen, followed by the index of the part
+pub const SCREEN_REPORT_ID: u8 = 1;
+
+/// The first byte of a report carrying part of a colour screen, followed by the index of the
+/// part as a big endian u16, see `WidePacket`
+pub const COLOR_REPORT_ID: u8 = 5;
+
+/// The first byte of a report carrying part of a 2 bit grayscale screen, laid out the same way as
+/// a colour one
+pub const GRAY_REPORT_ID: u8 = 6;
+
+/// A report prefix for sharing the raw HID interface with VIA or Vial, see
+/// `OledScreen::with_report_prefix`. Neither uses it as a command ID, so they pass reports
+/// starting with it on to `raw_hid_receive_kb` where the receiver can pick them up
+pub const VIA_REPORT_PREFIX: u8 = 0x6F;
+
+/// The number of bytes of the framebuffer sent in each packet, which is less when a report
+/// prefix takes up some of the payload
+pub fn chunk_size(report_prefix: Option<u8>) -> usize {
+    PAYLOAD_SIZE - 2 - usize::from(report_prefix.is_some())
 }
 
-impl DataPacket {
-    pub fn to_bytes(&self) -> Vec<u8> {
-        let mut bytes = vec![1, self.index];
-        bytes.extend_from_slice(&self.payload);
-        bytes
+/// Write a report to a device, starting it with the report prefix if there is one. Reports are
+/// cut off at `PAYLOAD_SIZE`
+#[cfg(feature = "std")]
+pub(crate) fn write_report(
+    device: &dyn HidAdapter,
+    report_prefix: Option<u8>,
+    report: &[u8],
+) -> Result<usize, HidError> {
+    match report_prefix {
+        Some(prefix) => {
+            let length = (report.len() + 1).min(PAYLOAD_SIZE);
+            let mut bytes = [0; PAYLOAD_SIZE];
+            bytes[0] = prefix;
+            bytes[1..length].copy_from_slice(&report[..length - 1]);
+            device.write(&bytes[..length])
+        }
+        None => device.write(report),
     }
+}
 
-    pub fn send(&self, device: &dyn HidAdapter) -> Result<(), HidError> {
-        let bytes = self.to_bytes();
+/// Split an encoded frame into the packets needed to update a screen which is showing
+/// `prev_frame`, skipping chunks which haven't changed. Every chunk is sent if there is no
+/// previous frame. Returns `None` if the frame needs more packets than an index can count
+pub fn frame_packets<'a>(
+    frame: &'a [u8],
+    prev_frame: Option<&'a [u8]>,
+    chunk_size: usize,
+) -> Option<impl Iterator<Item = DataPacket<'a>>> {
+    if frame.len().div_ceil(chunk_size) > usize::from(u8::MAX) + 1 {
+        return None;
+    }
 
-        device.write(&bytes)?;
+    Some(
+        changed_chunks(frame, prev_frame, chunk_size)
+            .map(|(index, chunk)| DataPacket::new(index as u8, chunk)),
+    )
+}
 
-        Ok(())
+/// The number of bytes of a colour or grayscale frame sent in each packet. Always a whole number
+/// of pixels at either depth, so the firmware can draw each packet as it arrives
+pub fn wide_chunk_size(report_prefix: Option<u8>) -> usize {
+    (PAYLOAD_SIZE - 3 - usize::from(report_prefix.is_some())) & !1
+}
+
+/// Split a frame encoded by `ColorFramebuffer::encode_into` or `GrayFramebuffer::as_bytes` into
+/// packets with the given report ID, in the same way as `frame_packets`. These frames are much
+/// larger, so packets have a 16 bit index. Returns `None` if the frame needs more packets than
+/// that can count
+pub fn wide_frame_packets<'a>(
+    report_id: u8,
+    frame: &'a [u8],
+    prev_frame: Option<&'a [u8]>,
+    chunk_size: usize,
+) -> Option<impl Iterator<Item = WidePacket<'a>>> {
+    if frame.len().div_ceil(chunk_size) > usize::from(u16::MAX) + 1 {
+        return None;
     }
 
-    pub fn new(starting_index: u8, payload: [u8; PAYLOAD_SIZE - 2]) -> Self {
+    Some(
+        changed_chunks(frame, prev_frame, chunk_size)
+            .map(move |(index, chunk)| WidePacket::new(report_id, index as u16, chunk)),
+    )
+}
+
+/// Send the parts of an encoded colour or grayscale frame which differ from `prev_frame`, then
+/// keep it in `prev_frame` for next time. If sending fails part way through, `prev_frame` is
+/// cleared so that all of the next frame is sent. Returns how many bytes were written
+#[cfg(feature = "std")]
+pub(crate) fn send_wide_frame(
+    device: &dyn HidAdapter,
+    report_prefix: Option<u8>,
+    report_id: u8,
+    frame: &[u8],
+    prev_frame: &mut Option<Vec<u8>>,
+) -> Result<usize, HidError> {
+    let packets = wide_frame_packets(
+        report_id,
+        frame,
+        prev_frame.as_deref(),
+        wide_chunk_size(report_prefix),
+    )
+    .ok_or_else(|| HidError::HidApiError {
+        message: "The screen is too large to send, it needs more than 65536 packets".into(),
+    })?;
+
+    let mut report = [0; PAYLOAD_SIZE];
+    let (mut written, mut failure) = (0, None);
+    for packet in packets {
+        packet.write_to(&mut report);
+        match write_report(device, report_prefix, &report) {
+            Ok(length) => written += length,
+            Err(error) => {
+                failure = Some(error);
+                break;
+            }
+        }
+    }
+    if let Some(error) = failure {
+        *prev_frame = None;
+        return Err(error);
+    }
+
+    let prev_frame = prev_frame.get_or_insert_with(Vec::new);
+    prev_frame.clear();
+    prev_frame.extend_from_slice(frame);
+    Ok(written)
+}
+
+/// The chunks of a frame along with their index, leaving out any which are the same in
+/// `prev_frame`
+fn changed_chunks<'a>(
+    frame: &'a [u8],
+    prev_frame: Option<&'a [u8]>,
+    chunk_size: usize,
+) -> impl Iterator<Item = (usize, &'a [u8])> {
+    frame
+        .chunks(chunk_size)
+        .enumerate()
+        .filter(move |(index, chunk)| {
+            prev_frame
+                .is_none_or(|prev_frame| prev_frame[index * chunk_size..][..chunk.len()] != **chunk)
+        })
+}
+
+/// Part of the screen, sent as `| 1 | INDEX | DATA... |`
+pub struct DataPacket<'a> {
+    index: u8,
+    payload: &'a [u8],
+}
+
+impl<'a> DataPacket<'a> {
+    pub fn new(index: u8, payload: &'a [u8]) -> Self {
+        Self { index, payload }
+    }
+
+    /// Write the packet into a report, padding the rest of it with zeroes. Payloads which don't
+    /// fit in the report are cut off
+    pub fn write_to(&self, report: &mut [u8; PAYLOAD_SIZE]) {
+        let length = self.payload.len().min(PAYLOAD_SIZE - 2);
+        report[0] = SCREEN_REPORT_ID;
+        report[1] = self.index;
+        report[2..2 + length].copy_from_slice(&self.payload[..length]);
+        report[2 + length..].fill(0);
+    }
+}
+
+/// Part of a colour or grayscale screen, sent as `| REPORT ID | INDEX (u16 BE) | PIXELS... |`.
+/// Packet `n` starts at byte `n * wide_chunk_size` of the frame, which runs along each row from
+/// the top left of the screen
+pub struct WidePacket<'a> {
+    report_id: u8,
+    index: u16,
+    payload: &'a [u8],
+}
+
+impl<'a> WidePacket<'a> {
+    pub fn new(report_id: u8, index: u16, payload: &'a [u8]) -> Self {
         Self {
-            index: starting_index,
+            report_id,
+            index,
             payload,
         }
     }
+
+    /// Write the packet into a report, padding the rest of it with zeroes. Payloads which don't
+    /// fit in the report are cut off
+    pub fn write_to(&self, report: &mut [u8; PAYLOAD_SIZE]) {
+        let length = self.payload.len().min(PAYLOAD_SIZE - 3);
+        report[0] = self.report_id;
+        report[1..3].copy_from_slice(&self.index.to_be_bytes());
+        report[3..3 + length].copy_from_slice(&self.payload[..length]);
+        report[3 + length..].fill(0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_frame_packets() {
+        let prev_frame = [0; 70];
+        let mut frame = prev_frame;
+        frame[65] = 1;
+
+        let all: Vec<_> = frame_packets(&frame, None, 30).unwrap().collect();
+        assert_eq!(all.len(), 3);
+
+        let changed: Vec<_> = frame_packets(&frame, Some(&prev_frame), 30)
+            .unwrap()
+            .collect();
+        assert_eq!(changed.len(), 1);
+        let mut report = [0xFF; PAYLOAD_SIZE];
+        changed[0].write_to(&mut report);
+        assert_eq!(report[..8], [SCREEN_REPORT_ID, 2, 0, 0, 0, 0, 0, 1]);
+        assert_eq!(report[12..], [0; 20]);
+
+        assert!(frame_packets(&[0; 257], None, 1).is_none());
+    }
+
+    #[test]
+    fn test_wide_frame_packets() {
+        assert_eq!(wide_chunk_size(None), 28);
+        assert_eq!(wide_chunk_size(Some(VIA_REPORT_PREFIX)), 28);
+
+        let prev_frame = vec![0; 28 * 300];
+        let mut frame = prev_frame.clone();
+        frame[28 * 299] = 0xAB;
+
+        let packets = |prev_frame| wide_frame_packets(COLOR_REPORT_ID, &frame, prev_frame, 28);
+        assert_eq!(packets(None).unwrap().count(), 300);
+        let changed: Vec<_> = packets(Some(&prev_frame)).unwrap().collect();
+        assert_eq!(changed.len(), 1);
+        let mut report = [0xFF; PAYLOAD_SIZE];
+        changed[0].write_to(&mut report);
+        assert_eq!(report[..5], [COLOR_REPORT_ID, 1, 43, 0xAB, 0]);
+        assert_eq!(report[31], 0);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_device_info_display() {
+        let mut info = DeviceInfo::default();
+        assert_eq!(info.to_string(), "unknown device");
+
+        info.path = Some("/dev/hidraw3".into());
+        info.vendor_id = Some(0x3434);
+        info.product_id = Some(0x110);
+        assert_eq!(info.to_string(), "3434:0110 at /dev/hidraw3");
+
+        info.manufacturer = Some("Keychron".into());
+        info.product = Some("Q1".into());
+        info.serial_number = Some("1234".into());
+        assert_eq!(
+            info.to_string(),
+            "Keychron Q1 (3434:0110, serial 1234) at /dev/hidraw3"
+        );
+    }
 }
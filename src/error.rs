@@ -0,0 +1,58 @@
+use std::fmt::Display;
+use std::io;
+
+use hidapi::HidError;
+use image::ImageError;
+
+/// Everything that can go wrong while drawing, such as an image or font failing to load.
+///
+/// Talking to the device still fails with a `HidError`, which converts into this so that both can
+/// be handled with `?` in the same function
+#[derive(Debug)]
+pub enum Error {
+    Hid(HidError),
+    Image(ImageError),
+    Io(io::Error),
+    /// A font file which couldn't be parsed
+    Font(String),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Hid(error) => write!(f, "{error}"),
+            Error::Image(error) => write!(f, "Failed to load image: {error}"),
+            Error::Io(error) => write!(f, "{error}"),
+            Error::Font(error) => write!(f, "Failed to load font: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Hid(error) => Some(error),
+            Error::Image(error) => Some(error),
+            Error::Io(error) => Some(error),
+            Error::Font(_) => None,
+        }
+    }
+}
+
+impl From<HidError> for Error {
+    fn from(error: HidError) -> Self {
+        Error::Hid(error)
+    }
+}
+
+impl From<ImageError> for Error {
+    fn from(error: ImageError) -> Self {
+        Error::Image(error)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Error::Io(error)
+    }
+}
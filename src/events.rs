@@ -0,0 +1,111 @@
+/// The first byte of a report sent by the keyboard to report an event. Screen payloads (and their
+/// echoes) always start with 1, so the two can share the same raw HID endpoint
+pub const EVENT_REPORT_ID: u8 = 2;
+
+pub(crate) const ENCODER_EVENT: u8 = 1;
+pub(crate) const KEYCODE_EVENT: u8 = 2;
+pub(crate) const KEYPRESS_EVENT: u8 = 3;
+pub(crate) const LAYER_EVENT: u8 = 4;
+pub(crate) const LOCK_EVENT: u8 = 5;
+
+/// An input event sent from the keyboard to the host.
+///
+/// Events are encoded as `| 2 | TYPE | ARGS... |`, see the client snippet in the README for the
+/// firmware side
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyboardEvent {
+    /// A rotary encoder was turned one step
+    Encoder { index: u8, clockwise: bool },
+    /// A custom keycode was pressed. The keycode is sent big-endian
+    Keycode(u16),
+    /// Any key was pressed. Used for typing statistics such as WPM
+    Keypress,
+    /// The highest active layer changed
+    Layer(u8),
+    /// The host changed the keyboard's lock LEDs. Sent as QMK's `led_t` bitmask
+    Locks { num: bool, caps: bool, scroll: bool },
+}
+
+impl KeyboardEvent {
+    /// Decode an event from a report read from the device. Returns `None` if the report is not an
+    /// event (e.g. it is the echo of a screen payload) or is malformed
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        match bytes {
+            [EVENT_REPORT_ID, ENCODER_EVENT, index, clockwise, ..] => Some(Self::Encoder {
+                index: *index,
+                clockwise: *clockwise != 0,
+            }),
+            [EVENT_REPORT_ID, KEYCODE_EVENT, high, low, ..] => {
+                Some(Self::Keycode(u16::from_be_bytes([*high, *low])))
+            }
+            [EVENT_REPORT_ID, KEYPRESS_EVENT, ..] => Some(Self::Keypress),
+            [EVENT_REPORT_ID, LAYER_EVENT, layer, ..] => Some(Self::Layer(*layer)),
+            [EVENT_REPORT_ID, LOCK_EVENT, leds, ..] => Some(Self::Locks {
+                num: leds & 0b001 != 0,
+                caps: leds & 0b010 != 0,
+                scroll: leds & 0b100 != 0,
+            }),
+            _ => None,
+        }
+    }
+
+    /// The encoder that was turned and which way, as 1 for a clockwise step or -1 for an
+    /// anticlockwise one. Handy for adjusting a value such as the volume. `None` for other events
+    pub fn encoder_steps(&self) -> Option<(u8, i32)> {
+        match *self {
+            Self::Encoder { index, clockwise } => Some((index, if clockwise { 1 } else { -1 })),
+            _ => None,
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            Self::Encoder { index, clockwise } => {
+                vec![EVENT_REPORT_ID, ENCODER_EVENT, *index, *clockwise as u8]
+            }
+            Self::Keycode(keycode) => {
+                let [high, low] = keycode.to_be_bytes();
+                vec![EVENT_REPORT_ID, KEYCODE_EVENT, high, low]
+            }
+            Self::Keypress => vec![EVENT_REPORT_ID, KEYPRESS_EVENT],
+            Self::Layer(layer) => vec![EVENT_REPORT_ID, LAYER_EVENT, *layer],
+            Self::Locks { num, caps, scroll } => {
+                let leds = *num as u8 | (*caps as u8) << 1 | (*scroll as u8) << 2;
+                vec![EVENT_REPORT_ID, LOCK_EVENT, leds]
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_round_trip() {
+        let events = [
+            KeyboardEvent::Encoder {
+                index: 1,
+                clockwise: true,
+            },
+            KeyboardEvent::Keycode(0x7E01),
+            KeyboardEvent::Keypress,
+            KeyboardEvent::Layer(3),
+            KeyboardEvent::Locks {
+                num: true,
+                caps: false,
+                scroll: true,
+            },
+        ];
+
+        for event in events {
+            assert_eq!(KeyboardEvent::from_bytes(&event.to_bytes()), Some(event));
+        }
+    }
+
+    #[test]
+    fn test_payload_echo_is_not_an_event() {
+        assert_eq!(KeyboardEvent::from_bytes(&[1, 0, 255, 255]), None);
+        assert_eq!(KeyboardEvent::from_bytes(&[EVENT_REPORT_ID]), None);
+    }
+}
@@ -0,0 +1,285 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use fontdue::{Font, FontSettings};
+
+use crate::error::Error;
+use crate::screen::Glyph;
+
+/// The first bytes of a subset saved with `FontSubset::to_bytes`, ending with the format version
+const MAGIC: &[u8; 4] = b"QOF\x01";
+
+/// The characters from a font which a program actually draws, already rasterized at the size it
+/// draws them. Embedding one of these rather than the whole font keeps small daemons and exported
+/// assets small: make one with `qmk-oled subset-font`, which prints it as a Rust byte array, and
+/// load it with `from_bytes`:
+///
+/// ```
+/// use qmk_oled_api::font_subset::FontSubset;
+/// use qmk_oled_api::screen::TextStyle;
+///
+/// let font = std::fs::read("assets/cozette.ttf").unwrap();
+/// let bytes = FontSubset::from_ttf(&font, 13.0, "0123456789:").unwrap().to_bytes();
+///
+/// let subset = FontSubset::from_bytes(&bytes).unwrap();
+/// let style = TextStyle::new(13.0).with_subset(&subset);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct FontSubset {
+    glyphs: BTreeMap<char, Glyph>,
+}
+
+impl FontSubset {
+    /// Rasterize the given characters from a TrueType or OpenType font at a size, in the same
+    /// way as `OledScreen::draw_text` would. Fails if the font doesn't have one of them
+    pub fn from_ttf(font: &[u8], size: f32, characters: &str) -> Result<Self, Error> {
+        let font = Font::from_bytes(font, FontSettings::default())
+            .map_err(|error| Error::Font(error.to_string()))?;
+
+        let mut glyphs = BTreeMap::new();
+        for letter in characters.chars().filter(|letter| !letter.is_control()) {
+            if font.lookup_glyph_index(letter) == 0 {
+                return Err(Error::Font(format!("the font has no glyph for {letter:?}")));
+            }
+
+            let (metrics, bitmap) = font.rasterize(letter, size);
+            let glyph = Glyph {
+                width: metrics.width,
+                height: metrics.height,
+                pixels: bitmap
+                    .iter()
+                    .map(|byte| (*byte as f32 / 255.0).round() as i32 == 1)
+                    .collect(),
+                advance: Some(metrics.advance_width.round().max(0.0) as usize),
+            };
+            glyphs.insert(letter, glyph);
+        }
+        Ok(Self { glyphs })
+    }
+
+    /// Take the given characters from a BDF bitmap font, at the size it was drawn at. Fails if
+    /// the font doesn't have one of them
+    pub fn from_bdf(font: &str, characters: &str) -> Result<Self, Error> {
+        let mut wanted: Vec<char> = characters
+            .chars()
+            .filter(|letter| !letter.is_control())
+            .collect();
+        wanted.sort_unstable();
+        wanted.dedup();
+
+        let mut glyphs = BTreeMap::new();
+        let mut lines = font.lines().map(str::trim);
+        let (mut letter, mut advance, mut size) = (None, None, (0, 0));
+        while let Some(line) = lines.next() {
+            let mut words = line.split_whitespace();
+            match words.next() {
+                Some("ENCODING") => {
+                    letter = words
+                        .next()
+                        .and_then(|code| code.parse().ok())
+                        .and_then(char::from_u32);
+                }
+                Some("DWIDTH") => advance = words.next().and_then(|width| width.parse().ok()),
+                Some("BBX") => {
+                    let mut numbers = words.map(|number| number.parse::<usize>());
+                    size = match (numbers.next(), numbers.next()) {
+                        (Some(Ok(width)), Some(Ok(height))) => (width, height),
+                        _ => return Err(Error::Font(format!("invalid BDF bounding box {line:?}"))),
+                    };
+                }
+                Some("BITMAP") => {
+                    let (width, height) = size;
+                    let mut pixels = Vec::with_capacity(width * height);
+                    for _ in 0..height {
+                        // Each row is in hex, padded out to whole bytes
+                        let hex = lines.next().unwrap_or_default();
+                        let bits = hex.len() * 4;
+                        let row = u128::from_str_radix(hex, 16)
+                            .ok()
+                            .filter(|_| width <= bits)
+                            .ok_or_else(|| {
+                                Error::Font(format!("invalid BDF bitmap row {hex:?}"))
+                            })?;
+                        pixels.extend((0..width).map(|x| row >> (bits - 1 - x) & 1 == 1));
+                    }
+
+                    if let Some(letter) = letter.filter(|letter| wanted.contains(letter)) {
+                        let glyph = Glyph {
+                            width,
+                            height,
+                            pixels,
+                            advance,
+                        };
+                        glyphs.insert(letter, glyph);
+                    }
+                    (letter, advance) = (None, None);
+                }
+                _ => (),
+            }
+        }
+
+        if let Some(missing) = wanted.iter().find(|letter| !glyphs.contains_key(letter)) {
+            return Err(Error::Font(format!(
+                "the font has no glyph for {missing:?}"
+            )));
+        }
+        Ok(Self { glyphs })
+    }
+
+    /// Load a subset saved with `to_bytes`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let invalid = || Error::Font("not a font subset, or a truncated one".into());
+        let mut bytes = bytes.strip_prefix(MAGIC).ok_or_else(invalid)?;
+
+        let mut glyphs = BTreeMap::new();
+        while !bytes.is_empty() {
+            let [a, b, c, d, width, height, advance, rest @ ..] = bytes else {
+                return Err(invalid());
+            };
+            let letter =
+                char::from_u32(u32::from_be_bytes([*a, *b, *c, *d])).ok_or_else(invalid)?;
+            let (width, height) = (usize::from(*width), usize::from(*height));
+            let length = (width * height).div_ceil(8);
+            if rest.len() < length {
+                return Err(invalid());
+            }
+
+            let pixels = (0..width * height)
+                .map(|index| rest[index / 8] & (0x80 >> (index % 8)) != 0)
+                .collect();
+            let glyph = Glyph {
+                width,
+                height,
+                pixels,
+                advance: Some(usize::from(*advance)),
+            };
+            glyphs.insert(letter, glyph);
+            bytes = &rest[length..];
+        }
+        Ok(Self { glyphs })
+    }
+
+    /// The subset in a compact binary form, for embedding and loading again with `from_bytes`.
+    /// After a magic number, each glyph is stored as its character (u32 BE), width, height and
+    /// advance (a byte each), then its pixels packed eight to a byte in rows from the top. Glyphs
+    /// bigger than 255 pixels either way are cut down to that
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        for (letter, glyph) in &self.glyphs {
+            let (width, height) = (glyph.width.min(255), glyph.height.min(255));
+            let advance = glyph.advance.unwrap_or(glyph.width + 1).min(255);
+            bytes.extend_from_slice(&u32::from(*letter).to_be_bytes());
+            bytes.extend_from_slice(&[width as u8, height as u8, advance as u8]);
+
+            let mut packed = vec![0; (width * height).div_ceil(8)];
+            for y in 0..height {
+                for x in 0..width {
+                    if glyph.pixels[y * glyph.width + x] {
+                        let index = y * width + x;
+                        packed[index / 8] |= 0x80 >> (index % 8);
+                    }
+                }
+            }
+            bytes.extend_from_slice(&packed);
+        }
+        bytes
+    }
+
+    /// The subset as Rust source for a static byte array, to paste into a program and load with
+    /// `from_bytes`
+    pub fn to_rust_array(&self, name: &str) -> String {
+        let characters: String = self.glyphs.keys().collect();
+        let mut array = String::new();
+        // Writing to a string can't fail
+        let _ = writeln!(
+            array,
+            "/// A font subset with the characters {characters:?}"
+        );
+        let _ = writeln!(array, "pub static {name}: &[u8] = &[");
+        for line in self.to_bytes().chunks(16) {
+            array.push_str("   ");
+            for byte in line {
+                let _ = write!(array, " {byte:#04x},");
+            }
+            array.push('\n');
+        }
+        array.push_str("];\n");
+        array
+    }
+
+    /// Every glyph in the subset
+    pub fn glyphs(&self) -> &BTreeMap<char, Glyph> {
+        &self.glyphs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BDF: &str = "STARTFONT 2.1
+CHARS 2
+STARTCHAR A
+ENCODING 65
+DWIDTH 4 0
+BBX 3 2 0 0
+BITMAP
+40
+A0
+ENDCHAR
+STARTCHAR B
+ENCODING 66
+DWIDTH 4 0
+BBX 3 1 0 0
+BITMAP
+E0
+ENDCHAR
+ENDFONT
+";
+
+    #[test]
+    fn test_from_bdf() {
+        let subset = FontSubset::from_bdf(BDF, "AA").unwrap();
+        assert_eq!(subset.glyphs().len(), 1);
+        let glyph = &subset.glyphs()[&'A'];
+        assert_eq!(
+            glyph,
+            &Glyph::from_ascii(
+                "
+                .#.
+                #.#
+                "
+            )
+            .with_advance(4)
+        );
+
+        let error = FontSubset::from_bdf(BDF, "AC").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Failed to load font: the font has no glyph for 'C'"
+        );
+    }
+
+    #[test]
+    fn test_from_ttf() {
+        let font = std::fs::read("assets/cozette.ttf").unwrap();
+        let subset = FontSubset::from_ttf(&font, 13.0, "ab\nb").unwrap();
+        assert_eq!(subset.glyphs().keys().collect::<String>(), "ab");
+        assert!(subset.glyphs()[&'a'].pixels.contains(&true));
+    }
+
+    #[test]
+    fn test_bytes_round_trip() {
+        let font = std::fs::read("assets/cozette.ttf").unwrap();
+        let subset = FontSubset::from_ttf(&font, 13.0, "0123456789:").unwrap();
+        let bytes = subset.to_bytes();
+        assert_eq!(FontSubset::from_bytes(&bytes).unwrap(), subset);
+
+        assert!(FontSubset::from_bytes(b"QOF\x01\0\0\0A\x08\x08\x04\xFF").is_err());
+        assert!(FontSubset::from_bytes(b"nope").is_err());
+        assert!(FontSubset::from_bdf(BDF, "B")
+            .unwrap()
+            .to_rust_array("DIGITS")
+            .starts_with("/// A font subset with the characters \"B\"\npub static DIGITS: &[u8] = &[\n    0x51, 0x4f, 0x46, 0x01, 0x00, 0x00, 0x00, 0x42, 0x03, 0x01, 0x04, 0xe0,\n];"));
+    }
+}
@@ -0,0 +1,324 @@
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::Write;
+use core::ops::{Bound, Range, RangeBounds};
+
+use crate::geometry::{line_points, outline, Point, Rect};
+use crate::utils::{get_bit_at_index, set_bit_at_index};
+
+/// How the framebuffer is laid out when it is sent to the keyboard
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BufferLayout {
+    /// Strips eight pixels wide running from the bottom of the screen to the top, left to right,
+    /// with the least significant bit of each byte on the left. This is how `Framebuffer` stores
+    /// the pixels, so the firmware has to remap it before drawing it
+    #[default]
+    Columns,
+    /// The layout `oled_write_raw` expects: pages eight pixels tall running from the top of the
+    /// screen to the bottom, each made up of a byte per column from left to right with the least
+    /// significant bit at the top. The firmware can draw frames sent like this as they arrive
+    OledWriteRaw,
+}
+
+impl BufferLayout {
+    /// Convert a framebuffer laid out the way `Framebuffer` stores it into this layout, writing
+    /// it into a buffer of the same size
+    pub fn encode_into(&self, data: &[u8], width: usize, height: usize, out: &mut [u8]) {
+        match self {
+            BufferLayout::Columns => out.copy_from_slice(data),
+            BufferLayout::OledWriteRaw => {
+                for (index, byte) in out.iter_mut().enumerate() {
+                    let (page, x) = (index / width, index % width);
+                    *byte = 0;
+                    for bit in 0..8 {
+                        let from_top = page * 8 + bit;
+                        if from_top < height && buffer_pixel(data, height, x, height - 1 - from_top)
+                        {
+                            *byte |= 1 << bit;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Convert a framebuffer in this layout back into the layout `Framebuffer` stores it in
+    pub fn decode(&self, data: &[u8], width: usize, height: usize) -> Vec<u8> {
+        match self {
+            BufferLayout::Columns => data.to_vec(),
+            BufferLayout::OledWriteRaw => {
+                let mut decoded = vec![0; data.len()];
+                for (index, byte) in data.iter().enumerate() {
+                    let (page, x) = (index / width, index % width);
+                    for bit in 0..8 {
+                        let from_top = page * 8 + bit;
+                        if from_top < height && byte & (1 << bit) != 0 {
+                            let target_byte = (x / 8) * height + height - 1 - from_top;
+                            let target_bit = 7 - ((x % 8) as u8);
+                            decoded[target_byte] =
+                                set_bit_at_index(decoded[target_byte], target_bit, true);
+                        }
+                    }
+                }
+                decoded
+            }
+        }
+    }
+}
+
+/// Whether a pixel is enabled in a framebuffer laid out the way `Framebuffer` stores it
+pub(crate) fn buffer_pixel(data: &[u8], height: usize, x: usize, y: usize) -> bool {
+    let byte_index = (x / 8) * height + y;
+    let bit_index: u8 = 7 - ((x % 8) as u8);
+
+    get_bit_at_index(data[byte_index], bit_index)
+}
+
+/// The pixels of a screen and the primitives for drawing them, without anything that needs a
+/// device or the standard library. `OledScreen` draws with one of these, and it can be used on
+/// its own (e.g. on a microcontroller acting as the host) along with `data::frame_packets` to
+/// send frames in the same format
+///
+/// The origin is in the bottom left, so the top of the screen is at the highest y coordinate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Framebuffer {
+    width: usize,
+    height: usize,
+    pub(crate) data: Vec<u8>,
+}
+
+impl Framebuffer {
+    /// A framebuffer for a screen of the given size with every pixel off
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            data: vec![0; (width * height) / 8],
+        }
+    }
+
+    /// The width of the screen in pixels
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// The height of the screen in pixels
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// The pixels as they are stored, in `BufferLayout::Columns`
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Set every byte of the framebuffer to the given value. Each byte covers a strip of eight
+    /// pixels side by side, so e.g. `0b01010101` draws vertical stripes
+    pub fn fill(&mut self, byte: u8) {
+        self.data.fill(byte);
+    }
+
+    /// Set every byte of the given rows of the framebuffer to a value, in the same way as
+    /// `fill`. Rows outside of the screen are ignored
+    pub fn fill_rows(&mut self, rows: impl RangeBounds<usize>, value: u8) {
+        let rows = self.row_range(rows);
+        if rows.is_empty() {
+            return;
+        }
+        for strip in self.data.chunks_mut(self.height) {
+            strip[rows.clone()].fill(value);
+        }
+    }
+
+    /// The rows covered by a range, cut off at the top of the screen
+    pub(crate) fn row_range(&self, rows: impl RangeBounds<usize>) -> Range<usize> {
+        let start = match rows.start_bound() {
+            Bound::Included(&start) => start,
+            Bound::Excluded(&start) => start + 1,
+            Bound::Unbounded => 0,
+        };
+        let end = match rows.end_bound() {
+            Bound::Included(&end) => end + 1,
+            Bound::Excluded(&end) => end,
+            Bound::Unbounded => self.height,
+        }
+        .min(self.height);
+
+        start..end
+    }
+
+    /// Set all pixels to the given state
+    pub fn fill_pixels(&mut self, enabled: bool) {
+        self.fill(if enabled { 0xFF } else { 0 });
+    }
+
+    /// Fill the screen with random pixels, which are always the same for a given seed. Shows
+    /// up pixels which are stuck on or off, and makes a frame which is easy to compare against
+    pub fn fill_noise(&mut self, seed: u64) {
+        // splitmix64, which is enough for noise and gives different output for every seed
+        let mut state = seed;
+        for chunk in self.data.chunks_mut(8) {
+            state = state.wrapping_add(0x9E3779B97F4A7C15);
+            let mut z = state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+            z ^= z >> 31;
+            chunk.copy_from_slice(&z.to_le_bytes()[..chunk.len()]);
+        }
+    }
+
+    /// Fill the screen with alternating squares `cell` pixels wide, with a lit one in the bottom
+    /// left corner. Single pixels show up any bits lost or duplicated along the way, and 8 pixel
+    /// squares line up with the bytes sent to the device, showing whether they end up in the
+    /// right place
+    pub fn fill_checkerboard(&mut self, cell: usize) {
+        let cell = cell.max(1);
+        for x in 0..self.width {
+            for y in 0..self.height {
+                self.set_pixel(x, y, (x / cell + y / cell).is_multiple_of(2));
+            }
+        }
+    }
+
+    /// Light the outermost pixels of the screen, leaving the rest as they are. Shows whether the
+    /// configured size matches the panel
+    pub fn fill_border(&mut self) {
+        self.draw_rect(Rect::new(0, 0, self.width, self.height));
+    }
+
+    /// Paint a square region
+    pub fn paint_region(&mut self, region: Rect, enabled: bool) {
+        for x in region.min_x()..region.max_x() {
+            for y in region.min_y()..region.max_y() {
+                self.set_pixel(x, y, enabled)
+            }
+        }
+    }
+
+    /// Flip the state of every pixel in a square region
+    pub fn invert_region(&mut self, region: Rect) {
+        for x in region.min_x()..region.max_x().min(self.width) {
+            for y in region.min_y()..region.max_y().min(self.height) {
+                let enabled = self.get_pixel(x, y);
+                self.set_pixel(x, y, !enabled)
+            }
+        }
+    }
+
+    /// Draw a one pixel wide straight line between two points, including both ends
+    pub fn draw_line(&mut self, from: Point, to: Point) {
+        for point in line_points(from, to) {
+            self.set_pixel(point.x, point.y, true);
+        }
+    }
+
+    /// Draw the outline of a square region, use `paint_region` to fill it in instead
+    pub fn draw_rect(&mut self, region: Rect) {
+        if region.is_empty() {
+            return;
+        }
+
+        for (from, to) in outline(region) {
+            self.draw_line(from, to);
+        }
+    }
+
+    /// Get the current state of a pixel
+    pub fn get_pixel(&self, x: usize, y: usize) -> bool {
+        buffer_pixel(&self.data, self.height, x, y)
+    }
+
+    /// The frame as a C array in the layout `oled_write_raw_P` expects, for drawing it from the
+    /// firmware without a host (e.g. as a boot logo)
+    pub fn to_qmk_array(&self, name: &str) -> String {
+        let mut encoded = vec![0; self.data.len()];
+        BufferLayout::OledWriteRaw.encode_into(&self.data, self.width, self.height, &mut encoded);
+
+        let mut array = String::new();
+        // Writing to a string can't fail
+        let _ = writeln!(array, "static const char PROGMEM {name}[] = {{");
+        for line in encoded.chunks(16) {
+            array.push_str("   ");
+            for byte in line {
+                let _ = write!(array, " {byte:#04x},");
+            }
+            array.push('\n');
+        }
+        array.push_str("};\n");
+        array
+    }
+
+    /// Set the state of a pixel. Pixels outside of the screen are ignored
+    pub fn set_pixel(&mut self, x: usize, y: usize, enabled: bool) {
+        if x >= self.width || y >= self.height {
+            return;
+        }
+
+        let target_byte = (x / 8) * self.height + y;
+        let target_bit: u8 = 7 - ((x % 8) as u8);
+
+        self.data[target_byte] = set_bit_at_index(self.data[target_byte], target_bit, enabled);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_framebuffer_pixels() {
+        let mut framebuffer = Framebuffer::new(16, 8);
+        framebuffer.set_pixel(9, 2, true);
+        framebuffer.set_pixel(16, 0, true);
+
+        assert!(framebuffer.get_pixel(9, 2));
+        assert_eq!(framebuffer.as_bytes()[10], 0b00000010);
+        assert_eq!(
+            framebuffer
+                .as_bytes()
+                .iter()
+                .filter(|&&byte| byte != 0)
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_fill_patterns() {
+        let mut framebuffer = Framebuffer::new(16, 4);
+        framebuffer.fill_checkerboard(2);
+        assert!(framebuffer.get_pixel(1, 1));
+        assert!(!framebuffer.get_pixel(2, 1));
+        assert!(framebuffer.get_pixel(2, 2));
+
+        framebuffer.fill(0);
+        framebuffer.fill_border();
+        assert!(framebuffer.get_pixel(15, 3));
+        assert!(framebuffer.get_pixel(0, 2));
+        assert!(!framebuffer.get_pixel(1, 1));
+
+        let mut noise = Framebuffer::new(16, 4);
+        noise.fill_noise(7);
+        let mut same_seed = Framebuffer::new(16, 4);
+        same_seed.fill_noise(7);
+        let mut other_seed = Framebuffer::new(16, 4);
+        other_seed.fill_noise(8);
+        assert_eq!(noise, same_seed);
+        assert_ne!(noise, other_seed);
+        assert!(noise.as_bytes().iter().any(|&byte| byte != 0));
+    }
+
+    #[test]
+    fn test_to_qmk_array() {
+        let mut framebuffer = Framebuffer::new(8, 8);
+        framebuffer.set_pixel(0, 7, true);
+        framebuffer.set_pixel(1, 0, true);
+
+        assert_eq!(
+            framebuffer.to_qmk_array("logo"),
+            "static const char PROGMEM logo[] = {\n    \
+             0x01, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,\n};\n"
+        );
+    }
+}
@@ -0,0 +1,186 @@
+/// A position on the screen, relative to its origin
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Point {
+    pub x: usize,
+    pub y: usize,
+}
+
+impl Point {
+    pub fn new(x: usize, y: usize) -> Self {
+        Self { x, y }
+    }
+}
+
+/// The width and height of something in pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Size {
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Size {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self { width, height }
+    }
+}
+
+/// A rectangular region of the screen. `origin` is the corner nearest the screen's origin, and
+/// the region covers every pixel from it up to (but not including) `origin + size`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Rect {
+    pub origin: Point,
+    pub size: Size,
+}
+
+impl Rect {
+    pub fn new(x: usize, y: usize, width: usize, height: usize) -> Self {
+        Self {
+            origin: Point::new(x, y),
+            size: Size::new(width, height),
+        }
+    }
+
+    /// The region from `min` up to (but not including) `max`. Empty if `max` isn't past `min`
+    pub fn from_corners(min: Point, max: Point) -> Self {
+        Self {
+            origin: min,
+            size: Size::new(max.x.saturating_sub(min.x), max.y.saturating_sub(min.y)),
+        }
+    }
+
+    pub fn min_x(&self) -> usize {
+        self.origin.x
+    }
+
+    pub fn min_y(&self) -> usize {
+        self.origin.y
+    }
+
+    /// The first x past the right of the region
+    pub fn max_x(&self) -> usize {
+        self.origin.x + self.size.width
+    }
+
+    /// The first y past the far edge of the region from the origin
+    pub fn max_y(&self) -> usize {
+        self.origin.y + self.size.height
+    }
+
+    pub fn width(&self) -> usize {
+        self.size.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.size.height
+    }
+
+    /// Whether the region doesn't cover any pixels
+    pub fn is_empty(&self) -> bool {
+        self.size.width == 0 || self.size.height == 0
+    }
+
+    /// Whether the given pixel is inside the region. Useful for hit-testing
+    pub fn contains(&self, point: Point) -> bool {
+        (self.min_x()..self.max_x()).contains(&point.x)
+            && (self.min_y()..self.max_y()).contains(&point.y)
+    }
+
+    /// The region shrunk by `amount` on every side, e.g. for the inside of a border. Regions too
+    /// small to shrink that far end up empty, centered where they were
+    pub fn inset(&self, amount: usize) -> Self {
+        let inset_x = amount.min(self.size.width / 2);
+        let inset_y = amount.min(self.size.height / 2);
+
+        Self {
+            origin: Point::new(self.origin.x + inset_x, self.origin.y + inset_y),
+            size: Size::new(
+                self.size.width.saturating_sub(amount * 2),
+                self.size.height.saturating_sub(amount * 2),
+            ),
+        }
+    }
+
+    /// The pixel in the middle of the region, rounded towards the origin
+    pub fn center(&self) -> Point {
+        Point::new(
+            self.origin.x + self.size.width.saturating_sub(1) / 2,
+            self.origin.y + self.size.height.saturating_sub(1) / 2,
+        )
+    }
+}
+
+/// Every pixel on a one pixel wide straight line between two points, including both ends
+pub fn line_points(from: Point, to: Point) -> impl Iterator<Item = Point> {
+    let (mut x, mut y) = (from.x as isize, from.y as isize);
+    let (x2, y2) = (to.x as isize, to.y as isize);
+    let dx = (x2 - x).abs();
+    let dy = -(y2 - y).abs();
+    let step_x = if x < x2 { 1 } else { -1 };
+    let step_y = if y < y2 { 1 } else { -1 };
+    let mut error = dx + dy;
+    let mut done = false;
+
+    core::iter::from_fn(move || {
+        if done {
+            return None;
+        }
+        let point = Point::new(x as usize, y as usize);
+        if x == x2 && y == y2 {
+            done = true;
+            return Some(point);
+        }
+
+        let doubled_error = 2 * error;
+        if doubled_error >= dy {
+            error += dy;
+            x += step_x;
+        }
+        if doubled_error <= dx {
+            error += dx;
+            y += step_y;
+        }
+        Some(point)
+    })
+}
+
+/// The four lines around the outside of a region, as the ends of each line
+pub(crate) fn outline(region: Rect) -> [(Point, Point); 4] {
+    let (min_x, min_y) = (region.min_x(), region.min_y());
+    let (max_x, max_y) = (region.max_x() - 1, region.max_y() - 1);
+    [
+        (Point::new(min_x, min_y), Point::new(max_x, min_y)),
+        (Point::new(min_x, max_y), Point::new(max_x, max_y)),
+        (Point::new(min_x, min_y), Point::new(min_x, max_y)),
+        (Point::new(max_x, min_y), Point::new(max_x, max_y)),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_rect() {
+        let rect = Rect::from_corners(Point::new(2, 4), Point::new(10, 8));
+        assert_eq!(rect, Rect::new(2, 4, 8, 4));
+        assert_eq!((rect.max_x(), rect.max_y()), (10, 8));
+        assert_eq!(rect.center(), Point::new(5, 5));
+        assert!(rect.contains(Point::new(9, 4)));
+        assert!(!rect.contains(Point::new(10, 4)));
+
+        assert_eq!(rect.inset(1), Rect::new(3, 5, 6, 2));
+        assert!(rect.inset(2).is_empty());
+        assert_eq!(rect.inset(3).origin, Point::new(5, 6));
+        assert!(Rect::from_corners(Point::new(3, 3), Point::new(1, 5)).is_empty());
+    }
+
+    #[test]
+    fn test_line_points() {
+        let points: Vec<_> = line_points(Point::new(3, 0), Point::new(0, 1))
+            .map(|point| (point.x, point.y))
+            .collect();
+        assert_eq!(points, [(3, 0), (2, 0), (1, 1), (0, 1)]);
+        assert_eq!(line_points(Point::new(2, 2), Point::new(2, 2)).count(), 1);
+    }
+}
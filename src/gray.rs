@@ -0,0 +1,189 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::framebuffer::Framebuffer;
+use crate::geometry::{line_points, outline, Point, Rect};
+
+/// The number of shades a `GrayFramebuffer` pixel can be, from 0 (off) to 3 (fully lit)
+pub const GRAY_LEVELS: u8 = 4;
+
+/// The number of monochrome frames `GrayFramebuffer::temporal_frame` cycles through
+pub const TEMPORAL_PHASES: usize = GRAY_LEVELS as usize - 1;
+
+/// The nearest level to an 8 bit brightness, e.g. a pixel of a grayscale image
+pub fn level_from_luma(luma: u8) -> u8 {
+    ((u16::from(luma) * u16::from(GRAY_LEVELS - 1) + 127) / 255) as u8
+}
+
+/// The pixels of a screen with four shades of gray and the primitives for drawing them, the 2
+/// bit counterpart to `Framebuffer`. `GrayScreen` sends one of these to firmware which can show
+/// the shades itself, and `temporal_frame` turns it into black and white frames which give the
+/// same shades on any OLED when shown one after the other
+///
+/// The origin is in the bottom left, the same as `Framebuffer`. Pixels are stored the way they
+/// are sent: rows from the top of the screen to the bottom, four pixels to a byte with the
+/// leftmost in the most significant bits. Each row starts on a new byte.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GrayFramebuffer {
+    width: usize,
+    height: usize,
+    data: Vec<u8>,
+}
+
+impl GrayFramebuffer {
+    /// A framebuffer for a screen of the given size with every pixel off
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            data: vec![0; width.div_ceil(4) * height],
+        }
+    }
+
+    /// The width of the screen in pixels
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// The height of the screen in pixels
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// The pixels as they are stored and sent
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Set every pixel to the given level
+    pub fn fill(&mut self, level: u8) {
+        let level = level.min(GRAY_LEVELS - 1);
+        self.data.fill(level * 0b01010101);
+    }
+
+    /// Paint a square region
+    pub fn paint_region(&mut self, region: Rect, level: u8) {
+        for x in region.min_x()..region.max_x().min(self.width) {
+            for y in region.min_y()..region.max_y().min(self.height) {
+                self.set_pixel(x, y, level)
+            }
+        }
+    }
+
+    /// Draw a one pixel wide straight line between two points, including both ends
+    pub fn draw_line(&mut self, from: Point, to: Point, level: u8) {
+        for point in line_points(from, to) {
+            self.set_pixel(point.x, point.y, level);
+        }
+    }
+
+    /// Draw the outline of a square region, use `paint_region` to fill it in instead
+    pub fn draw_rect(&mut self, region: Rect, level: u8) {
+        if region.is_empty() {
+            return;
+        }
+
+        for (from, to) in outline(region) {
+            self.draw_line(from, to, level);
+        }
+    }
+
+    /// Paint the lit pixels of a monochrome framebuffer at a level, with its bottom left corner at
+    /// `at`. Unlit pixels are left as they are, see `ColorFramebuffer::draw_mask`
+    pub fn draw_mask(&mut self, mask: &Framebuffer, at: Point, level: u8) {
+        for x in 0..mask.width() {
+            for y in 0..mask.height() {
+                if mask.get_pixel(x, y) {
+                    self.set_pixel(at.x + x, at.y + y, level);
+                }
+            }
+        }
+    }
+
+    /// One of the `TEMPORAL_PHASES` black and white frames which, shown one after the other,
+    /// give every pixel its shade. A pixel at level `n` is lit in `n` of them, so a screen showing
+    /// them in turn quickly enough looks like it has four shades
+    pub fn temporal_frame(&self, phase: usize) -> Framebuffer {
+        let phase = (phase % TEMPORAL_PHASES) as u8;
+        let mut frame = Framebuffer::new(self.width, self.height);
+        for x in 0..self.width {
+            for y in 0..self.height {
+                frame.set_pixel(x, y, self.get_pixel(x, y) > phase);
+            }
+        }
+        frame
+    }
+
+    /// Get the current level of a pixel. Pixels outside of the screen read as off
+    pub fn get_pixel(&self, x: usize, y: usize) -> u8 {
+        match self.index(x, y) {
+            Some((byte, shift)) => (self.data[byte] >> shift) & 0b11,
+            None => 0,
+        }
+    }
+
+    /// Set the level of a pixel, with anything past the brightest level set to it. Pixels
+    /// outside of the screen are ignored
+    pub fn set_pixel(&mut self, x: usize, y: usize, level: u8) {
+        if let Some((byte, shift)) = self.index(x, y) {
+            let level = level.min(GRAY_LEVELS - 1);
+            self.data[byte] = (self.data[byte] & !(0b11 << shift)) | (level << shift);
+        }
+    }
+
+    /// The byte a pixel is stored in and how far up it the pixel's bits are, flipping y as rows
+    /// are stored from the top
+    fn index(&self, x: usize, y: usize) -> Option<(usize, u8)> {
+        (x < self.width && y < self.height).then(|| {
+            let row = self.height - 1 - y;
+            let byte = row * self.width.div_ceil(4) + x / 4;
+            (byte, 6 - 2 * (x % 4) as u8)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gray_framebuffer() {
+        let mut framebuffer = GrayFramebuffer::new(6, 2);
+        assert_eq!(framebuffer.as_bytes().len(), 4);
+
+        framebuffer.set_pixel(1, 1, 2);
+        framebuffer.set_pixel(5, 0, 9);
+        framebuffer.set_pixel(6, 0, 3);
+        assert_eq!(framebuffer.get_pixel(1, 1), 2);
+        assert_eq!(framebuffer.get_pixel(5, 0), 3);
+        assert_eq!(framebuffer.as_bytes(), [0b00100000, 0, 0, 0b00110000]);
+
+        framebuffer.fill(1);
+        assert_eq!(framebuffer.as_bytes(), [0b01010101; 4]);
+        assert_eq!((level_from_luma(0), level_from_luma(128)), (0, 2));
+        assert_eq!(level_from_luma(255), 3);
+    }
+
+    #[test]
+    fn test_temporal_frames() {
+        let mut framebuffer = GrayFramebuffer::new(8, 1);
+        for level in 0..GRAY_LEVELS {
+            framebuffer.set_pixel(level as usize, 0, level);
+        }
+
+        let lit: Vec<Vec<bool>> = (0..TEMPORAL_PHASES)
+            .map(|phase| {
+                let frame = framebuffer.temporal_frame(phase);
+                (0..4).map(|x| frame.get_pixel(x, 0)).collect()
+            })
+            .collect();
+        assert_eq!(
+            lit,
+            [
+                [false, true, true, true],
+                [false, false, true, true],
+                [false, false, false, true],
+            ]
+        );
+    }
+}
@@ -0,0 +1,120 @@
+use image::{Pixel, Rgb};
+
+use crate::data::GRAY_REPORT_ID;
+use crate::geometry::{Point, Rect};
+use crate::gray::{level_from_luma, GrayFramebuffer};
+use crate::wide_screen::{WideFramebuffer, WideScreen};
+
+/// A screen with four shades of gray, for firmware driving a panel which can show them (e.g. an
+/// SSD1327 through Quantum Painter). Frames are sent two bits to a pixel in `GRAY_REPORT_ID`
+/// reports, and like `OledScreen` only the parts which changed since the last frame are sent.
+///
+/// On a black and white OLED the same shades can be faked by sending the frames from
+/// `GrayFramebuffer::temporal_frame` to an `OledScreen` one after the other:
+///
+/// ```no_run
+/// use std::ffi::CString;
+/// use qmk_oled_api::geometry::Rect;
+/// use qmk_oled_api::gray::{GrayFramebuffer, TEMPORAL_PHASES};
+/// use qmk_oled_api::screen::OledScreen;
+///
+/// let mut shades = GrayFramebuffer::new(32, 128);
+/// shades.paint_region(Rect::new(0, 0, 32, 64), 1);
+/// shades.paint_region(Rect::new(0, 64, 32, 64), 2);
+///
+/// let path = CString::new("/dev/hidraw0").unwrap();
+/// let mut screen = OledScreen::from_path(&path, 32, 128).unwrap();
+/// for phase in (0..TEMPORAL_PHASES).cycle() {
+///     *screen.framebuffer_mut() = shades.temporal_frame(phase);
+///     screen.send().unwrap();
+/// }
+/// ```
+pub type GrayScreen = WideScreen<GrayFramebuffer>;
+
+impl WideFramebuffer for GrayFramebuffer {
+    /// The level of a pixel, from 0 (off) to 3 (fully lit)
+    type Pixel = u8;
+
+    const REPORT_ID: u8 = GRAY_REPORT_ID;
+
+    fn new(width: usize, height: usize) -> Self {
+        GrayFramebuffer::new(width, height)
+    }
+
+    fn width(&self) -> usize {
+        GrayFramebuffer::width(self)
+    }
+
+    fn height(&self) -> usize {
+        GrayFramebuffer::height(self)
+    }
+
+    fn encode<'a>(&'a self, _buffer: &'a mut Vec<u8>) -> &'a [u8] {
+        self.as_bytes()
+    }
+
+    fn pixel_from_rgb(red: u8, green: u8, blue: u8) -> u8 {
+        level_from_luma(Rgb([red, green, blue]).to_luma().0[0])
+    }
+
+    fn fill(&mut self, level: u8) {
+        GrayFramebuffer::fill(self, level);
+    }
+
+    fn paint_region(&mut self, region: Rect, level: u8) {
+        GrayFramebuffer::paint_region(self, region, level);
+    }
+
+    fn draw_line(&mut self, from: Point, to: Point, level: u8) {
+        GrayFramebuffer::draw_line(self, from, to, level);
+    }
+
+    fn draw_rect(&mut self, region: Rect, level: u8) {
+        GrayFramebuffer::draw_rect(self, region, level);
+    }
+
+    fn get_pixel(&self, x: usize, y: usize) -> u8 {
+        GrayFramebuffer::get_pixel(self, x, y)
+    }
+
+    fn set_pixel(&mut self, x: usize, y: usize, level: u8) {
+        GrayFramebuffer::set_pixel(self, x, y, level);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockDevice;
+    use image::{DynamicImage, GrayImage, Luma};
+
+    #[test]
+    fn test_gray_screen_send() {
+        let device = MockDevice::new();
+        let mut screen = GrayScreen::from_device(device.clone(), 128, 64).unwrap();
+
+        screen.send().unwrap();
+        assert_eq!(
+            device.take_writes().len(),
+            (128 * 64 / 4_usize).div_ceil(28)
+        );
+
+        screen.set_pixel(0, 63, 3);
+        screen.set_pixel(127, 0, 1);
+        screen.send().unwrap();
+        let writes = device.take_writes();
+        assert_eq!(writes.len(), 2);
+        assert_eq!(writes[0][..4], [GRAY_REPORT_ID, 0, 0, 0b11000000]);
+        assert_eq!(writes[1][1..3], 73_u16.to_be_bytes());
+    }
+
+    #[test]
+    fn test_gray_screen_draw_image() {
+        let image = GrayImage::from_fn(4, 1, |x, _| Luma([(x * 85) as u8]));
+
+        let mut screen = GrayScreen::from_device(MockDevice::new(), 4, 2).unwrap();
+        screen.draw_image(&DynamicImage::ImageLuma8(image), 0, 1);
+        let levels: Vec<_> = (0..4).map(|x| screen.get_pixel(x, 1)).collect();
+        assert_eq!(levels, [0, 1, 2, 3]);
+    }
+}
@@ -0,0 +1,172 @@
+use std::ops::{Deref, DerefMut};
+
+use hidapi::HidError;
+
+use crate::control::ControlMessage;
+use crate::screen::OledScreen;
+
+/// Shows the same frame on several screens, e.g. two keyboards on one desk.
+///
+/// Draw on the group as if it were a single screen, it derefs to the first one. `send` copies
+/// the frame to the rest before sending it to each of them, and each screen keeps track of what
+/// its own device is showing, so only the parts which changed on a device are sent to it. Screens
+/// keep their own settings, so they can use different layouts or report prefixes:
+///
+/// ```no_run
+/// use std::ffi::CString;
+/// use qmk_oled_api::geometry::Point;
+/// use qmk_oled_api::group::ScreenGroup;
+/// use qmk_oled_api::screen::OledScreen;
+///
+/// let left = OledScreen::from_path(&CString::new("/dev/hidraw0").unwrap(), 32, 128).unwrap();
+/// let right = OledScreen::from_path(&CString::new("/dev/hidraw1").unwrap(), 32, 128).unwrap();
+/// let mut group = ScreenGroup::new(vec![left, right]).unwrap();
+///
+/// group.draw_line(Point::new(0, 0), Point::new(31, 127));
+/// group.send().unwrap();
+/// ```
+pub struct ScreenGroup {
+    /// Never empty, and every screen is the same size
+    screens: Vec<OledScreen>,
+}
+
+impl ScreenGroup {
+    /// Group screens together, drawing on the first. Fails if there are no screens or they aren't
+    /// all the same size
+    pub fn new(screens: Vec<OledScreen>) -> Result<Self, HidError> {
+        let mut screens = screens.into_iter();
+        let first = screens.next().ok_or_else(|| HidError::HidApiError {
+            message: "A screen group needs at least one screen".into(),
+        })?;
+
+        let mut group = Self {
+            screens: vec![first],
+        };
+        for screen in screens {
+            group.push(screen)?;
+        }
+        Ok(group)
+    }
+
+    /// Add another screen to the group. It is sent the whole frame the next time the group is
+    /// sent. Fails if it isn't the same size as the rest
+    pub fn push(&mut self, screen: OledScreen) -> Result<(), HidError> {
+        let size = (self.width(), self.height());
+        if (screen.width(), screen.height()) != size {
+            return Err(HidError::HidApiError {
+                message: format!(
+                    "Screen is {}x{} but the group is {}x{}",
+                    screen.width(),
+                    screen.height(),
+                    size.0,
+                    size.1
+                ),
+            });
+        }
+
+        self.screens.push(screen);
+        Ok(())
+    }
+
+    /// Every screen in the group, starting with the one drawn on
+    pub fn screens(&self) -> &[OledScreen] {
+        &self.screens
+    }
+
+    /// Send the frame to every screen. A device failing doesn't stop the frame being sent to the
+    /// others, the first error is returned once they have all been tried
+    pub fn send(&mut self) -> Result<(), HidError> {
+        let (first, rest) = self.screens.split_first_mut().unwrap();
+        for screen in rest.iter_mut() {
+            screen
+                .framebuffer_mut()
+                .data
+                .copy_from_slice(first.framebuffer().as_bytes());
+        }
+
+        let mut result = Ok(());
+        for screen in &mut self.screens {
+            if let Err(error) = screen.send() {
+                result = result.and(Err(error));
+            }
+        }
+        result
+    }
+
+    /// Send a control command to every screen, see `OledScreen::send_control`. Like `send`, every
+    /// screen is tried even if one fails
+    pub fn send_control(&self, message: &ControlMessage) -> Result<(), HidError> {
+        let mut result = Ok(());
+        for screen in &self.screens {
+            if let Err(error) = screen.send_control(message) {
+                result = result.and(Err(error));
+            }
+        }
+        result
+    }
+}
+
+impl Deref for ScreenGroup {
+    type Target = OledScreen;
+
+    fn deref(&self) -> &OledScreen {
+        &self.screens[0]
+    }
+}
+
+impl DerefMut for ScreenGroup {
+    fn deref_mut(&mut self) -> &mut OledScreen {
+        &mut self.screens[0]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::Point;
+    use crate::testing::MockDevice;
+
+    #[test]
+    fn test_group_mirrors_frames() {
+        let (left, right) = (MockDevice::new(), MockDevice::new());
+        let mut group = ScreenGroup::new(vec![
+            OledScreen::from_device(left.clone(), 32, 128).unwrap(),
+            OledScreen::from_device(right.clone(), 32, 128).unwrap(),
+        ])
+        .unwrap();
+
+        group.draw_line(Point::new(0, 0), Point::new(31, 127));
+        group.send().unwrap();
+        let sent = left.take_writes();
+        assert_eq!(right.take_writes(), sent);
+        assert_eq!(group.screens()[1].to_braille(), group.to_braille());
+
+        // Each device is only sent what changed for it, including one added part way through
+        let late = MockDevice::new();
+        group
+            .push(OledScreen::from_device(late.clone(), 32, 128).unwrap())
+            .unwrap();
+        group.set_pixel(0, 127, true);
+        group.send().unwrap();
+        assert_eq!(left.take_writes().len(), 1);
+        assert_eq!(right.take_writes().len(), 1);
+        assert_eq!(late.take_writes().len(), sent.len());
+    }
+
+    #[test]
+    fn test_group_sizes() {
+        assert!(ScreenGroup::new(vec![]).is_err());
+
+        let mut group = ScreenGroup::new(vec![
+            OledScreen::from_device(MockDevice::new(), 32, 128).unwrap()
+        ])
+        .unwrap();
+        let error = group
+            .push(OledScreen::from_device(MockDevice::new(), 32, 64).unwrap())
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "hidapi error: Screen is 32x64 but the group is 32x128"
+        );
+    }
+}
@@ -0,0 +1,325 @@
+use std::fmt::Display;
+use std::time::{Duration, Instant};
+
+use hidapi::HidError;
+
+use crate::data::PAYLOAD_SIZE;
+use crate::screen::OledScreen;
+
+/// The first byte of a handshake report, sent by the host with the protocol version it speaks as
+/// `| 4 | MAJOR | MINOR |`. The firmware answers with its own version followed by the size of
+/// its screen as big-endian 16 bit numbers and its `oled_rotation_t`:
+/// `| 4 | MAJOR | MINOR | WIDTH | HEIGHT | ROTATION | CAPABILITIES |`, where `CAPABILITIES` is a
+/// bitmask of `Capabilities`. Firmware older than 1.2 leaves the size out, and older than 1.3
+/// leaves the capabilities out
+pub const HANDSHAKE_REPORT_ID: u8 = 4;
+
+/// The version of the protocol spoken by this crate. The firmware must speak the same major
+/// version, see the module in the repository's `qmk` directory for the firmware side
+pub const PROTOCOL_VERSION: ProtocolVersion = ProtocolVersion { major: 1, minor: 3 };
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProtocolVersion {
+    pub major: u8,
+    pub minor: u8,
+}
+
+impl ProtocolVersion {
+    /// Whether firmware speaking this version understands everything sent by this crate
+    pub fn is_compatible(&self) -> bool {
+        self.major == PROTOCOL_VERSION.major
+    }
+}
+
+impl Display for ProtocolVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+/// Which way round the firmware draws its screen, in the same order as QMK's `oled_rotation_t`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Rotation {
+    #[default]
+    R0,
+    R90,
+    R180,
+    R270,
+}
+
+impl Rotation {
+    fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(Self::R0),
+            1 => Some(Self::R90),
+            2 => Some(Self::R180),
+            3 => Some(Self::R270),
+            _ => None,
+        }
+    }
+}
+
+/// The size of the screen as the firmware reported it during the handshake, see
+/// `OledScreen::detect_size`. The width and height are those of the frames it expects, which
+/// already take the rotation into account
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScreenGeometry {
+    pub width: usize,
+    pub height: usize,
+    pub rotation: Rotation,
+}
+
+pub(crate) const BRIGHTNESS_CAPABILITY: u8 = 1 << 0;
+pub(crate) const EVENTS_CAPABILITY: u8 = 1 << 1;
+pub(crate) const COMPRESSION_CAPABILITY: u8 = 1 << 2;
+pub(crate) const MULTI_SCREEN_CAPABILITY: u8 = 1 << 3;
+pub(crate) const QUANTUM_PAINTER_CAPABILITY: u8 = 1 << 4;
+
+/// Optional parts of the protocol which the firmware supports, as it advertised them during the
+/// handshake. `OledScreen` skips anything the firmware says it doesn't support, so the same host
+/// works with keymaps which leave things out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities {
+    /// Brightness and power commands, see `ControlMessage`
+    pub brightness: bool,
+    /// Sending keyboard events to the host, see `OledScreen::poll_event`
+    pub events: bool,
+    /// Receiving compressed frames. This version of the crate never compresses them
+    pub compression: bool,
+    /// Receiving frames for more than one screen. This version of the crate only sends one
+    pub multi_screen: bool,
+    /// Drawing colour frames on a Quantum Painter display rather than the OLED driver, see
+    /// `FrameProtocol::QuantumPainter`
+    pub quantum_painter: bool,
+}
+
+impl Capabilities {
+    /// What firmware which doesn't advertise its capabilities (older than 1.3) is assumed to
+    /// support, which is everything the protocol had before then. Also used before the handshake
+    pub const ASSUMED: Self = Self {
+        brightness: true,
+        events: true,
+        compression: false,
+        multi_screen: false,
+        quantum_painter: false,
+    };
+
+    pub(crate) fn from_byte(flags: u8) -> Self {
+        Self {
+            brightness: flags & BRIGHTNESS_CAPABILITY != 0,
+            events: flags & EVENTS_CAPABILITY != 0,
+            compression: flags & COMPRESSION_CAPABILITY != 0,
+            multi_screen: flags & MULTI_SCREEN_CAPABILITY != 0,
+            quantum_painter: flags & QUANTUM_PAINTER_CAPABILITY != 0,
+        }
+    }
+
+    pub(crate) fn to_byte(self) -> u8 {
+        [
+            (self.brightness, BRIGHTNESS_CAPABILITY),
+            (self.events, EVENTS_CAPABILITY),
+            (self.compression, COMPRESSION_CAPABILITY),
+            (self.multi_screen, MULTI_SCREEN_CAPABILITY),
+            (self.quantum_painter, QUANTUM_PAINTER_CAPABILITY),
+        ]
+        .into_iter()
+        .filter(|(supported, _)| *supported)
+        .fold(0, |flags, (_, flag)| flags | flag)
+    }
+}
+
+/// Everything the firmware said in answer to a handshake
+pub(crate) struct Answer {
+    pub version: ProtocolVersion,
+    /// `None` if the firmware is too old to report it
+    pub geometry: Option<ScreenGeometry>,
+    pub capabilities: Capabilities,
+}
+
+impl Answer {
+    /// The size of the screen, failing if the firmware is too old to report it
+    pub fn geometry(&self) -> Result<ScreenGeometry, HidError> {
+        self.geometry.ok_or_else(|| HidError::HidApiError {
+            message: format!(
+                "The keyboard speaks version {} of the protocol, which doesn't report the size of \
+                 the screen. Update the receiver in your keymap or give the size yourself",
+                self.version
+            ),
+        })
+    }
+}
+
+/// Ask the firmware which version of the protocol it speaks and what it supports, waiting up to
+/// `timeout` for an answer. Fails with a description of the problem if it doesn't answer or
+/// isn't compatible
+pub(crate) fn handshake(screen: &OledScreen, timeout: Duration) -> Result<Answer, HidError> {
+    let (version, answer) = exchange(screen, timeout)?;
+
+    let width = u16::from_be_bytes([answer[3], answer[4]]) as usize;
+    let height = u16::from_be_bytes([answer[5], answer[6]]) as usize;
+    let geometry = match Rotation::from_byte(answer[7]) {
+        Some(rotation) if width > 0 && height > 0 => Some(ScreenGeometry {
+            width,
+            height,
+            rotation,
+        }),
+        _ => None,
+    };
+    let capabilities = match version.minor {
+        0..3 => Capabilities::ASSUMED,
+        _ => Capabilities::from_byte(answer[8]),
+    };
+
+    Ok(Answer {
+        version,
+        geometry,
+        capabilities,
+    })
+}
+
+/// Send a handshake and wait for the answer, returning the firmware's version along with the
+/// whole answer (padded with zeroes) for anything else it reported
+fn exchange(
+    screen: &OledScreen,
+    timeout: Duration,
+) -> Result<(ProtocolVersion, [u8; PAYLOAD_SIZE]), HidError> {
+    let mut request = vec![
+        HANDSHAKE_REPORT_ID,
+        PROTOCOL_VERSION.major,
+        PROTOCOL_VERSION.minor,
+    ];
+    request.resize(PAYLOAD_SIZE, 0);
+    screen.write_report(&request)?;
+
+    let deadline = Instant::now() + timeout;
+    let mut buf = [0; PAYLOAD_SIZE];
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let report = screen.read_report(&mut buf, remaining.as_millis() as i32)?;
+
+        match report {
+            Some(answer @ &[HANDSHAKE_REPORT_ID, major, minor, ..]) => {
+                let version = ProtocolVersion { major, minor };
+                if version.is_compatible() {
+                    let mut padded = [0; PAYLOAD_SIZE];
+                    let length = answer.len().min(PAYLOAD_SIZE);
+                    padded[..length].copy_from_slice(&answer[..length]);
+                    return Ok((version, padded));
+                }
+                return Err(HidError::HidApiError {
+                    message: format!(
+                        "The keyboard speaks version {version} of the protocol but this version \
+                         of qmk-oled-api needs {}.x. Update the receiver in your keymap to match",
+                        PROTOCOL_VERSION.major
+                    ),
+                });
+            }
+            // Anything else, such as a keyboard event, isn't the answer so keep waiting
+            Some(_) if !remaining.is_zero() => (),
+            _ => {
+                return Err(HidError::HidApiError {
+                    message: "The keyboard didn't answer the handshake. Make sure the receiver \
+                              in your keymap is up to date"
+                        .into(),
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::KeyboardEvent;
+    use crate::testing::MockDevice;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_handshake() {
+        let device = MockDevice::new();
+        device.queue_read(KeyboardEvent::Keypress.to_bytes());
+        device.queue_read([HANDSHAKE_REPORT_ID, PROTOCOL_VERSION.major, 7]);
+        let screen = OledScreen::from_device(device.clone(), 32, 128).unwrap();
+
+        let version = screen.handshake(Duration::from_millis(10)).unwrap();
+        assert_eq!(version.minor, 7);
+        assert_eq!(device.writes()[0][..3], [HANDSHAKE_REPORT_ID, 1, 3]);
+    }
+
+    #[test]
+    fn test_query_geometry() {
+        let device = MockDevice::new();
+        device.queue_read([HANDSHAKE_REPORT_ID, 1, 2, 0, 32, 0, 128, 3]);
+        device.queue_read([HANDSHAKE_REPORT_ID, 1, 1]);
+        let screen = OledScreen::from_device(device, 128, 32).unwrap();
+
+        let answer = handshake(&screen, Duration::from_millis(10)).unwrap();
+        assert_eq!(
+            answer.geometry().unwrap(),
+            ScreenGeometry {
+                width: 32,
+                height: 128,
+                rotation: Rotation::R270
+            }
+        );
+
+        let answer = handshake(&screen, Duration::from_millis(10)).unwrap();
+        let error = answer.geometry().unwrap_err();
+        assert!(error.to_string().contains("version 1.1 of the protocol"));
+        assert_eq!(answer.capabilities, Capabilities::ASSUMED);
+    }
+
+    #[test]
+    fn test_capabilities() {
+        let device = MockDevice::new();
+        let flags = BRIGHTNESS_CAPABILITY | COMPRESSION_CAPABILITY | QUANTUM_PAINTER_CAPABILITY;
+        device.queue_read([HANDSHAKE_REPORT_ID, 1, 3, 0, 32, 0, 128, 0, flags]);
+        let screen = OledScreen::from_device(device, 32, 128).unwrap();
+
+        let capabilities = handshake(&screen, Duration::from_millis(10))
+            .unwrap()
+            .capabilities;
+        assert_eq!(
+            capabilities,
+            Capabilities {
+                brightness: true,
+                events: false,
+                compression: true,
+                multi_screen: false,
+                quantum_painter: true,
+            }
+        );
+        assert_eq!(capabilities.to_byte(), flags);
+    }
+
+    #[test]
+    fn test_handshake_incompatible() {
+        let device = MockDevice::new();
+        device.queue_read([HANDSHAKE_REPORT_ID, PROTOCOL_VERSION.major + 1, 0]);
+        let screen = OledScreen::from_device(device, 32, 128).unwrap();
+
+        let error = screen.handshake(Duration::from_millis(10)).unwrap_err();
+        assert!(error.to_string().contains("version 2.0 of the protocol"));
+    }
+
+    #[test]
+    fn test_qmk_module_version() {
+        let header = include_str!("../qmk/oled_api/oled_api.h");
+
+        assert!(header.contains(&format!(
+            "#define OLED_API_PROTOCOL_MAJOR {}\n",
+            PROTOCOL_VERSION.major
+        )));
+        assert!(header.contains(&format!(
+            "#define OLED_API_PROTOCOL_MINOR {}\n",
+            PROTOCOL_VERSION.minor
+        )));
+    }
+
+    #[test]
+    fn test_handshake_no_answer() {
+        let screen = OledScreen::from_device(MockDevice::new(), 32, 128).unwrap();
+        let error = screen.handshake(Duration::from_millis(10)).unwrap_err();
+        assert!(error.to_string().contains("didn't answer"));
+    }
+}
@@ -0,0 +1,401 @@
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+pub use crate::command::Command;
+use crate::pages::Pages;
+use crate::profiles::Profiles;
+use crate::screen::OledScreen;
+use crate::widgets::Pomodoro;
+use crate::widgets::Widget;
+
+/// How long a client can go without sending anything before it is disconnected
+const DEFAULT_CLIENT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// A server listening on a Unix socket for a simple line based drawing protocol, so that shell
+/// scripts and status bars can update the screen, e.g.
+///
+/// ```sh
+/// echo "text 0 0 8 Hello" | nc -U /tmp/qmk-oled.sock
+/// ```
+///
+/// Each line is answered with `ok` or `error: <reason>`. The screen is sent to the device once
+/// the client disconnects, so a batch of commands shows up as a single frame. Besides drawing,
+/// clients can control anything passed in with `Controls` to `serve_with` or `handle_pending`.
+///
+/// Clients are handled one at a time, and one which goes quiet for longer than the client
+/// timeout is disconnected so that it can't hold up the rest
+pub struct DrawServer {
+    listener: UnixListener,
+    path: PathBuf,
+    client_timeout: Duration,
+}
+
+impl DrawServer {
+    /// Listen on the given socket path, replacing any stale socket left behind by a previous run.
+    /// Fails rather than removing anything at the path which isn't a socket, or a socket which
+    /// another server is still listening on
+    pub fn bind<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        match fs::symlink_metadata(&path) {
+            Ok(metadata) if metadata.file_type().is_socket() => match UnixStream::connect(&path) {
+                Ok(_) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::AddrInUse,
+                        format!("another server is already listening on {}", path.display()),
+                    ))
+                }
+                Err(error) if error.kind() == io::ErrorKind::ConnectionRefused => {
+                    fs::remove_file(&path)?
+                }
+                Err(error) => return Err(error),
+            },
+            Ok(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!("{} exists and isn't a socket", path.display()),
+                ))
+            }
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {}
+            Err(error) => return Err(error),
+        }
+
+        Ok(Self {
+            listener: UnixListener::bind(&path)?,
+            path,
+            client_timeout: DEFAULT_CLIENT_TIMEOUT,
+        })
+    }
+
+    /// Disconnect clients which go quiet for longer than the given time, rather than 2 seconds
+    pub fn with_client_timeout(mut self, timeout: Duration) -> Self {
+        self.client_timeout = timeout;
+        self
+    }
+
+    /// Handle clients one at a time, forever. Only fails if the socket itself stops working
+    pub fn serve(&self, screen: &mut OledScreen) -> io::Result<()> {
+        self.serve_with(screen, &mut Controls::new())
+    }
+
+    /// Like `serve`, but also accepting the commands for each of the given controls
+    pub fn serve_with(&self, screen: &mut OledScreen, controls: &mut Controls) -> io::Result<()> {
+        self.listener.set_nonblocking(false)?;
+        for stream in self.listener.incoming() {
+            self.handle(screen, controls, stream?);
+        }
+        Ok(())
+    }
+
+    /// Handle every client which has connected since the last call, then return straight away.
+    /// Call this from a loop which also does other work, such as rotating pages and sending the
+    /// screen, in place of `serve_with`:
+    ///
+    /// ```no_run
+    /// # use std::thread;
+    /// # use std::time::Duration;
+    /// # use qmk_oled_api::ipc::{Controls, DrawServer};
+    /// # use qmk_oled_api::pages::{Page, Pages};
+    /// # use qmk_oled_api::screen::OledScreen;
+    /// # use qmk_oled_api::widgets::Widget;
+    /// # let mut screen = OledScreen::from_path(c"/dev/hidraw0", 32, 128).unwrap();
+    /// let mut pages = Pages::new(vec![Page::new("clock"), Page::new("stats")]);
+    /// let server = DrawServer::bind("/tmp/qmk-oled.sock").unwrap();
+    /// loop {
+    ///     server
+    ///         .handle_pending(&mut screen, &mut Controls::new().with_pages(&mut pages))
+    ///         .unwrap();
+    ///     if pages.update() {
+    ///         pages.draw(&mut screen).unwrap();
+    ///         screen.send().unwrap();
+    ///     }
+    ///     thread::sleep(Duration::from_millis(50));
+    /// }
+    /// ```
+    pub fn handle_pending(
+        &self,
+        screen: &mut OledScreen,
+        controls: &mut Controls,
+    ) -> io::Result<()> {
+        self.listener.set_nonblocking(true)?;
+        loop {
+            match self.listener.accept() {
+                Ok((stream, _)) => self.handle(screen, controls, stream),
+                Err(error) if error.kind() == io::ErrorKind::WouldBlock => return Ok(()),
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
+    fn handle(&self, screen: &mut OledScreen, controls: &mut Controls, stream: UnixStream) {
+        // A misbehaving client shouldn't take the server down with it
+        let _ = stream
+            .set_nonblocking(false)
+            .and_then(|()| stream.set_read_timeout(Some(self.client_timeout)))
+            .and_then(|()| stream.set_write_timeout(Some(self.client_timeout)))
+            .and_then(|()| handle_client(screen, controls, stream));
+    }
+}
+
+/// What a `DrawServer` lets clients control besides drawing. Each control adds a command, and
+/// redraws what it controls after running it:
+///
+/// - `page <name>` shows the named page
+/// - `profile <name>` switches to the named profile
+/// - `pomodoro start|pause|reset` controls a pomodoro timer
+#[derive(Default)]
+pub struct Controls<'a> {
+    pages: Option<&'a mut Pages>,
+    profiles: Option<&'a mut Profiles>,
+    pomodoro: Option<&'a mut Pomodoro>,
+}
+
+impl<'a> Controls<'a> {
+    /// No controls, so only drawing commands are accepted
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Accept `page <name>`
+    pub fn with_pages(mut self, pages: &'a mut Pages) -> Self {
+        self.pages = Some(pages);
+        self
+    }
+
+    /// Accept `profile <name>`
+    pub fn with_profiles(mut self, profiles: &'a mut Profiles) -> Self {
+        self.profiles = Some(profiles);
+        self
+    }
+
+    /// Accept `pomodoro start`, `pomodoro pause` and `pomodoro reset`
+    pub fn with_pomodoro(mut self, pomodoro: &'a mut Pomodoro) -> Self {
+        self.pomodoro = Some(pomodoro);
+        self
+    }
+
+    /// Run a line if it is one of the control commands, returning `None` if it isn't so that it
+    /// can be parsed as a drawing command instead
+    fn apply(&mut self, screen: &mut OledScreen, line: &str) -> Option<Result<(), String>> {
+        let (name, argument) = line.trim().split_once(' ')?;
+        let argument = argument.trim();
+
+        let result = match name {
+            "page" => {
+                let pages = self.pages.as_deref_mut()?;
+                if !pages.show_named(argument) {
+                    return Some(Err(format!("unknown page '{argument}'")));
+                }
+                pages.draw(screen)
+            }
+            "profile" => {
+                let profiles = self.profiles.as_deref_mut()?;
+                if !profiles.switch(argument) {
+                    return Some(Err(format!("unknown profile '{argument}'")));
+                }
+                profiles.draw(screen)
+            }
+            "pomodoro" => {
+                let pomodoro = self.pomodoro.as_deref_mut()?;
+                match argument {
+                    "start" => pomodoro.start(),
+                    "pause" => pomodoro.pause(),
+                    "reset" => pomodoro.reset(),
+                    _ => return Some(Err("pomodoro takes start, pause or reset".to_string())),
+                }
+                pomodoro.draw(screen)
+            }
+            _ => return None,
+        };
+        Some(result.map_err(|error| error.to_string()))
+    }
+}
+
+impl Drop for DrawServer {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+fn handle_client(
+    screen: &mut OledScreen,
+    controls: &mut Controls,
+    stream: UnixStream,
+) -> io::Result<()> {
+    let mut writer = stream.try_clone()?;
+
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        let result = controls
+            .apply(screen, &line)
+            .unwrap_or_else(|| Command::parse(&line).and_then(|command| command.apply(screen)));
+        match result {
+            Ok(()) => writeln!(writer, "ok")?,
+            Err(error) => writeln!(writer, "error: {error}")?,
+        }
+    }
+
+    screen
+        .send()
+        .map_err(|error| io::Error::other(error.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockDevice;
+    use std::io::Read;
+    use std::net::Shutdown;
+
+    #[test]
+    fn test_handle_client() {
+        let mut filled = OledScreen::from_device(MockDevice::new(), 32, 128).unwrap();
+        filled.fill_all();
+
+        let mut screen = OledScreen::from_device(MockDevice::new(), 32, 128).unwrap();
+        let (mut client, server) = UnixStream::pair().unwrap();
+
+        client.write_all(b"fill\nbogus\n").unwrap();
+        client.shutdown(Shutdown::Write).unwrap();
+        handle_client(&mut screen, &mut Controls::new(), server).unwrap();
+
+        let mut response = String::new();
+        client.read_to_string(&mut response).unwrap();
+        assert_eq!(response, "ok\nerror: unknown command 'bogus'\n");
+        assert_eq!(screen.snapshot(), filled.snapshot());
+    }
+
+    #[test]
+    fn test_bind_keeps_other_files() {
+        let path = std::env::temp_dir().join(format!("qmk-oled-api-bind-{}", std::process::id()));
+
+        fs::write(&path, "keep me").unwrap();
+        let error = DrawServer::bind(&path).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "keep me");
+        fs::remove_file(&path).unwrap();
+
+        // A socket left behind by a previous server is replaced
+        let stale = UnixListener::bind(&path).unwrap();
+        drop(stale);
+        let server = DrawServer::bind(&path).unwrap();
+
+        // But one which is still being listened on is left alone
+        let error = DrawServer::bind(&path).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::AddrInUse);
+        assert!(UnixStream::connect(&path).is_ok());
+
+        drop(server);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_handle_pending() {
+        let path =
+            std::env::temp_dir().join(format!("qmk-oled-api-pending-{}", std::process::id()));
+        let server = DrawServer::bind(&path)
+            .unwrap()
+            .with_client_timeout(Duration::from_millis(50));
+        let mut screen = OledScreen::from_device(MockDevice::new(), 32, 128).unwrap();
+
+        // Nothing is waiting, so this returns straight away
+        server
+            .handle_pending(&mut screen, &mut Controls::new())
+            .unwrap();
+
+        // A client which never sends anything is dropped, and the one behind it still served
+        let _idle = UnixStream::connect(&path).unwrap();
+        let mut client = UnixStream::connect(&path).unwrap();
+        client.write_all(b"fill\n").unwrap();
+        client.shutdown(Shutdown::Write).unwrap();
+        server
+            .handle_pending(&mut screen, &mut Controls::new())
+            .unwrap();
+
+        let mut response = String::new();
+        client.read_to_string(&mut response).unwrap();
+        assert_eq!(response, "ok\n");
+        assert!(screen.get_pixel(0, 0));
+    }
+
+    #[test]
+    fn test_show_page() {
+        use crate::pages::Page;
+
+        let mut pages = Pages::new(vec![Page::new("clock"), Page::new("media")]);
+        let mut screen = OledScreen::from_device(MockDevice::new(), 32, 128).unwrap();
+        let (mut client, server) = UnixStream::pair().unwrap();
+
+        client.write_all(b"page media\npage music\n").unwrap();
+        client.shutdown(Shutdown::Write).unwrap();
+        handle_client(
+            &mut screen,
+            &mut Controls::new().with_pages(&mut pages),
+            server,
+        )
+        .unwrap();
+
+        let mut response = String::new();
+        client.read_to_string(&mut response).unwrap();
+        assert_eq!(response, "ok\nerror: unknown page 'music'\n");
+        assert_eq!(pages.current().unwrap().name(), "media");
+    }
+
+    #[test]
+    fn test_switch_profile() {
+        use crate::pages::Page;
+        use crate::profiles::Profile;
+
+        let mut profiles = Profiles::new(vec![
+            Profile::new("work", Pages::new(vec![Page::new("clock")])),
+            Profile::new("gaming", Pages::new(vec![Page::new("fps")])),
+        ]);
+        let mut screen = OledScreen::from_device(MockDevice::new(), 32, 128).unwrap();
+        let (mut client, server) = UnixStream::pair().unwrap();
+
+        client
+            .write_all(b"profile gaming\nprofile music\n")
+            .unwrap();
+        client.shutdown(Shutdown::Write).unwrap();
+        handle_client(
+            &mut screen,
+            &mut Controls::new().with_profiles(&mut profiles),
+            server,
+        )
+        .unwrap();
+
+        let mut response = String::new();
+        client.read_to_string(&mut response).unwrap();
+        assert_eq!(response, "ok\nerror: unknown profile 'music'\n");
+        assert_eq!(profiles.current().unwrap().name(), "gaming");
+    }
+
+    #[test]
+    fn test_control_pomodoro() {
+        let mut pomodoro = Pomodoro::new(0, 0, 32, 64);
+        let mut screen = OledScreen::from_device(MockDevice::new(), 32, 128).unwrap();
+        let (mut client, server) = UnixStream::pair().unwrap();
+
+        client
+            .write_all(b"pomodoro start\npomodoro stop\n")
+            .unwrap();
+        client.shutdown(Shutdown::Write).unwrap();
+        handle_client(
+            &mut screen,
+            &mut Controls::new().with_pomodoro(&mut pomodoro),
+            server,
+        )
+        .unwrap();
+
+        let mut response = String::new();
+        client.read_to_string(&mut response).unwrap();
+        assert_eq!(
+            response,
+            "ok\nerror: pomodoro takes start, pause or reset\n"
+        );
+        assert!(pomodoro.is_running());
+    }
+}
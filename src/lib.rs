@@ -1,3 +1,60 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "std")]
+pub mod animation;
+#[cfg(feature = "std")]
+pub mod codegen;
+pub mod color;
+#[cfg(feature = "std")]
+pub mod color_screen;
+#[cfg(feature = "std")]
+pub mod command;
+#[cfg(feature = "std")]
+pub mod control;
 pub mod data;
+#[cfg(feature = "std")]
+pub mod error;
+#[cfg(feature = "std")]
+pub mod events;
+#[cfg(feature = "std")]
+pub mod font_subset;
+pub mod framebuffer;
+pub mod geometry;
+pub mod gray;
+#[cfg(feature = "std")]
+pub mod gray_screen;
+#[cfg(feature = "std")]
+pub mod group;
+#[cfg(feature = "std")]
+pub mod handshake;
+#[cfg(all(unix, feature = "std"))]
+pub mod ipc;
+#[cfg(feature = "std")]
+pub mod metrics;
+#[cfg(feature = "std")]
+pub mod page_machine;
+#[cfg(feature = "std")]
+pub mod pages;
+#[cfg(feature = "std")]
+pub mod profiles;
+#[cfg(feature = "std")]
+pub mod recording;
+#[cfg(feature = "std")]
 pub mod screen;
+#[cfg(feature = "std")]
+pub mod sender;
+#[cfg(feature = "std")]
+pub mod simulator;
+#[cfg(feature = "std")]
+pub mod sources;
+#[cfg(all(feature = "std", any(test, feature = "testing")))]
+pub mod testing;
 pub mod utils;
+#[cfg(target_arch = "wasm32")]
+pub mod wasm;
+#[cfg(feature = "std")]
+pub mod wide_screen;
+#[cfg(feature = "std")]
+pub mod widgets;
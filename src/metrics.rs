@@ -0,0 +1,300 @@
+use std::fmt::Write as _;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How long a scrape can stall, reading or writing, before it is given up on
+const DEFAULT_CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The most request (line and headers) read from a client, so a client can't send forever
+const MAX_REQUEST_SIZE: u64 = 8 * 1024;
+
+/// Counters for how much is being sent to a keyboard, for checking that the refresh rate isn't
+/// more than the endpoint can keep up with. Clones share the same counters, so one set can be
+/// given to a screen with `OledScreen::with_metrics` (and a `BackgroundSender`) and read from
+/// elsewhere, such as a `MetricsServer` on another thread
+#[derive(Debug, Clone)]
+pub struct Metrics {
+    counters: Arc<Counters>,
+}
+
+#[derive(Debug)]
+struct Counters {
+    started: Instant,
+    frames: AtomicU64,
+    bytes: AtomicU64,
+    dropped_frames: AtomicU64,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self {
+            counters: Arc::new(Counters {
+                started: Instant::now(),
+                frames: AtomicU64::new(0),
+                bytes: AtomicU64::new(0),
+                dropped_frames: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// Count a frame which made it to the device
+    pub fn record_frame(&self) {
+        self.counters.frames.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count bytes written to the device
+    pub fn record_bytes(&self, bytes: usize) {
+        self.counters
+            .bytes
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    /// Count a frame which was never shown, because sending it failed or a newer frame replaced
+    /// it first
+    pub fn record_dropped_frame(&self) {
+        self.counters.dropped_frames.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// The counters as they are now. Take two snapshots some time apart to work out rates
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        MetricsSnapshot {
+            frames: self.counters.frames.load(Ordering::Relaxed),
+            bytes: self.counters.bytes.load(Ordering::Relaxed),
+            dropped_frames: self.counters.dropped_frames.load(Ordering::Relaxed),
+            taken_at: Instant::now(),
+        }
+    }
+
+    /// The counters in the Prometheus text exposition format. They only ever go up, so use
+    /// `rate()` in queries for frames and bytes per second
+    pub fn to_prometheus(&self) -> String {
+        let snapshot = self.snapshot();
+        let uptime = snapshot
+            .taken_at
+            .duration_since(self.counters.started)
+            .as_secs_f64();
+
+        let mut text = String::new();
+        for (name, help, kind, value) in [
+            (
+                "qmk_oled_frames_total",
+                "Frames sent to the keyboard",
+                "counter",
+                snapshot.frames.to_string(),
+            ),
+            (
+                "qmk_oled_bytes_total",
+                "Bytes written to the keyboard, including report IDs and prefixes",
+                "counter",
+                snapshot.bytes.to_string(),
+            ),
+            (
+                "qmk_oled_dropped_frames_total",
+                "Frames which failed to send or were replaced by a newer frame before being sent",
+                "counter",
+                snapshot.dropped_frames.to_string(),
+            ),
+            (
+                "qmk_oled_uptime_seconds",
+                "Seconds since the metrics were created",
+                "gauge",
+                uptime.to_string(),
+            ),
+        ] {
+            // Writing to a string can't fail
+            let _ = write!(
+                text,
+                "# HELP {name} {help}\n# TYPE {name} {kind}\n{name} {value}\n"
+            );
+        }
+        text
+    }
+}
+
+/// The value of each counter in `Metrics` at a point in time
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MetricsSnapshot {
+    pub frames: u64,
+    pub bytes: u64,
+    pub dropped_frames: u64,
+    pub taken_at: Instant,
+}
+
+impl MetricsSnapshot {
+    /// The frames per second sent between an earlier snapshot and this one
+    pub fn frames_per_second(&self, earlier: &Self) -> f64 {
+        self.rate(earlier, |snapshot| snapshot.frames)
+    }
+
+    /// The bytes per second written between an earlier snapshot and this one
+    pub fn bytes_per_second(&self, earlier: &Self) -> f64 {
+        self.rate(earlier, |snapshot| snapshot.bytes)
+    }
+
+    fn rate(&self, earlier: &Self, counter: impl Fn(&Self) -> u64) -> f64 {
+        let seconds = self
+            .taken_at
+            .saturating_duration_since(earlier.taken_at)
+            .as_secs_f64();
+        if seconds == 0.0 {
+            return 0.0;
+        }
+        counter(self).saturating_sub(counter(earlier)) as f64 / seconds
+    }
+}
+
+/// A minimal HTTP server answering `GET /metrics` with `Metrics::to_prometheus`, for Prometheus
+/// to scrape. Run it on its own thread:
+///
+/// ```no_run
+/// use std::thread;
+/// use qmk_oled_api::metrics::{Metrics, MetricsServer};
+///
+/// let metrics = Metrics::new();
+/// let server = MetricsServer::bind("127.0.0.1:9185").unwrap();
+/// let served = metrics.clone();
+/// thread::spawn(move || server.serve(&served));
+/// ```
+pub struct MetricsServer {
+    listener: TcpListener,
+    client_timeout: Duration,
+}
+
+impl MetricsServer {
+    /// Listen on the given address
+    pub fn bind(address: impl ToSocketAddrs) -> io::Result<Self> {
+        Ok(Self {
+            listener: TcpListener::bind(address)?,
+            client_timeout: DEFAULT_CLIENT_TIMEOUT,
+        })
+    }
+
+    /// Give up on clients which stall for longer than the given time, rather than 5 seconds
+    pub fn with_client_timeout(mut self, timeout: Duration) -> Self {
+        self.client_timeout = timeout;
+        self
+    }
+
+    /// The address being listened on, e.g. to find the port after binding to port 0
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Handle requests one at a time, forever. Only fails if the socket itself stops working
+    pub fn serve(&self, metrics: &Metrics) -> io::Result<()> {
+        for stream in self.listener.incoming() {
+            // A misbehaving or stalled client shouldn't take the server down with it, and times
+            // out so the next scrape is answered
+            let stream = stream?;
+            let _ = stream
+                .set_read_timeout(Some(self.client_timeout))
+                .and_then(|()| stream.set_write_timeout(Some(self.client_timeout)))
+                .and_then(|()| handle_client(metrics, stream));
+        }
+        Ok(())
+    }
+}
+
+fn handle_client(metrics: &Metrics, stream: TcpStream) -> io::Result<()> {
+    let mut reader = BufReader::new((&stream).take(MAX_REQUEST_SIZE));
+    let mut request = String::new();
+    reader.read_line(&mut request)?;
+
+    // Read the rest of the request before answering. Closing the socket with some of it unread
+    // resets the connection, which can lose the response before the client reads it
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 0 && !header.trim_end().is_empty() {
+        header.clear();
+    }
+
+    (&stream).write_all(response(metrics, &request).as_bytes())
+}
+
+/// The full HTTP response to a request, given its first line
+fn response(metrics: &Metrics, request: &str) -> String {
+    let (status, content_type, body) = match request.split_whitespace().collect::<Vec<_>>()[..] {
+        ["GET", "/metrics", ..] => (
+            "200 OK",
+            "text/plain; version=0.0.4",
+            metrics.to_prometheus(),
+        ),
+        _ => ("404 Not Found", "text/plain", "not found\n".to_string()),
+    };
+    format!(
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_rates() {
+        let metrics = Metrics::new();
+        let earlier = metrics.snapshot();
+        metrics.record_frame();
+        metrics.record_frame();
+        metrics.record_bytes(64);
+
+        let later = MetricsSnapshot {
+            taken_at: earlier.taken_at + Duration::from_secs(2),
+            ..metrics.snapshot()
+        };
+        assert_eq!(later.frames_per_second(&earlier), 1.0);
+        assert_eq!(later.bytes_per_second(&earlier), 32.0);
+        assert_eq!(earlier.frames_per_second(&earlier), 0.0);
+    }
+
+    #[test]
+    fn test_prometheus_response() {
+        let metrics = Metrics::new();
+        metrics.record_frame();
+        metrics.record_dropped_frame();
+
+        let text = metrics.to_prometheus();
+        assert!(text.starts_with(
+            "# HELP qmk_oled_frames_total Frames sent to the keyboard\n\
+             # TYPE qmk_oled_frames_total counter\n\
+             qmk_oled_frames_total 1\n"
+        ));
+        assert!(text.contains("\nqmk_oled_dropped_frames_total 1\n"));
+
+        assert!(response(&metrics, "GET /metrics HTTP/1.1\r\n").starts_with("HTTP/1.1 200 OK"));
+        assert!(response(&metrics, "GET / HTTP/1.1\r\n").starts_with("HTTP/1.1 404"));
+    }
+
+    #[test]
+    fn test_serve() {
+        let server = MetricsServer::bind("127.0.0.1:0")
+            .unwrap()
+            .with_client_timeout(Duration::from_millis(50));
+        let address = server.local_addr().unwrap();
+        let metrics = Metrics::new();
+        metrics.record_frame();
+        thread::spawn(move || server.serve(&metrics));
+
+        // A client which connects and never sends anything only holds the server up briefly
+        let _stalled = TcpStream::connect(address).unwrap();
+
+        let mut stream = TcpStream::connect(address).unwrap();
+        stream
+            .write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\nAccept: */*\r\n\r\n")
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains("\nqmk_oled_frames_total 1\n"));
+    }
+}
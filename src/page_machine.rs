@@ -0,0 +1,310 @@
+use std::time::{Duration, Instant};
+
+use crate::error::Error;
+use crate::events::KeyboardEvent;
+use crate::framebuffer::Framebuffer;
+use crate::pages::Page;
+use crate::screen::OledScreen;
+use crate::widgets::Widget;
+
+/// An event from the keyboard which moves a `PageMachine` from one page to another
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trigger {
+    /// A custom keycode was pressed
+    Keycode(u16),
+    /// The highest active layer changed to the given one
+    Layer(u8),
+    /// An encoder was turned one step in the given direction
+    Encoder { index: u8, clockwise: bool },
+}
+
+impl Trigger {
+    /// Whether the trigger fires for an event
+    pub fn matches(&self, event: &KeyboardEvent) -> bool {
+        match (*self, *event) {
+            (Self::Keycode(keycode), KeyboardEvent::Keycode(pressed)) => keycode == pressed,
+            (Self::Layer(layer), KeyboardEvent::Layer(active)) => layer == active,
+            (
+                Self::Encoder { index, clockwise },
+                KeyboardEvent::Encoder {
+                    index: turned,
+                    clockwise: direction,
+                },
+            ) => index == turned && clockwise == direction,
+            _ => false,
+        }
+    }
+}
+
+/// How the screen changes from one page to the next
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Animation {
+    /// Show the new page straight away
+    #[default]
+    Cut,
+    /// The new page pushes the old one off to the left
+    SlideLeft,
+    /// The new page pushes the old one off to the right
+    SlideRight,
+    /// The new page pushes the old one off the top
+    SlideUp,
+    /// The new page pushes the old one off the bottom
+    SlideDown,
+    /// The new page is uncovered from left to right over the old one
+    Wipe,
+}
+
+impl Animation {
+    /// Blend two frames of the same size part of the way through the animation, where `progress`
+    /// goes from 0 (all `from`) to 1 (all `to`)
+    pub fn blend(&self, from: &Framebuffer, to: &Framebuffer, progress: f32) -> Framebuffer {
+        let (width, height) = (to.width(), to.height());
+        let progress = progress.clamp(0.0, 1.0);
+        let columns = (progress * width as f32).round() as usize;
+        let rows = (progress * height as f32).round() as usize;
+
+        let mut frame = Framebuffer::new(width, height);
+        for x in 0..width {
+            for y in 0..height {
+                // Where the pixel comes from in the combined strip of the old and new frames
+                let pixel = match self {
+                    Self::Cut => to.get_pixel(x, y),
+                    Self::SlideLeft if x + columns < width => from.get_pixel(x + columns, y),
+                    Self::SlideLeft => to.get_pixel(x + columns - width, y),
+                    Self::SlideRight if x >= columns => from.get_pixel(x - columns, y),
+                    Self::SlideRight => to.get_pixel(x + width - columns, y),
+                    Self::SlideUp if y >= rows => from.get_pixel(x, y - rows),
+                    Self::SlideUp => to.get_pixel(x, y + height - rows),
+                    Self::SlideDown if y + rows < height => from.get_pixel(x, y + rows),
+                    Self::SlideDown => to.get_pixel(x, y + rows - height),
+                    Self::Wipe if x < columns => to.get_pixel(x, y),
+                    Self::Wipe => from.get_pixel(x, y),
+                };
+                frame.set_pixel(x, y, pixel);
+            }
+        }
+        frame
+    }
+}
+
+/// A move from one page to another when a trigger fires
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transition {
+    from: Option<String>,
+    trigger: Trigger,
+    to: String,
+    animation: Animation,
+    duration: Duration,
+}
+
+impl Transition {
+    /// Move to the named page whenever the trigger fires, from any page and without an animation
+    pub fn new(trigger: Trigger, to: &str) -> Self {
+        Self {
+            from: None,
+            trigger,
+            to: to.to_string(),
+            animation: Animation::Cut,
+            duration: Duration::ZERO,
+        }
+    }
+
+    /// Only fire while the named page is being shown
+    pub fn with_from(mut self, from: &str) -> Self {
+        self.from = Some(from.to_string());
+        self
+    }
+
+    /// Animate the move over the given time
+    pub fn with_animation(mut self, animation: Animation, duration: Duration) -> Self {
+        self.animation = animation;
+        self.duration = duration;
+        self
+    }
+}
+
+/// The animation currently being played between two pages
+struct Active {
+    previous: usize,
+    animation: Animation,
+    started: Instant,
+    duration: Duration,
+}
+
+/// A set of pages where events from the keyboard move between them, in place of handling each
+/// event by hand. Transitions are checked in the order they were added and the first one which
+/// matches the event and the current page is taken:
+///
+/// ```
+/// use std::time::Duration;
+/// use qmk_oled_api::page_machine::{Animation, PageMachine, Transition, Trigger};
+/// use qmk_oled_api::pages::Page;
+///
+/// let slide = Duration::from_millis(200);
+/// let machine = PageMachine::new(vec![Page::new("home"), Page::new("numpad"), Page::new("media")])
+///     .with_transition(Transition::new(Trigger::Layer(0), "home"))
+///     .with_transition(
+///         Transition::new(Trigger::Layer(2), "numpad").with_animation(Animation::SlideUp, slide),
+///     )
+///     .with_transition(
+///         Transition::new(Trigger::Encoder { index: 0, clockwise: true }, "media")
+///             .with_from("home")
+///             .with_animation(Animation::SlideLeft, slide),
+///     );
+/// ```
+///
+/// While an animation is playing `is_animating` is true, and the screen should be drawn and sent
+/// every frame to show it
+pub struct PageMachine {
+    pages: Vec<Page>,
+    current: usize,
+    transitions: Vec<Transition>,
+    active: Option<Active>,
+}
+
+impl PageMachine {
+    /// Create a machine which starts on the first page and has no transitions
+    pub fn new(pages: Vec<Page>) -> Self {
+        Self {
+            pages,
+            current: 0,
+            transitions: vec![],
+            active: None,
+        }
+    }
+
+    /// Add a transition, which is only taken if no transition added before it matches
+    pub fn with_transition(mut self, transition: Transition) -> Self {
+        self.transitions.push(transition);
+        self
+    }
+
+    /// The page being shown, or being moved to while an animation plays
+    pub fn current(&self) -> Option<&Page> {
+        self.pages.get(self.current)
+    }
+
+    /// Whether an animation between two pages is still playing
+    pub fn is_animating(&self) -> bool {
+        self.is_animating_at(Instant::now())
+    }
+
+    /// Take the first transition matching the event from the current page. Returns whether the
+    /// page changed
+    pub fn handle_event(&mut self, event: &KeyboardEvent) -> bool {
+        self.handle_event_at(event, Instant::now())
+    }
+
+    fn handle_event_at(&mut self, event: &KeyboardEvent, now: Instant) -> bool {
+        let current = self.current().map(Page::name);
+        let Some(transition) = self.transitions.iter().find(|transition| {
+            transition.trigger.matches(event)
+                && transition
+                    .from
+                    .as_deref()
+                    .is_none_or(|from| Some(from) == current)
+        }) else {
+            return false;
+        };
+
+        let target = self
+            .pages
+            .iter()
+            .position(|page| page.name() == transition.to);
+        match target {
+            Some(index) if index != self.current => {
+                self.active = (transition.animation != Animation::Cut
+                    && !transition.duration.is_zero())
+                .then_some(Active {
+                    previous: self.current,
+                    animation: transition.animation,
+                    started: now,
+                    duration: transition.duration,
+                });
+                self.current = index;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn is_animating_at(&self, now: Instant) -> bool {
+        self.active
+            .as_ref()
+            .is_some_and(|active| now.duration_since(active.started) < active.duration)
+    }
+
+    fn draw_at(&self, screen: &mut OledScreen, now: Instant) -> Result<(), Error> {
+        screen.clear();
+        let Some(page) = self.current() else {
+            return Ok(());
+        };
+        page.draw(screen)?;
+
+        let Some(active) = self.active.as_ref().filter(|_| self.is_animating_at(now)) else {
+            return Ok(());
+        };
+        let to = screen.framebuffer().clone();
+        screen.clear();
+        self.pages[active.previous].draw(screen)?;
+
+        let progress =
+            now.duration_since(active.started).as_secs_f32() / active.duration.as_secs_f32();
+        let frame = active.animation.blend(screen.framebuffer(), &to, progress);
+        *screen.framebuffer_mut() = frame;
+        Ok(())
+    }
+}
+
+impl Widget for PageMachine {
+    /// Clear the screen and draw the current page, part of the way through the animation to it
+    /// if one is playing
+    fn draw(&self, screen: &mut OledScreen) -> Result<(), Error> {
+        self.draw_at(screen, Instant::now())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::Rect;
+
+    #[test]
+    fn test_transitions() {
+        let start = Instant::now();
+        let mut machine = PageMachine::new(vec![Page::new("home"), Page::new("numpad")])
+            .with_transition(Transition::new(Trigger::Layer(0), "home"))
+            .with_transition(
+                Transition::new(Trigger::Keycode(5), "numpad")
+                    .with_from("home")
+                    .with_animation(Animation::Wipe, Duration::from_millis(100)),
+            );
+
+        assert!(!machine.handle_event_at(&KeyboardEvent::Layer(0), start));
+        assert!(machine.handle_event_at(&KeyboardEvent::Keycode(5), start));
+        assert_eq!(machine.current().unwrap().name(), "numpad");
+        assert!(machine.is_animating_at(start + Duration::from_millis(50)));
+        assert!(!machine.is_animating_at(start + Duration::from_millis(100)));
+
+        assert!(!machine.handle_event_at(&KeyboardEvent::Keycode(5), start));
+        assert!(machine.handle_event_at(&KeyboardEvent::Layer(0), start));
+        assert!(!machine.is_animating_at(start));
+    }
+
+    #[test]
+    fn test_animation_blend() {
+        let mut from = Framebuffer::new(8, 4);
+        from.paint_region(Rect::new(0, 0, 1, 4), true);
+        let mut to = Framebuffer::new(8, 4);
+        to.fill_pixels(true);
+
+        let halfway = Animation::SlideLeft.blend(&from, &to, 0.5);
+        let row: Vec<_> = (0..8).map(|x| halfway.get_pixel(x, 0)).collect();
+        assert_eq!(row, [false, false, false, false, true, true, true, true]);
+        assert!(Animation::SlideRight
+            .blend(&from, &to, 0.25)
+            .get_pixel(2, 0));
+        assert_eq!(Animation::SlideUp.blend(&from, &to, 0.0), from);
+        assert_eq!(Animation::Wipe.blend(&from, &to, 1.0), to);
+    }
+}
@@ -0,0 +1,202 @@
+use std::time::{Duration, Instant};
+
+use crate::error::Error;
+use crate::events::KeyboardEvent;
+use crate::screen::OledScreen;
+use crate::widgets::Widget;
+
+/// A named layout made up of widgets, drawn in the order they were added
+pub struct Page {
+    name: String,
+    widgets: Vec<Box<dyn Widget>>,
+}
+
+impl Page {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            widgets: vec![],
+        }
+    }
+
+    /// Add a widget to the page. To keep updating a widget after adding it, wrap it in an
+    /// `Rc<RefCell<_>>` and add a clone
+    pub fn with_widget(mut self, widget: impl Widget + 'static) -> Self {
+        self.widgets.push(Box::new(widget));
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Widget for Page {
+    fn draw(&self, screen: &mut OledScreen) -> Result<(), Error> {
+        for widget in &self.widgets {
+            widget.draw(screen)?;
+        }
+        Ok(())
+    }
+}
+
+/// A set of pages where one is shown at a time. Pages can rotate on a timer and be switched
+/// explicitly or with keycodes sent from the keyboard
+pub struct Pages {
+    pages: Vec<Page>,
+    current: usize,
+    rotate_every: Option<Duration>,
+    shown_at: Instant,
+    keycodes: Option<(u16, u16)>,
+}
+
+impl Pages {
+    /// Create a set of pages which starts on the first page and doesn't rotate
+    pub fn new(pages: Vec<Page>) -> Self {
+        Self {
+            pages,
+            current: 0,
+            rotate_every: None,
+            shown_at: Instant::now(),
+            keycodes: None,
+        }
+    }
+
+    /// Move on to the next page each time the given interval passes
+    pub fn with_rotation(mut self, rotate_every: Duration) -> Self {
+        self.rotate_every = Some(rotate_every);
+        self
+    }
+
+    /// Switch to the previous and next page when the given keycodes are received
+    pub fn with_keycodes(mut self, previous: u16, next: u16) -> Self {
+        self.keycodes = Some((previous, next));
+        self
+    }
+
+    /// The page currently being shown, if there are any pages
+    pub fn current(&self) -> Option<&Page> {
+        self.pages.get(self.current)
+    }
+
+    /// Show the page at the given index, restarting the rotation timer. Out of range indices are
+    /// ignored
+    pub fn show(&mut self, index: usize) {
+        self.show_at(index, Instant::now())
+    }
+
+    /// Show the page with the given name. Returns whether a page was found
+    pub fn show_named(&mut self, name: &str) -> bool {
+        match self.pages.iter().position(|page| page.name == name) {
+            Some(index) => {
+                self.show(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Show the next page, wrapping around after the last one
+    pub fn next(&mut self) {
+        if !self.pages.is_empty() {
+            self.show((self.current + 1) % self.pages.len());
+        }
+    }
+
+    /// Show the previous page, wrapping around before the first one
+    pub fn previous(&mut self) {
+        if !self.pages.is_empty() {
+            self.show((self.current + self.pages.len() - 1) % self.pages.len());
+        }
+    }
+
+    /// Switch pages in response to the configured keycodes. Returns whether the event was handled
+    pub fn handle_event(&mut self, event: &KeyboardEvent) -> bool {
+        match (event, self.keycodes) {
+            (KeyboardEvent::Keycode(keycode), Some((previous, _))) if *keycode == previous => {
+                self.previous();
+                true
+            }
+            (KeyboardEvent::Keycode(keycode), Some((_, next))) if *keycode == next => {
+                self.next();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Rotate to the next page if it is due. Call this regularly (e.g. once per frame). Returns
+    /// whether the page changed
+    pub fn update(&mut self) -> bool {
+        self.update_at(Instant::now())
+    }
+
+    fn show_at(&mut self, index: usize, now: Instant) {
+        if index < self.pages.len() {
+            self.current = index;
+            self.shown_at = now;
+        }
+    }
+
+    fn update_at(&mut self, now: Instant) -> bool {
+        let due = self
+            .rotate_every
+            .is_some_and(|rotate_every| now.duration_since(self.shown_at) >= rotate_every);
+        if !due || self.pages.len() < 2 {
+            return false;
+        }
+
+        self.show_at((self.current + 1) % self.pages.len(), now);
+        true
+    }
+}
+
+impl Widget for Pages {
+    /// Clear the screen and draw the current page
+    fn draw(&self, screen: &mut OledScreen) -> Result<(), Error> {
+        screen.clear();
+        match self.current() {
+            Some(page) => page.draw(screen),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pages() -> Pages {
+        Pages::new(vec![
+            Page::new("media"),
+            Page::new("stats"),
+            Page::new("clock"),
+        ])
+    }
+
+    #[test]
+    fn test_rotation() {
+        let start = Instant::now();
+        let mut pages = pages().with_rotation(Duration::from_secs(10));
+        pages.show_at(0, start);
+
+        assert!(!pages.update_at(start + Duration::from_secs(5)));
+        assert!(pages.update_at(start + Duration::from_secs(10)));
+        assert!(!pages.update_at(start + Duration::from_secs(15)));
+        assert_eq!(pages.current().unwrap().name(), "stats");
+    }
+
+    #[test]
+    fn test_explicit_switching() {
+        let mut pages = pages().with_keycodes(1, 2);
+
+        pages.previous();
+        assert_eq!(pages.current().unwrap().name(), "clock");
+        assert!(pages.handle_event(&KeyboardEvent::Keycode(2)));
+        assert_eq!(pages.current().unwrap().name(), "media");
+
+        assert!(pages.show_named("stats"));
+        assert!(!pages.show_named("weather"));
+        assert_eq!(pages.current().unwrap().name(), "stats");
+    }
+}
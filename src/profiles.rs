@@ -0,0 +1,183 @@
+use std::io;
+
+use crate::error::Error;
+use crate::events::KeyboardEvent;
+use crate::pages::Pages;
+use crate::screen::OledScreen;
+use crate::sources::{DataSource, Sources};
+use crate::widgets::Widget;
+
+/// A named set of pages along with the data sources which feed them, e.g. a "gaming" profile
+/// showing FPS and temperatures in place of the usual clock and calendar
+pub struct Profile {
+    name: String,
+    pages: Pages,
+    sources: Sources,
+}
+
+impl Profile {
+    pub fn new(name: &str, pages: Pages) -> Self {
+        Self {
+            name: name.to_string(),
+            pages,
+            sources: Sources::new(),
+        }
+    }
+
+    /// Add a data source, which is only polled while the profile is active
+    pub fn with_source(mut self, source: impl DataSource + 'static) -> Self {
+        self.sources.add(source);
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn pages(&self) -> &Pages {
+        &self.pages
+    }
+
+    pub fn pages_mut(&mut self) -> &mut Pages {
+        &mut self.pages
+    }
+
+    /// The latest values from the profile's sources, for updating its widgets
+    pub fn sources(&self) -> &Sources {
+        &self.sources
+    }
+}
+
+/// A set of profiles where one is active at a time, giving the screen an entirely different set
+/// of pages and sources depending on what the keyboard is being used for. Profiles can be
+/// switched by name (e.g. over the draw server with `profile <name>`) or with keycodes sent from
+/// the keyboard
+pub struct Profiles {
+    profiles: Vec<Profile>,
+    current: usize,
+    keycodes: Vec<(u16, String)>,
+}
+
+impl Profiles {
+    /// Create a set of profiles which starts on the first one
+    pub fn new(profiles: Vec<Profile>) -> Self {
+        Self {
+            profiles,
+            current: 0,
+            keycodes: vec![],
+        }
+    }
+
+    /// Switch to the named profile when the given keycode is received
+    pub fn with_keycode(mut self, keycode: u16, profile: &str) -> Self {
+        self.keycodes.push((keycode, profile.to_string()));
+        self
+    }
+
+    /// The active profile, if there are any profiles
+    pub fn current(&self) -> Option<&Profile> {
+        self.profiles.get(self.current)
+    }
+
+    /// The active profile, see `current`
+    pub fn current_mut(&mut self) -> Option<&mut Profile> {
+        self.profiles.get_mut(self.current)
+    }
+
+    /// Make the profile with the given name active. Returns whether a profile was found
+    pub fn switch(&mut self, name: &str) -> bool {
+        match self
+            .profiles
+            .iter()
+            .position(|profile| profile.name == name)
+        {
+            Some(index) => {
+                self.current = index;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Switch profiles in response to the configured keycodes, passing any other event on to the
+    /// active profile's pages. Returns whether the event was handled
+    pub fn handle_event(&mut self, event: &KeyboardEvent) -> bool {
+        let target = self.keycodes.iter().find_map(|(keycode, name)| {
+            (*event == KeyboardEvent::Keycode(*keycode)).then(|| name.clone())
+        });
+
+        match target {
+            Some(name) => self.switch(&name),
+            None => self
+                .current_mut()
+                .is_some_and(|profile| profile.pages.handle_event(event)),
+        }
+    }
+
+    /// Poll the active profile's sources and rotate its pages if either is due. Call this
+    /// regularly (e.g. once per frame). Returns whether anything changed, or the first error from
+    /// a source once they have all been polled
+    pub fn update(&mut self) -> io::Result<bool> {
+        let Some(profile) = self.current_mut() else {
+            return Ok(false);
+        };
+
+        let rotated = profile.pages.update();
+        Ok(profile.sources.poll_due()? || rotated)
+    }
+}
+
+impl Widget for Profiles {
+    /// Clear the screen and draw the active profile's current page
+    fn draw(&self, screen: &mut OledScreen) -> Result<(), Error> {
+        match self.current() {
+            Some(profile) => profile.pages.draw(screen),
+            None => {
+                screen.clear();
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pages::Page;
+
+    fn profiles() -> Profiles {
+        let work = Pages::new(vec![Page::new("calendar"), Page::new("clock")]);
+        let gaming = Pages::new(vec![Page::new("fps")]).with_keycodes(1, 2);
+        Profiles::new(vec![
+            Profile::new("work", work),
+            Profile::new("gaming", gaming),
+        ])
+        .with_keycode(10, "gaming")
+        .with_keycode(11, "work")
+    }
+
+    #[test]
+    fn test_switching_profiles() {
+        let mut profiles = profiles();
+        assert_eq!(profiles.current().unwrap().name(), "work");
+
+        assert!(profiles.handle_event(&KeyboardEvent::Keycode(10)));
+        assert_eq!(profiles.current().unwrap().name(), "gaming");
+        assert!(profiles.handle_event(&KeyboardEvent::Keycode(2)));
+        assert!(!profiles.handle_event(&KeyboardEvent::Keypress));
+
+        assert!(profiles.switch("work"));
+        assert!(!profiles.switch("music"));
+        assert_eq!(profiles.current().unwrap().name(), "work");
+        assert_eq!(
+            profiles
+                .current()
+                .unwrap()
+                .pages()
+                .current()
+                .unwrap()
+                .name(),
+            "calendar"
+        );
+    }
+}
@@ -0,0 +1,212 @@
+use std::any::Any;
+use std::cell::RefCell;
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{self, BufRead, BufWriter, Write};
+use std::path::Path;
+use std::rc::Rc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use hidapi::HidError;
+
+use crate::data::{DeviceInfo, HidAdapter};
+
+/// A device which passes every write on to another device, logging it to a capture along the way.
+/// Captures can be played back with `replay`, which makes it possible to reproduce what the
+/// keyboard received without the program that sent it.
+///
+/// Captures are plain text with a line per write: the microseconds since recording started,
+/// followed by the written bytes in hex. Lines starting with `#` are comments.
+pub struct RecordingAdapter<A, W: Write = BufWriter<File>> {
+    device: A,
+    capture: Rc<RefCell<W>>,
+    started: Instant,
+}
+
+/// A single write read back from a capture
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CapturedWrite {
+    /// How long after the start of the recording the write happened
+    pub at: Duration,
+    pub data: Vec<u8>,
+}
+
+impl<A: HidAdapter> RecordingAdapter<A> {
+    /// Record writes to `device` into a new capture file at the given path
+    pub fn new<P: AsRef<Path>>(device: A, path: P) -> io::Result<Self> {
+        Self::with_writer(device, BufWriter::new(File::create(path)?))
+    }
+}
+
+impl<A: HidAdapter, W: Write> RecordingAdapter<A, W> {
+    /// Record writes to `device` into any writer rather than a file
+    pub fn with_writer(device: A, mut capture: W) -> io::Result<Self> {
+        writeln!(capture, "# qmk-oled capture")?;
+
+        Ok(Self {
+            device,
+            capture: Rc::new(RefCell::new(capture)),
+            started: Instant::now(),
+        })
+    }
+
+    fn record(&self, data: &[u8]) -> io::Result<()> {
+        let mut line = self.started.elapsed().as_micros().to_string();
+        line.push(' ');
+        for byte in data {
+            let _ = write!(line, "{byte:02x}");
+        }
+
+        let mut capture = self.capture.borrow_mut();
+        writeln!(capture, "{line}")?;
+        capture.flush()
+    }
+}
+
+impl<A: Clone, W: Write> Clone for RecordingAdapter<A, W> {
+    fn clone(&self) -> Self {
+        Self {
+            device: self.device.clone(),
+            capture: Rc::clone(&self.capture),
+            started: self.started,
+        }
+    }
+}
+
+impl<A: HidAdapter + 'static, W: Write + 'static> HidAdapter for RecordingAdapter<A, W> {
+    fn write(&self, data: &[u8]) -> Result<usize, HidError> {
+        self.record(data).map_err(|error| HidError::HidApiError {
+            message: format!("Failed to record write: {error}"),
+        })?;
+        self.device.write(data)
+    }
+
+    fn read_timeout(&self, buf: &mut [u8], timeout: i32) -> Result<usize, HidError> {
+        self.device.read_timeout(buf, timeout)
+    }
+
+    fn end_frame(&self) -> Result<(), HidError> {
+        self.device.end_frame()
+    }
+
+    fn device_info(&self) -> DeviceInfo {
+        self.device.device_info()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Read every write from a capture made by `RecordingAdapter`
+pub fn read_capture(reader: impl BufRead) -> io::Result<Vec<CapturedWrite>> {
+    let mut writes = vec![];
+
+    for (number, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let invalid = || {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid capture on line {}: {line}", number + 1),
+            )
+        };
+
+        let (micros, hex) = line.split_once(' ').unwrap_or((line, ""));
+        let micros: u64 = micros.parse().map_err(|_| invalid())?;
+        // Checking every character is a hex digit also makes slicing pairs of them safe
+        if hex.len() % 2 != 0 || !hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+            return Err(invalid());
+        }
+        let data = (0..hex.len())
+            .step_by(2)
+            .map(|index| u8::from_str_radix(&hex[index..index + 2], 16))
+            .collect::<Result<_, _>>()
+            .map_err(|_| invalid())?;
+
+        writes.push(CapturedWrite {
+            at: Duration::from_micros(micros),
+            data,
+        });
+    }
+
+    Ok(writes)
+}
+
+/// Send captured writes to a device with the same timing they were recorded with. `speed` scales
+/// the timing, e.g. 2.0 plays the capture back twice as fast and infinity sends every write
+/// straight away
+pub fn replay(
+    writes: &[CapturedWrite],
+    device: &dyn HidAdapter,
+    speed: f32,
+) -> Result<(), HidError> {
+    let started = Instant::now();
+
+    for write in writes {
+        let due = Duration::try_from_secs_f32(write.at.as_secs_f32() / speed).unwrap_or_default();
+        thread::sleep(due.saturating_sub(started.elapsed()));
+        device.write(&write.data)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::Point;
+    use crate::screen::OledScreen;
+    use crate::testing::MockDevice;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_record_and_replay() {
+        let mock_device = MockDevice::new();
+        let recorder = RecordingAdapter::with_writer(mock_device.clone(), vec![]).unwrap();
+        let mut screen = OledScreen::from_device(recorder.clone(), 32, 128).unwrap();
+
+        screen.draw_line(Point::new(0, 0), Point::new(31, 127));
+        screen.send().unwrap();
+        let sent = mock_device.take_writes();
+        drop(screen);
+
+        let capture = Rc::try_unwrap(recorder.capture).unwrap().into_inner();
+        let writes = read_capture(capture.as_slice()).unwrap();
+        let recorded: Vec<_> = writes.iter().map(|write| write.data.clone()).collect();
+        assert_eq!(recorded, sent);
+
+        let replayed_device = MockDevice::new();
+        replay(&writes, &replayed_device, f32::INFINITY).unwrap();
+        assert_eq!(replayed_device.writes(), sent);
+    }
+
+    #[test]
+    fn test_read_capture() {
+        let capture = "# qmk-oled capture\n0 0100ff\n\n1500 0301\n";
+        assert_eq!(
+            read_capture(capture.as_bytes()).unwrap(),
+            vec![
+                CapturedWrite {
+                    at: Duration::ZERO,
+                    data: vec![1, 0, 255]
+                },
+                CapturedWrite {
+                    at: Duration::from_micros(1500),
+                    data: vec![3, 1]
+                }
+            ]
+        );
+
+        assert!(read_capture("12 0g".as_bytes()).is_err());
+        assert!(read_capture("12 010".as_bytes()).is_err());
+        assert!(read_capture("soon 01".as_bytes()).is_err());
+        assert!(read_capture("100 aé0".as_bytes()).is_err());
+        assert!(read_capture("100 +1".as_bytes()).is_err());
+    }
+}
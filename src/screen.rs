@@ -1,37 +1,540 @@
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::collections::BTreeMap;
 use std::ffi::CStr;
 use std::fmt::Display;
 use std::fs;
+use std::ops::RangeBounds;
 use std::path::Path;
+use std::time::Duration;
 
 use fontdue::Font;
 use hidapi::{HidApi, HidError};
 use image::imageops::{dither, BiLevel, FilterType};
-use image::DynamicImage;
+use image::{DynamicImage, GrayImage, ImageFormat};
 use itertools::Itertools;
 
-use crate::data::{DataPacket, HidAdapter, PAYLOAD_SIZE};
-use crate::utils::{get_bit_at_index, set_bit_at_index};
+use crate::color::{encode_monochrome_into, Rgb565};
+use crate::control::ControlMessage;
+use crate::data::{
+    chunk_size, frame_packets, send_wide_frame, write_report, DeviceInfo, HidAdapter,
+    COLOR_REPORT_ID, PAYLOAD_SIZE,
+};
+use crate::error::Error;
+use crate::events::KeyboardEvent;
+use crate::font_subset::FontSubset;
+use crate::framebuffer::{buffer_pixel, Framebuffer};
+use crate::geometry::{Point, Rect};
+use crate::handshake::{handshake, Capabilities, ProtocolVersion, ScreenGeometry};
+use crate::metrics::Metrics;
 
+pub use crate::framebuffer::BufferLayout;
+
+/// How long `OledScreen::autodetect` waits for the firmware to report the size of its screen
+const AUTODETECT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Where the screen's origin is, which every coordinate given to `OledScreen` is relative to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Origin {
+    /// y counts up from the bottom of the screen. Images and text are drawn upwards from the y
+    /// they are drawn at
+    #[default]
+    BottomLeft,
+    /// y counts down from the top of the screen, as it does in most image formats. Images and
+    /// text are drawn downwards from the y they are drawn at, which is their top edge
+    TopLeft,
+}
+
+/// How an image is sized to the area it is drawn in: the screen for `OledScreen::draw_image`, the
+/// region given to `OledScreen::draw_image_in`, or a 32x128 screen for `prepare_image`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ImageSizing {
+    /// Scale the image to fit inside the area, keeping its aspect ratio
     Contain,
+    /// Scale the image to fill the area, keeping its aspect ratio, and crop whatever sticks out
+    /// equally from both sides so that the middle of the image is shown
     Cover,
+    /// Draw the image at its own size
     Original,
+    /// Scale the image to exactly fill the area, squashing or stretching it if its aspect ratio
+    /// is different. Handy for backgrounds such as gradients, where `Contain` would leave bars
+    /// either side
+    Stretch,
 }
 
-pub struct OledScreen {
-    width: usize,
-    height: usize,
+/// Where text is drawn relative to the x coordinate it is drawn at
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextAlign {
+    /// The text starts at x
+    #[default]
+    Left,
+    /// The text is centred on x
+    Center,
+    /// The text ends at x
+    Right,
+}
+
+/// How to draw text with `OledScreen::draw_text`. The default is Cozette (which is bundled with
+/// the project) at size 8:
+///
+/// ```
+/// use qmk_oled_api::screen::{TextAlign, TextStyle};
+///
+/// let style = TextStyle::new(12.0).with_align(TextAlign::Center).with_bold(true);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextStyle {
+    /// The path of the font to draw with, or `None` for Cozette
+    pub font: Option<String>,
+    pub size: f32,
+    /// Extra pixels between each letter, which can be negative to squeeze text together
+    pub tracking: i32,
+    /// The distance between the bottom of one line and the next, when the text has several lines.
+    /// Defaults to the size plus two pixels
+    pub line_height: Option<usize>,
+    pub align: TextAlign,
+    /// Draw the text as unlit pixels on a lit background
+    pub inverted: bool,
+    /// Thicken the text by drawing every letter again one pixel to the right
+    pub bold: bool,
+    /// Drawn in place of the font's letter for each of these characters, see `with_glyph`
+    pub glyphs: BTreeMap<char, Glyph>,
+}
+
+impl Default for TextStyle {
+    fn default() -> Self {
+        Self {
+            font: None,
+            size: 8.0,
+            tracking: 0,
+            line_height: None,
+            align: TextAlign::default(),
+            inverted: false,
+            bold: false,
+            glyphs: BTreeMap::new(),
+        }
+    }
+}
+
+impl TextStyle {
+    pub fn new(size: f32) -> Self {
+        Self {
+            size,
+            ..Self::default()
+        }
+    }
+
+    /// Draw with the font at the given path, or Cozette if no path is given
+    pub fn with_font(mut self, font_path: Option<&str>) -> Self {
+        self.font = font_path.map(str::to_string);
+        self
+    }
+
+    pub fn with_tracking(mut self, tracking: i32) -> Self {
+        self.tracking = tracking;
+        self
+    }
+
+    pub fn with_line_height(mut self, line_height: usize) -> Self {
+        self.line_height = Some(line_height);
+        self
+    }
+
+    pub fn with_align(mut self, align: TextAlign) -> Self {
+        self.align = align;
+        self
+    }
+
+    pub fn with_inverted(mut self, inverted: bool) -> Self {
+        self.inverted = inverted;
+        self
+    }
+
+    pub fn with_bold(mut self, bold: bool) -> Self {
+        self.bold = bold;
+        self
+    }
+
+    /// Draw a glyph instead of the font's letter wherever the character appears, e.g. an icon
+    /// for U+25B6 to write "▶ Song Title". Useful for icons the font doesn't have, or which look
+    /// better drawn by hand at the size of the text
+    pub fn with_glyph(mut self, letter: char, glyph: Glyph) -> Self {
+        self.glyphs.insert(letter, glyph);
+        self
+    }
+
+    /// Draw every character in a font subset with its glyph from the subset rather than the font,
+    /// see `FontSubset`
+    pub fn with_subset(mut self, subset: &FontSubset) -> Self {
+        self.glyphs.extend(subset.glyphs().clone());
+        self
+    }
+
+    fn line_height(&self) -> usize {
+        self.line_height.unwrap_or(self.size.ceil() as usize + 2)
+    }
+
+    /// How far the cursor moves after drawing a letter
+    fn advance(&self, font: &Font, letter: char) -> usize {
+        let advance = match self.glyphs.get(&letter) {
+            Some(glyph) => glyph.advance.unwrap_or(glyph.width + 1) as i64,
+            None => font.metrics(letter, self.size).advance_width.round() as i64,
+        };
+        (advance + i64::from(self.tracking)).max(0) as usize
+    }
+
+    fn line_width(&self, font: &Font, line: &str) -> usize {
+        line.chars()
+            .map(|letter| self.advance(font, letter))
+            .sum::<usize>()
+            + usize::from(self.bold)
+    }
+}
+
+/// A hand drawn letter or icon to draw in text in place of a character, see
+/// `TextStyle::with_glyph`. Its bottom sits on the same line as the bottom of the font's letters,
+/// and the cursor moves on by its width plus a pixel of space unless it is given an advance:
+///
+/// ```
+/// use qmk_oled_api::screen::{Glyph, TextStyle};
+///
+/// let play = Glyph::from_ascii("
+///     #...
+///     ##..
+///     ###.
+///     ##..
+///     #...
+/// ");
+/// let style = TextStyle::default().with_glyph('▶', play);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Glyph {
+    pub(crate) width: usize,
+    pub(crate) height: usize,
+    /// Whether each pixel is lit, in rows from the top
+    pub(crate) pixels: Vec<bool>,
+    /// How far the cursor moves on after the glyph, if not its width plus one
+    pub(crate) advance: Option<usize>,
+}
+
+impl Glyph {
+    /// A glyph drawn as ASCII art, with `#` for lit pixels and anything else for unlit ones.
+    /// Blank lines and indentation are ignored, and short lines are padded with unlit pixels
+    pub fn from_ascii(art: &str) -> Self {
+        let rows: Vec<&str> = art
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect();
+        let width = rows
+            .iter()
+            .map(|row| row.chars().count())
+            .max()
+            .unwrap_or(0);
+
+        let pixels = rows
+            .iter()
+            .flat_map(|row| {
+                row.chars()
+                    .map(|pixel| pixel == '#')
+                    .chain(std::iter::repeat(false))
+                    .take(width)
+            })
+            .collect();
+        Self {
+            width,
+            height: rows.len(),
+            pixels,
+            advance: None,
+        }
+    }
+
+    /// A glyph from an image at its original size, lighting pixels which are brighter than mid
+    /// gray and at least half opaque
+    pub fn from_image(image: &DynamicImage) -> Self {
+        let image = image.to_luma_alpha8();
+        Self {
+            width: image.width() as usize,
+            height: image.height() as usize,
+            pixels: image
+                .pixels()
+                .map(|pixel| pixel.0[0] >= 128 && pixel.0[1] >= 128)
+                .collect(),
+            advance: None,
+        }
+    }
+
+    /// Move the cursor on by the given number of pixels after drawing the glyph, rather than its
+    /// width plus one. Tracking is still added on top
+    pub fn with_advance(mut self, advance: usize) -> Self {
+        self.advance = Some(advance);
+        self
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+}
+
+/// An image which has been resized and dithered down to black and white, ready to be drawn with
+/// `OledScreen::draw_dithered_image`. See `prepare_image`
+#[derive(Debug, Clone, PartialEq)]
+pub struct DitheredImage {
+    image: GrayImage,
+    /// Whether each pixel should be drawn, for images with an alpha channel. Pixels which are less
+    /// than half opaque are left out so whatever is already on the screen shows through
+    opaque: Option<Vec<bool>>,
+}
+
+impl DitheredImage {
+    pub fn width(&self) -> u32 {
+        self.image.width()
+    }
+
+    pub fn height(&self) -> u32 {
+        self.image.height()
+    }
+}
+
+/// How an image is turned into black and white pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Binarization {
+    /// Error diffusion dithering, which keeps shading and detail at the cost of some noise
+    #[default]
+    Dither,
+    /// Light every pixel brighter than the given value
+    Threshold(u8),
+    /// Pick the threshold from the image's histogram using Otsu's method, which splits images
+    /// made up of a light and a dark part (e.g. a logo on a flat background) much more cleanly
+    /// than dithering or a fixed threshold
+    Otsu,
+}
+
+/// The threshold which best separates the pixels of an image into a dark and a light class, by
+/// maximising the variance between the two (Otsu's method)
+fn otsu_threshold(image: &GrayImage) -> u8 {
+    let mut histogram = [0_u64; 256];
+    for pixel in image.pixels() {
+        histogram[pixel.0[0] as usize] += 1;
+    }
+
+    let total = image.pixels().len() as f64;
+    let total_sum: f64 = (0..256)
+        .map(|value| value as f64 * histogram[value] as f64)
+        .sum();
+    let (mut dark_count, mut dark_sum) = (0.0, 0.0);
+    let (mut best_threshold, mut best_variance) = (0, 0.0);
+
+    for (threshold, &count) in histogram.iter().enumerate() {
+        dark_count += count as f64;
+        dark_sum += threshold as f64 * count as f64;
+        let light_count = total - dark_count;
+        if dark_count == 0.0 || light_count == 0.0 {
+            continue;
+        }
+
+        let dark_mean = dark_sum / dark_count;
+        let light_mean = (total_sum - dark_sum) / light_count;
+        let variance = dark_count * light_count * (dark_mean - light_mean).powi(2);
+        if variance > best_variance {
+            (best_threshold, best_variance) = (threshold, variance);
+        }
+    }
+    best_threshold as u8
+}
+
+/// Resize an image for a 32x128 screen and turn it into black and white, which is the slow part
+/// of drawing an image. Doing this ahead of time (e.g. for every frame of an animation) keeps it
+/// out of the loop sending frames to the keyboard. Use `prepare_image_for` for other sizes
+pub fn prepare_image(
+    image: DynamicImage,
+    sizing: &ImageSizing,
+    binarization: Binarization,
+) -> DitheredImage {
+    prepare_image_for(image, 32, 128, sizing, binarization)
+}
+
+/// Resize an image for an area of the given size (e.g. a thumbnail in a widget) and turn it into
+/// black and white, see `prepare_image`
+pub fn prepare_image_for(
+    mut image: DynamicImage,
+    width: u32,
+    height: u32,
+    sizing: &ImageSizing,
+    binarization: Binarization,
+) -> DitheredImage {
+    match sizing {
+        ImageSizing::Contain => image = image.resize(width, height, FilterType::Lanczos3),
+        ImageSizing::Cover => image = image.resize_to_fill(width, height, FilterType::Lanczos3),
+        ImageSizing::Original => (),
+        ImageSizing::Stretch => image = image.resize_exact(width, height, FilterType::Lanczos3),
+    };
+
+    let opaque = image.color().has_alpha().then(|| {
+        image
+            .to_luma_alpha8()
+            .pixels()
+            .map(|pixel| pixel.0[1] >= 128)
+            .collect()
+    });
+
+    let mut image = image.grayscale().into_luma8();
+    let threshold = match binarization {
+        Binarization::Dither => {
+            dither(&mut image, &BiLevel);
+            return DitheredImage { image, opaque };
+        }
+        Binarization::Threshold(threshold) => threshold,
+        Binarization::Otsu => otsu_threshold(&image),
+    };
+    for pixel in image.pixels_mut() {
+        pixel.0[0] = if pixel.0[0] > threshold { 255 } else { 0 };
+    }
+    DitheredImage { image, opaque }
+}
+
+/// Prepare several images at once for an area of the given size, such as the frames of a gif for
+/// the screen they will be played on. With the `rayon` feature enabled they are prepared in
+/// parallel
+pub fn prepare_images(
+    images: Vec<DynamicImage>,
+    width: u32,
+    height: u32,
+    sizing: &ImageSizing,
+    binarization: Binarization,
+) -> Vec<DitheredImage> {
+    let prepare = |image| prepare_image_for(image, width, height, sizing, binarization);
+
+    #[cfg(feature = "rayon")]
+    {
+        use rayon::prelude::*;
+        images.into_par_iter().map(prepare).collect()
+    }
+    #[cfg(not(feature = "rayon"))]
+    {
+        images.into_iter().map(prepare).collect()
+    }
+}
+
+/// A copy of the screen's framebuffer at a point in time. See `OledScreen::snapshot`
+#[derive(Clone, PartialEq, Debug)]
+pub struct Snapshot {
     data: Vec<u8>,
-    _prev_packets: Option<Vec<DataPacket>>,
+}
+
+/// How frames are sent to the keyboard, which depends on the display driver its firmware uses
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FrameProtocol {
+    /// One bit to a pixel in `SCREEN_REPORT_ID` reports, for QMK's OLED driver
+    #[default]
+    Oled,
+    /// Every pixel as an RGB565 colour in `COLOR_REPORT_ID` reports, the same as a `ColorScreen`
+    /// sends, for a display driven by Quantum Painter. Lit pixels are sent in `foreground` and the
+    /// rest in `background`
+    QuantumPainter {
+        foreground: Rgb565,
+        background: Rgb565,
+    },
+}
+
+impl FrameProtocol {
+    /// The number of bytes a frame of the given size takes up when sent
+    fn encoded_len(&self, width: usize, height: usize) -> usize {
+        match self {
+            FrameProtocol::Oled => (width * height) / 8,
+            FrameProtocol::QuantumPainter { .. } => width * height * 2,
+        }
+    }
+}
+
+pub struct OledScreen {
+    framebuffer: Framebuffer,
+    /// The framebuffer as it is sent, reused between sends
+    encoded: Vec<u8>,
+    /// What was in `encoded` the last time the screen was sent, if all of it made it
+    _prev_frame: Option<Vec<u8>>,
     device: Box<dyn HidAdapter>,
+    device_info: DeviceInfo,
+    capabilities: Cell<Capabilities>,
+    report_prefix: Option<u8>,
+    layout: BufferLayout,
+    protocol: FrameProtocol,
+    origin: Origin,
+    clear_on_drop: bool,
+    power_off_on_drop: bool,
+    metrics: Option<Metrics>,
+}
+
+/// Load the font at the given path, or Cozette (which is bundled with the project) if no path is
+/// given
+fn load_font(font_path: Option<&str>) -> Result<Font, Error> {
+    if let Some(font_path) = font_path {
+        let font_bytes = fs::read(font_path)?;
+        Font::from_bytes(font_bytes, fontdue::FontSettings::default())
+            .map_err(|error| Error::Font(format!("{font_path}: {error}")))
+    } else {
+        // The bundled font is known to be valid
+        Ok(Font::from_bytes(
+            include_bytes!("../assets/cozette.ttf") as &[u8],
+            fontdue::FontSettings::default(),
+        )
+        .unwrap())
+    }
+}
+
+/// The width in pixels that `OledScreen::draw_text` would advance by when drawing the given text,
+/// or of its longest line if it has several. Useful for laying out text around other things
+pub fn text_width(text: &str, style: &TextStyle) -> Result<usize, Error> {
+    let font = load_font(style.font.as_deref())?;
+
+    Ok(text
+        .lines()
+        .map(|line| style.line_width(&font, line))
+        .max()
+        .unwrap_or(0))
+}
+
+/// Render a framebuffer laid out the way `OledScreen` stores it as braille, see
+/// `OledScreen::to_braille`
+pub(crate) fn render_braille(data: &[u8], width: usize, height: usize) -> String {
+    // The dot of a braille character for each pixel of its cell, by row then column
+    const DOTS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+    (0..height.div_ceil(4))
+        .map(|cell_row| {
+            (0..width.div_ceil(2))
+                .map(|cell_col| {
+                    let mut dots = 0;
+                    for (row, row_dots) in DOTS.iter().enumerate() {
+                        for (col, dot) in row_dots.iter().enumerate() {
+                            let x = cell_col * 2 + col;
+                            let from_top = cell_row * 4 + row;
+                            if x < width
+                                && from_top < height
+                                && buffer_pixel(data, height, x, height - 1 - from_top)
+                            {
+                                dots |= dot;
+                            }
+                        }
+                    }
+                    char::from_u32(0x2800 + dots).unwrap()
+                })
+                .collect::<String>()
+        })
+        .join("\n")
 }
 
 impl Display for OledScreen {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let string = self
-            .data
+            .framebuffer
+            .as_bytes()
             .iter()
-            .chunks(self.width / 8)
+            .chunks(self.width() / 8)
             .into_iter()
             .map(|row| row.map(|byte| format!("{byte:08b}")).join(""))
             .join("\n")
@@ -46,12 +549,27 @@ impl OledScreen {
     pub fn from_path(device_path: &CStr, width: usize, height: usize) -> Result<Self, HidError> {
         let api = HidApi::new()?;
         let device = api.open_path(device_path)?;
+        let device_info = match api.device_list().find(|dev| dev.path() == device_path) {
+            Some(device_info) => DeviceInfo::from(device_info),
+            None => DeviceInfo {
+                path: Some(device_path.to_string_lossy().into_owned()),
+                ..device.device_info()
+            },
+        };
         Ok(Self {
-            data: vec![0; (width * height) / 8],
+            framebuffer: Framebuffer::new(width, height),
             device: Box::new(device),
-            width,
-            height,
-            _prev_packets: None,
+            device_info,
+            encoded: vec![0; (width * height) / 8],
+            _prev_frame: None,
+            capabilities: Cell::new(Capabilities::ASSUMED),
+            report_prefix: None,
+            layout: BufferLayout::default(),
+            protocol: FrameProtocol::default(),
+            origin: Origin::default(),
+            clear_on_drop: false,
+            power_off_on_drop: false,
+            metrics: None,
         })
     }
 
@@ -71,11 +589,19 @@ impl OledScreen {
         if let Some(device_info) = device_info {
             let device = device_info.open_device(&api)?;
             Ok(Self {
-                data: vec![0; (width * height) / 8],
+                framebuffer: Framebuffer::new(width, height),
                 device: Box::new(device),
-                width,
-                height,
-                _prev_packets: None,
+                device_info: DeviceInfo::from(device_info),
+                encoded: vec![0; (width * height) / 8],
+                _prev_frame: None,
+                capabilities: Cell::new(Capabilities::ASSUMED),
+                report_prefix: None,
+                layout: BufferLayout::default(),
+                protocol: FrameProtocol::default(),
+                origin: Origin::default(),
+                clear_on_drop: false,
+                power_off_on_drop: false,
+                metrics: None,
             })
         } else {
             Err(HidError::HidApiError {
@@ -91,33 +617,155 @@ impl OledScreen {
         height: usize,
     ) -> Result<Self, HidError> {
         Ok(Self {
-            data: vec![0; (width * height) / 8],
+            framebuffer: Framebuffer::new(width, height),
+            device_info: device.device_info(),
             device: Box::new(device),
-            width,
-            height,
-            _prev_packets: None,
+            encoded: vec![0; (width * height) / 8],
+            _prev_frame: None,
+            capabilities: Cell::new(Capabilities::ASSUMED),
+            report_prefix: None,
+            layout: BufferLayout::default(),
+            protocol: FrameProtocol::default(),
+            origin: Origin::default(),
+            clear_on_drop: false,
+            power_off_on_drop: false,
+            metrics: None,
         })
     }
 
-    /// Convert the current state of the screen into a vector of datapackets.
-    ///
-    /// Useful when trying to send the state of the screen to a device
-    pub(crate) fn to_packets(&self) -> Vec<DataPacket> {
-        self.data
-            .iter()
-            .chunks(PAYLOAD_SIZE - 2)
-            .into_iter()
-            .map(|chunk| {
-                let mut output_array: [u8; PAYLOAD_SIZE - 2] = [0; PAYLOAD_SIZE - 2];
-                chunk
-                    .take(PAYLOAD_SIZE - 2)
-                    .enumerate()
-                    .for_each(|(index, byte)| output_array[index] = *byte);
-                output_array
-            })
-            .enumerate()
-            .map(|(index, chunk)| DataPacket::new(index.try_into().unwrap(), chunk))
-            .collect()
+    /// Load from a device, asking the firmware how big its screen is rather than taking a size.
+    /// Needs firmware speaking version 1.2 of the protocol or later. To share the interface with
+    /// VIA, open the screen with `from_device` and `with_report_prefix` then call `detect_size`
+    pub fn autodetect(device: impl HidAdapter + 'static + Clone) -> Result<Self, HidError> {
+        let mut screen = Self::from_device(device, 0, 0)?;
+        screen.detect_size(AUTODETECT_TIMEOUT)?;
+        Ok(screen)
+    }
+
+    /// Start every report sent to and from the device with the given byte. This lets the screen
+    /// share the raw HID interface with another protocol, such as VIA or Vial (see
+    /// `VIA_REPORT_PREFIX`), as long as the firmware is set up to expect the same prefix
+    pub fn with_report_prefix(mut self, prefix: u8) -> Self {
+        self.report_prefix = Some(prefix);
+        self
+    }
+
+    /// Send the framebuffer in the given layout rather than the one it is stored in. Use
+    /// `BufferLayout::OledWriteRaw` when the firmware passes frames straight to `oled_write_raw`,
+    /// as the receiver generated by `codegen` does
+    pub fn with_layout(mut self, layout: BufferLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Send frames with the given protocol, e.g. `FrameProtocol::QuantumPainter` to draw on an LCD
+    /// driven by Quantum Painter with the same API as an OLED. The layout only applies to
+    /// `FrameProtocol::Oled`
+    pub fn with_protocol(mut self, protocol: FrameProtocol) -> Self {
+        self.protocol = protocol;
+        self.encoded = vec![0; protocol.encoded_len(self.width(), self.height())];
+        self._prev_frame = None;
+        self
+    }
+
+    /// Measure coordinates from the given corner of the screen. This applies to widgets too, so
+    /// a widget at (0, 0) sits in the same corner as text drawn at (0, 0). The framebuffer itself
+    /// (see `framebuffer`) is always bottom-left
+    pub fn with_origin(mut self, origin: Origin) -> Self {
+        self.origin = origin;
+        self
+    }
+
+    /// Run `draw` for a widget occupying `bounds`, which are measured from the origin like any
+    /// other coordinates. While it runs coordinates are measured from the bottom left, and it is
+    /// given the same region measured that way to draw in, so that the widget's layout (e.g. text
+    /// drawn upwards from its bottom edge) holds whichever corner the origin is in. The origin is
+    /// put back afterwards
+    pub fn draw_widget<T>(&mut self, bounds: Rect, draw: impl FnOnce(&mut Self, Rect) -> T) -> T {
+        let bounds = self.framebuffer_rect(bounds);
+        let origin = std::mem::replace(&mut self.origin, Origin::BottomLeft);
+        let result = draw(self, bounds);
+        self.origin = origin;
+        result
+    }
+
+    /// Run `draw`, keeping anything it draws outside of `region` off the screen. Useful for text,
+    /// which is otherwise drawn however far it reaches
+    pub fn draw_clipped<T>(&mut self, region: Rect, draw: impl FnOnce(&mut Self) -> T) -> T {
+        let before = self.framebuffer.clone();
+        let result = draw(self);
+
+        let region = self.framebuffer_rect(region);
+        for x in 0..self.width() {
+            for y in 0..self.height() {
+                if !region.contains(Point::new(x, y)) {
+                    self.framebuffer.set_pixel(x, y, before.get_pixel(x, y));
+                }
+            }
+        }
+        result
+    }
+
+    /// Clear the screen when this is dropped, and turn the display off too if `power_off` is set.
+    /// Otherwise the last frame sent stays on the display after the program exits, which can burn
+    /// it into the panel. Errors while clearing are ignored, as there is nothing left to report
+    /// them to
+    pub fn with_clear_on_drop(mut self, power_off: bool) -> Self {
+        self.clear_on_drop = true;
+        self.power_off_on_drop = power_off;
+        self
+    }
+
+    /// Count the frames and bytes sent to the device in `metrics`, along with frames which failed
+    /// to send
+    pub fn with_metrics(mut self, metrics: &Metrics) -> Self {
+        self.metrics = Some(metrics.clone());
+        self
+    }
+
+    /// The pixels being drawn, for drawing code shared with hosts which don't have the standard
+    /// library (see `framebuffer`)
+    pub fn framebuffer(&self) -> &Framebuffer {
+        &self.framebuffer
+    }
+
+    /// The pixels being drawn, see `framebuffer`
+    pub fn framebuffer_mut(&mut self) -> &mut Framebuffer {
+        &mut self.framebuffer
+    }
+
+    /// Write a report to the device, adding the report prefix if there is one. Reports are cut
+    /// off at `PAYLOAD_SIZE`
+    pub(crate) fn write_report(&self, report: &[u8]) -> Result<usize, HidError> {
+        let written = write_report(self.device.as_ref(), self.report_prefix, report)?;
+        self.record_bytes(written);
+        Ok(written)
+    }
+
+    fn record_bytes(&self, bytes: usize) {
+        if let Some(metrics) = &self.metrics {
+            metrics.record_bytes(bytes);
+        }
+    }
+
+    /// Read a report from the device, waiting at most `timeout` milliseconds, with the report
+    /// prefix removed. Returns `None` if nothing arrived in time, and an empty report if one
+    /// arrived without the prefix (e.g. a reply meant for VIA)
+    pub(crate) fn read_report<'a>(
+        &self,
+        buf: &'a mut [u8],
+        timeout: i32,
+    ) -> Result<Option<&'a [u8]>, HidError> {
+        let length = self.device.read_timeout(buf, timeout)?;
+        if length == 0 {
+            return Ok(None);
+        }
+
+        let report = &buf[..length];
+        Ok(Some(match self.report_prefix {
+            Some(prefix) => report.strip_prefix(&[prefix]).unwrap_or_default(),
+            None => report,
+        }))
     }
 
     /// Draw a given image on the display, loading the image from a path
@@ -127,245 +775,797 @@ impl OledScreen {
         x: usize,
         y: usize,
         sizing: &ImageSizing,
-    ) {
-        let image = image::open(image_path).unwrap();
-        self.draw_image(image, x, y, sizing)
+    ) -> Result<(), Error> {
+        let image = image::open(image_path)?;
+        self.draw_image(image, x, y, sizing);
+        Ok(())
     }
 
-    /// Draw a given image on the display, loading the image from an existing `DynamicImage` variable.
-    pub fn draw_image(
+    /// Draw an image which is still encoded (e.g. a PNG fetched over HTTP, or embedded with
+    /// `include_bytes!`). The format is guessed from the bytes unless a hint is given
+    pub fn draw_image_bytes(
         &mut self,
-        mut image: DynamicImage,
+        bytes: &[u8],
+        format_hint: Option<ImageFormat>,
         x: usize,
         y: usize,
         sizing: &ImageSizing,
-    ) {
-        match sizing {
-            ImageSizing::Contain => image = image.resize(32, 128, FilterType::Lanczos3),
-            ImageSizing::Cover => {
-                let scaling = f32::max(
-                    32_f32 / image.width() as f32,
-                    128_f32 / image.height() as f32,
-                );
+    ) -> Result<(), Error> {
+        let image = match format_hint {
+            Some(format) => image::load_from_memory_with_format(bytes, format)?,
+            None => image::load_from_memory(bytes)?,
+        };
+        self.draw_image(image, x, y, sizing);
+        Ok(())
+    }
 
-                image = image.resize(
-                    (image.width() as f32 * scaling) as u32,
-                    (image.height() as f32 * scaling) as u32,
-                    FilterType::Lanczos3,
-                );
-            }
-            ImageSizing::Original => (),
+    /// Draw a given image on the display, loading the image from an existing `DynamicImage` variable.
+    /// The image is sized to the whole screen. It is dithered, use `prepare_image_for` and
+    /// `draw_dithered_image` to turn it into black and white another way
+    pub fn draw_image(&mut self, image: DynamicImage, x: usize, y: usize, sizing: &ImageSizing) {
+        let (width, height) = (self.width() as u32, self.height() as u32);
+        let prepared = prepare_image_for(image, width, height, sizing, Binarization::Dither);
+        self.draw_dithered_image(&prepared, x, y)
+    }
+
+    /// Draw an image sized to a region of the screen rather than the whole of it, e.g. album art
+    /// as a thumbnail next to the track name. The image is centred in the region and anything
+    /// outside of it is cut off. The image is dithered, see `draw_image`
+    pub fn draw_image_in(&mut self, image: DynamicImage, region: Rect, sizing: &ImageSizing) {
+        let (width, height) = (region.width() as u32, region.height() as u32);
+        let prepared = prepare_image_for(image, width, height, sizing, Binarization::Dither);
+        self.draw_dithered_image_in(&prepared, region);
+    }
+
+    /// Draw an image which has already been through `prepare_image_for` centred in a region of
+    /// the screen, cutting off anything outside of it. Transparent pixels are skipped
+    pub fn draw_dithered_image_in(&mut self, image: &DitheredImage, region: Rect) {
+        let region = self.framebuffer_rect(region);
+        let image_width = image.image.width() as usize;
+        // Where the image starts in the region and how much of it is cut off, along one axis
+        let centre = |space: usize, size: usize| match space.checked_sub(size) {
+            Some(spare) => (spare / 2, 0),
+            None => (0, (size - space) / 2),
         };
+        let (offset_x, skip_x) = centre(region.width(), image_width);
+        let (offset_y, skip_y) = centre(region.height(), image.image.height() as usize);
 
-        let mut image = image.grayscale().into_luma8();
-        dither(&mut image, &BiLevel);
+        for row in 0..region.height().min(image.image.height() as usize) {
+            for col in 0..region.width().min(image_width) {
+                let index = (skip_y + row) * image_width + skip_x + col;
+                if image.opaque.as_ref().is_some_and(|opaque| !opaque[index]) {
+                    continue;
+                }
 
-        let image_width = image.width();
-        let image_height = image.height();
+                // Rows of the image go down from the top of the region
+                let x = region.min_x() + offset_x + col;
+                let y = region.max_y() - 1 - offset_y - row;
+                let enabled = image.image.as_raw()[index] == 255;
+                self.framebuffer.set_pixel(x, y, enabled);
+            }
+        }
+    }
+
+    /// Draw an image which has already been through `prepare_image`. Transparent pixels are
+    /// skipped, leaving the screen underneath them untouched
+    pub fn draw_dithered_image(&mut self, image: &DitheredImage, x: usize, y: usize) {
+        let image_width = image.image.width();
+        let image_height = image.image.height();
+
+        for (index, pixel) in image.image.pixels().enumerate() {
+            if image.opaque.as_ref().is_some_and(|opaque| !opaque[index]) {
+                continue;
+            }
 
-        for (index, pixel) in image.pixels().enumerate() {
             let row = index / image_width as usize;
             let col = index % image_width as usize;
 
             let enabled = pixel.0[0] == 255;
 
-            self.set_pixel(x + col, y + image_height as usize - row, enabled)
+            let row_y = match self.origin {
+                Origin::BottomLeft => y + image_height as usize - row,
+                Origin::TopLeft => y + row,
+            };
+            self.set_pixel(x + col, row_y, enabled)
         }
     }
 
-    /// Draw a given string to the display with a given size. If no font is givem, the font used
-    /// will be Cozette (which is bundled with the project)
+    /// Draw a given string to the display in the given style, upwards from y (or downwards with
+    /// `Origin::TopLeft`). Each line after the first is drawn below the one before it, and lines
+    /// which would go off the bottom of the screen are skipped
     pub fn draw_text(
         &mut self,
         text: &str,
         x: usize,
         y: usize,
-        size: f32,
-        font_path: Option<&str>,
-    ) {
-        let font = if let Some(font_path) = font_path {
-            let font_bytes = fs::read(&font_path).unwrap();
-            Font::from_bytes(font_bytes, fontdue::FontSettings::default()).unwrap()
-        } else {
-            Font::from_bytes(
-                include_bytes!("../assets/cozette.ttf") as &[u8],
-                fontdue::FontSettings::default(),
-            )
-            .unwrap()
-        };
+        style: &TextStyle,
+    ) -> Result<(), Error> {
+        let font = load_font(style.font.as_deref())?;
 
-        let mut x_cursor = x;
+        for (index, line) in text.lines().enumerate() {
+            let line_y = match self.origin {
+                Origin::BottomLeft => match y.checked_sub(index * style.line_height()) {
+                    Some(line_y) => line_y,
+                    None => break,
+                },
+                Origin::TopLeft => y + index * style.line_height(),
+            };
 
-        for letter in text.chars() {
-            let letter_metrics = font.metrics(letter, size);
-            self.draw_letter(letter, x_cursor, y, size, &font);
+            let width = style.line_width(&font, line);
+            let mut x_cursor = match style.align {
+                TextAlign::Left => x,
+                TextAlign::Center => x.saturating_sub(width / 2),
+                TextAlign::Right => x.saturating_sub(width),
+            };
 
-            x_cursor += letter_metrics.advance_width.round() as usize;
+            for letter in line.chars() {
+                self.draw_glyph(letter, x_cursor, line_y, style, &font);
+                x_cursor += style.advance(&font, letter);
+            }
         }
+        Ok(())
     }
 
     /// Draw a singular letter to the display (the function you are probably looking for is `draw_text`)
     pub fn draw_letter(&mut self, letter: char, x: usize, y: usize, size: f32, font: &Font) {
-        let (metrics, bitmap) = font.rasterize(letter, size);
+        self.draw_glyph(letter, x, y, &TextStyle::new(size), font)
+    }
 
-        for (index, byte) in bitmap.into_iter().enumerate() {
-            let width = metrics.width;
-            let height = metrics.height;
+    fn draw_glyph(&mut self, letter: char, x: usize, y: usize, style: &TextStyle, font: &Font) {
+        let (width, height, lit) = match style.glyphs.get(&letter) {
+            Some(glyph) => (glyph.width, glyph.height, Cow::Borrowed(&glyph.pixels[..])),
+            None => {
+                let (metrics, bitmap) = font.rasterize(letter, style.size);
+                let lit = bitmap
+                    .iter()
+                    .map(|byte| (*byte as f32 / 255.0).round() as i32 == 1)
+                    .collect();
+                (metrics.width, metrics.height, Cow::Owned(lit))
+            }
+        };
+        // The y of each row of the glyph, with the bottom of the glyph at the bottom of the line
+        let origin = self.origin;
+        let row_y = |row: usize| match origin {
+            Origin::BottomLeft => Some(y + height - row),
+            Origin::TopLeft => (y + style.line_height() + row).checked_sub(height),
+        };
 
+        for (index, enabled) in lit.iter().enumerate() {
             let col = x + (index % width);
-            let row = y + height - (index / width);
-            let enabled = (byte as f32 / 255.0).round() as i32 == 1;
-            self.set_pixel(col, row, enabled)
+            let Some(row) = row_y(index / width) else {
+                continue;
+            };
+            self.set_pixel(col, row, *enabled != style.inverted)
+        }
+
+        if style.bold {
+            // Only add to the letter, so that its background doesn't cover what was just drawn
+            for (index, enabled) in lit.iter().enumerate() {
+                if *enabled {
+                    let col = x + (index % width) + 1;
+                    if let Some(row) = row_y(index / width) {
+                        self.set_pixel(col, row, !style.inverted);
+                    }
+                }
+            }
         }
     }
 
     /// Send the current state of the screen to the wrapped HID device
     pub fn send(&mut self) -> Result<(), HidError> {
-        let mut packets = self.to_packets();
+        let result = self.send_frame();
+        if let Some(metrics) = &self.metrics {
+            match result {
+                Ok(()) => metrics.record_frame(),
+                Err(_) => metrics.record_dropped_frame(),
+            }
+        }
+        result
+    }
 
-        // Filter out packets for regions of the screen which haven't changed since last time
-        if let Some(prev_packets) = &self._prev_packets {
-            packets.retain(|packet| !prev_packets.contains(packet))
-        };
+    fn send_frame(&mut self) -> Result<(), HidError> {
+        if let FrameProtocol::QuantumPainter {
+            foreground,
+            background,
+        } = self.protocol
+        {
+            encode_monochrome_into(&self.framebuffer, foreground, background, &mut self.encoded);
+            let written = send_wide_frame(
+                self.device.as_ref(),
+                self.report_prefix,
+                COLOR_REPORT_ID,
+                &self.encoded,
+                &mut self._prev_frame,
+            )?;
+            self.record_bytes(written);
+            return self.device.end_frame();
+        }
 
-        self._prev_packets = Some(self.to_packets());
+        let (width, height) = (self.width(), self.height());
+        self.layout.encode_into(
+            self.framebuffer.as_bytes(),
+            width,
+            height,
+            &mut self.encoded,
+        );
+
+        let packets = frame_packets(
+            &self.encoded,
+            self._prev_frame.as_deref(),
+            chunk_size(self.report_prefix),
+        )
+        .ok_or_else(|| HidError::HidApiError {
+            message: "The screen is too large to send, it needs more than 256 packets".into(),
+        })?;
 
+        let mut report = [0; PAYLOAD_SIZE];
+        let mut result = Ok(());
         for packet in packets {
-            packet.send(self.device.as_ref())?;
+            packet.write_to(&mut report);
+            if let Err(error) = self.write_report(&report) {
+                result = Err(error);
+                break;
+            }
+        }
+        if let Err(error) = result {
+            // Part of the frame may not have made it, so send all of it next time
+            self._prev_frame = None;
+            return Err(error);
         }
 
-        Ok(())
+        // Keep the frame that was just sent and reuse the old one for encoding the next
+        match &mut self._prev_frame {
+            Some(prev_frame) => std::mem::swap(prev_frame, &mut self.encoded),
+            None => self._prev_frame = Some(self.encoded.clone()),
+        }
+
+        self.device.end_frame()
     }
 
-    /// Set all pixels on the screen to their off state
-    pub fn clear(&mut self) {
-        self.data = vec![0; (self.width * self.height) / 8_usize];
+    /// The device the screen is drawn on, for sending reports which don't come from the
+    /// framebuffer (e.g. replaying a capture)
+    pub fn device(&self) -> &dyn HidAdapter {
+        self.device.as_ref()
     }
 
-    /// Set all pixels on the screen to their on state
-    pub fn fill_all(&mut self) {
-        self.data = vec![1; (self.width * self.height) / 8_usize];
+    /// The manufacturer, product, IDs and path of the device the screen is drawn on, as far as
+    /// they are known. Devices given to `from_device` only report what they know about themselves
+    pub fn device_info(&self) -> &DeviceInfo {
+        &self.device_info
     }
 
-    /// Paint a square region on the screen
-    pub fn paint_region(
-        &mut self,
-        min_x: usize,
-        min_y: usize,
-        max_x: usize,
-        max_y: usize,
-        enabled: bool,
-    ) {
-        for x in min_x..max_x {
-            for y in min_y..max_y {
-                self.set_pixel(x, y, enabled)
+    /// Send a control command (e.g. a brightness change) to the device. Unlike drawing, this takes
+    /// effect immediately without needing to call `send`. Commands the firmware said it doesn't
+    /// support are skipped
+    pub fn send_control(&self, message: &ControlMessage) -> Result<(), HidError> {
+        let supported = match message {
+            ControlMessage::Brightness(_) | ControlMessage::Power(_) => {
+                self.capabilities().brightness
             }
+            ControlMessage::Heartbeat => true,
+        };
+        if !supported {
+            return Ok(());
         }
+        self.write_report(&message.to_bytes())?;
+        Ok(())
     }
 
-    /// Get the current state of the pixel on the screen. This function does not communicate
-    /// with the underlying device and instead reads from the local version of what the screen
-    /// *should* look like at the moment
-    pub fn get_pixel(&self, x: usize, y: usize) -> bool {
-        let byte_index = (x + y * self.width) / 8;
-        let bit_index: u8 = 7 - ((x % 8) as u8);
-
-        let byte = self.data[byte_index];
-        get_bit_at_index(byte, bit_index)
+    /// Check that the firmware speaks a compatible version of the protocol, waiting up to
+    /// `timeout` for it to answer. Worth doing once after connecting, as an outdated receiver
+    /// otherwise shows up as garbage or nothing at all on the screen
+    pub fn handshake(&self, timeout: Duration) -> Result<ProtocolVersion, HidError> {
+        let answer = handshake(self, timeout)?;
+        self.capabilities.set(answer.capabilities);
+        Ok(answer.version)
     }
 
-    /// Underlying function for drawing to the canvas, if provided coordinates are out of range,
-    /// this function will fail silently
-    ///
-    /// # Arguments
-    /// * `x` - The x coordinate of the pixel to set
-    /// * `y` - The y coordinate of the pixel to set
-    /// * `enabled` - Whether to set the pixel to an enabled or disabled state (on/off)
-    pub fn set_pixel(&mut self, x: usize, y: usize, enabled: bool) {
-        if x >= self.width || y >= self.height {
-            // If a pixel is rendered outside of the canvas, fail silently
-            return;
-        }
+    /// What the firmware supports, as of the last handshake (or `detect_size`). Until then every
+    /// feature the firmware might have is assumed to be there
+    pub fn capabilities(&self) -> Capabilities {
+        self.capabilities.get()
+    }
 
-        let x = x as usize;
-        let y = y as usize;
+    /// Ask the firmware how big its screen is, waiting up to `timeout` for it to answer, and
+    /// resize the screen to match. This clears the framebuffer. Fails if the size can't be packed
+    /// into frames: the width has to be a multiple of 8, as does the height when sending in the
+    /// `BufferLayout::OledWriteRaw` layout
+    pub fn detect_size(&mut self, timeout: Duration) -> Result<ScreenGeometry, HidError> {
+        let answer = handshake(self, timeout)?;
+        self.capabilities.set(answer.capabilities);
+        let geometry = answer.geometry()?;
+        let (width, height) = (geometry.width, geometry.height);
 
-        let target_byte = (x / 8) * self.height + y;
-        let target_bit: u8 = 7 - ((x % 8) as u8);
+        let pages = matches!(self.protocol, FrameProtocol::Oled)
+            && self.layout == BufferLayout::OledWriteRaw;
+        if width % 8 != 0 || (pages && height % 8 != 0) {
+            let sides = if pages { "width and height" } else { "width" };
+            return Err(HidError::HidApiError {
+                message: format!(
+                    "The keyboard reported a {width}x{height} screen, but the {sides} of the \
+                     screen has to be a multiple of 8 to send frames to it"
+                ),
+            });
+        }
 
-        self.data[target_byte] = set_bit_at_index(self.data[target_byte], target_bit, enabled);
+        self.framebuffer = Framebuffer::new(width, height);
+        self.encoded = vec![0; self.protocol.encoded_len(width, height)];
+        self._prev_frame = None;
+        Ok(geometry)
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use std::cell::RefCell;
+    /// Wait up to `timeout` milliseconds (-1 to block) for an event from the keyboard. Reports
+    /// which aren't events, such as the firmware echoing back screen payloads, are skipped.
+    /// Returns `None` straight away if the firmware said it doesn't send events
+    pub fn poll_event(&self, timeout: i32) -> Result<Option<KeyboardEvent>, HidError> {
+        if !self.capabilities().events {
+            return Ok(None);
+        }
+        let mut buf = [0; PAYLOAD_SIZE];
 
-    use super::*;
+        loop {
+            let Some(report) = self.read_report(&mut buf, timeout)? else {
+                return Ok(None);
+            };
+            if let Some(event) = KeyboardEvent::from_bytes(report) {
+                return Ok(Some(event));
+            }
+        }
+    }
 
-    #[derive(Clone)]
-    struct MockHidDevice {
-        pub write_log: RefCell<Vec<Vec<u8>>>,
+    /// Events from the keyboard as they arrive, waiting up to `timeout` milliseconds for each one.
+    /// The iterator ends once nothing arrives in time, or never with a timeout of -1. Stops after
+    /// the first error
+    pub fn events(
+        &self,
+        timeout: i32,
+    ) -> impl Iterator<Item = Result<KeyboardEvent, HidError>> + '_ {
+        let mut failed = false;
+        std::iter::from_fn(move || {
+            if failed {
+                return None;
+            }
+            let event = self.poll_event(timeout).transpose();
+            failed = matches!(event, Some(Err(_)));
+            event
+        })
     }
 
-    impl MockHidDevice {
-        pub const fn new() -> Self {
-            MockHidDevice {
-                write_log: RefCell::new(vec![]),
+    /// Read every event which arrives within `timeout` milliseconds of the last one, calling
+    /// `on_turn` with the encoder index and steps (see `KeyboardEvent::encoder_steps`) for each
+    /// encoder turn. Every other event is returned, in order, for the rest of the UI to handle
+    pub fn handle_encoder_turns(
+        &self,
+        timeout: i32,
+        mut on_turn: impl FnMut(u8, i32),
+    ) -> Result<Vec<KeyboardEvent>, HidError> {
+        let mut others = vec![];
+        for event in self.events(timeout) {
+            let event = event?;
+            match event.encoder_steps() {
+                Some((index, steps)) => on_turn(index, steps),
+                None => others.push(event),
             }
         }
+        Ok(others)
     }
 
-    impl HidAdapter for MockHidDevice {
-        fn write(&self, data: &[u8]) -> Result<usize, HidError> {
-            self.write_log.borrow_mut().push(data.into());
-            Ok(1)
+    /// Take a copy of the current framebuffer so that it can be put back later with `restore`.
+    /// Useful for temporarily drawing over the screen (e.g. popups)
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            data: self.framebuffer.data.clone(),
         }
+    }
 
-        fn as_any(&self) -> &dyn std::any::Any {
-            self
+    /// Replace the framebuffer with a previously taken snapshot. Snapshots taken from a screen of
+    /// a different size are ignored
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        if snapshot.data.len() == self.framebuffer.data.len() {
+            self.framebuffer.data.copy_from_slice(&snapshot.data);
         }
     }
 
-    #[test]
-    fn test_display_oled_screen() {
-        let mock_device = MockHidDevice::new();
-        let mut screen = OledScreen::from_device(mock_device, 32, 128).unwrap();
-        for i in 0..128 {
-            screen.set_pixel(0, i, true);
-            screen.set_pixel(31, i, true);
-        }
+    /// Set all pixels on the screen to their off state
+    pub fn clear(&mut self) {
+        self.fill(0);
     }
 
-    #[test]
-    fn test_to_packets() {
-        let mock_device = MockHidDevice::new();
-        let screen = OledScreen::from_device(mock_device, 32, 128).unwrap();
-        let packets = screen.to_packets();
-        assert_eq!(packets.len(), 18);
+    /// Set all pixels on the screen to their on state
+    pub fn fill_all(&mut self) {
+        self.fill(0xFF);
     }
 
-    #[test]
-    fn test_draw_image_file() {
-        let mock_device = MockHidDevice::new();
-        let mut screen = OledScreen::from_device(mock_device, 32, 128).unwrap();
-        screen.draw_image_file("assets/bitmaps/test_square.bmp", 0, 0, &ImageSizing::Cover);
+    /// Set every byte of the framebuffer to the given value. Each byte covers a strip of eight
+    /// pixels side by side, so e.g. `0b01010101` draws vertical stripes
+    pub fn fill(&mut self, byte: u8) {
+        self.framebuffer.fill(byte);
+    }
 
-        for i in 0..5 {
-            for j in 0..5 {
-                assert!(screen.get_pixel(j, i));
+    /// Set every byte of the given rows of the framebuffer to a value, in the same way as
+    /// `fill`. Rows outside of the screen are ignored
+    pub fn fill_rows(&mut self, rows: impl RangeBounds<usize>, value: u8) {
+        match self.origin {
+            Origin::BottomLeft => self.framebuffer.fill_rows(rows, value),
+            Origin::TopLeft => {
+                let rows = self.framebuffer.row_range(rows);
+                let height = self.height();
+                self.framebuffer.fill_rows(
+                    height.saturating_sub(rows.end)..height.saturating_sub(rows.start),
+                    value,
+                );
+            }
+        }
+    }
+
+    /// Fill the screen with random pixels, see `Framebuffer::fill_noise`
+    pub fn fill_noise(&mut self, seed: u64) {
+        self.framebuffer.fill_noise(seed);
+    }
+
+    /// Fill the screen with alternating squares `cell` pixels wide, see
+    /// `Framebuffer::fill_checkerboard`. The squares are lined up with the bottom left of the
+    /// screen whatever its origin is, so that they match the bytes sent to the device
+    pub fn fill_checkerboard(&mut self, cell: usize) {
+        self.framebuffer.fill_checkerboard(cell);
+    }
+
+    /// Light the outermost pixels of the screen, leaving the rest as they are
+    pub fn fill_border(&mut self) {
+        self.framebuffer.fill_border();
+    }
+
+    /// Set all pixels on the screen to the given state
+    pub fn fill_pixels(&mut self, enabled: bool) {
+        self.framebuffer.fill_pixels(enabled);
+    }
+
+    /// Paint a square region on the screen
+    pub fn paint_region(&mut self, region: Rect, enabled: bool) {
+        let region = self.framebuffer_rect(region);
+        self.framebuffer.paint_region(region, enabled);
+    }
+
+    /// Flip the state of every pixel in a square region on the screen
+    pub fn invert_region(&mut self, region: Rect) {
+        let region = self.framebuffer_rect(region);
+        self.framebuffer.invert_region(region);
+    }
+
+    /// Draw a one pixel wide straight line between two points, including both ends
+    pub fn draw_line(&mut self, from: Point, to: Point) {
+        // Lines off the top of the screen wrap around to huge coordinates here, which are ignored
+        // when drawn the same as any other coordinate off the screen
+        let from = Point::new(from.x, self.framebuffer_y(from.y));
+        let to = Point::new(to.x, self.framebuffer_y(to.y));
+        self.framebuffer.draw_line(from, to);
+    }
+
+    /// Draw the outline of a square region on the screen, use `paint_region` to fill it in
+    /// instead
+    pub fn draw_rect(&mut self, region: Rect) {
+        let region = self.framebuffer_rect(region);
+        self.framebuffer.draw_rect(region);
+    }
+
+    /// Render the screen as braille characters, each covering 2x4 pixels, with the top of the
+    /// screen (the highest y coordinates) on the first line. Useful for previewing frames in a
+    /// terminal
+    pub fn to_braille(&self) -> String {
+        render_braille(self.framebuffer.as_bytes(), self.width(), self.height())
+    }
+
+    /// The width of the screen in pixels
+    pub fn width(&self) -> usize {
+        self.framebuffer.width()
+    }
+
+    /// The height of the screen in pixels
+    pub fn height(&self) -> usize {
+        self.framebuffer.height()
+    }
+
+    /// Get the current state of the pixel on the screen. This function does not communicate
+    /// with the underlying device and instead reads from the local version of what the screen
+    /// *should* look like at the moment
+    pub fn get_pixel(&self, x: usize, y: usize) -> bool {
+        self.framebuffer.get_pixel(x, self.framebuffer_y(y))
+    }
+
+    /// Read a square region of the screen, in the same way as `get_pixel`. Each row, starting from
+    /// the one nearest the origin, is packed into whole bytes with its leftmost pixel in the most significant bit of
+    /// the first one, the same way round as a PBM image. Pixels outside of the screen read as off
+    pub fn get_region(&self, region: Rect) -> Vec<u8> {
+        (region.min_y()..region.max_y())
+            .flat_map(|y| self.pack_pixels((region.min_x()..region.max_x()).map(|x| (x, y))))
+            .collect()
+    }
+
+    /// Write pixels packed the way `get_region` returns them back into a region of the same size,
+    /// e.g. to put back what was underneath something drawn over the top. Pixels outside of the
+    /// screen are skipped, and pixels missing from `packed` are turned off
+    pub fn set_region(&mut self, region: Rect, packed: &[u8]) {
+        let row_bytes = region.width().div_ceil(8);
+        for (row, y) in (region.min_y()..region.max_y()).enumerate() {
+            for (column, x) in (region.min_x()..region.max_x()).enumerate() {
+                let byte = packed.get(row * row_bytes + column / 8).copied();
+                let enabled = byte.is_some_and(|byte| byte & (0x80 >> (column % 8)) != 0);
+                if x < self.width() && y < self.height() {
+                    self.set_pixel(x, y, enabled);
+                }
+            }
+        }
+    }
+
+    /// Read a whole row of the screen, packed in the same way as `get_region`
+    pub fn get_row(&self, y: usize) -> Vec<u8> {
+        self.get_region(Rect::new(0, y, self.width(), 1))
+    }
+
+    /// Read a whole column of the screen from y 0 up, packed into bytes in the same way as a row
+    /// of `get_region`
+    pub fn get_column(&self, x: usize) -> Vec<u8> {
+        self.pack_pixels((0..self.height()).map(|y| (x, y)))
+    }
+
+    fn pack_pixels(&self, pixels: impl Iterator<Item = (usize, usize)>) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for (index, (x, y)) in pixels.enumerate() {
+            if index % 8 == 0 {
+                bytes.push(0);
+            }
+            if x < self.width() && y < self.height() && self.get_pixel(x, y) {
+                *bytes.last_mut().unwrap() |= 0x80 >> (index % 8);
+            }
+        }
+        bytes
+    }
+
+    /// Underlying function for drawing to the canvas, if provided coordinates are out of range,
+    /// this function will fail silently
+    ///
+    /// # Arguments
+    /// * `x` - The x coordinate of the pixel to set
+    /// * `y` - The y coordinate of the pixel to set
+    /// * `enabled` - Whether to set the pixel to an enabled or disabled state (on/off)
+    pub fn set_pixel(&mut self, x: usize, y: usize, enabled: bool) {
+        self.framebuffer
+            .set_pixel(x, self.framebuffer_y(y), enabled);
+    }
+
+    /// Convert a y coordinate relative to the origin into one in the framebuffer
+    fn framebuffer_y(&self, y: usize) -> usize {
+        match self.origin {
+            Origin::BottomLeft => y,
+            Origin::TopLeft => (self.height() - 1).wrapping_sub(y),
+        }
+    }
+
+    /// Convert a region relative to the origin into the same region in the framebuffer
+    fn framebuffer_rect(&self, region: Rect) -> Rect {
+        match self.origin {
+            Origin::BottomLeft => region,
+            Origin::TopLeft => Rect::from_corners(
+                Point::new(region.min_x(), self.height().saturating_sub(region.max_y())),
+                Point::new(region.max_x(), self.height().saturating_sub(region.min_y())),
+            ),
+        }
+    }
+}
+
+impl Drop for OledScreen {
+    fn drop(&mut self) {
+        if self.clear_on_drop {
+            self.clear();
+            let _ = self.send();
+        }
+        if self.power_off_on_drop {
+            let _ = self.send_control(&ControlMessage::Power(false));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::control::CONTROL_REPORT_ID;
+    use crate::data::{SCREEN_REPORT_ID, VIA_REPORT_PREFIX};
+    use crate::handshake::HANDSHAKE_REPORT_ID;
+    use crate::testing::{assert_ascii_at, Failure, MockDevice};
+
+    #[test]
+    fn test_display_oled_screen() {
+        let mock_device = MockDevice::new();
+        let mut screen = OledScreen::from_device(mock_device, 32, 128).unwrap();
+        for i in 0..128 {
+            screen.set_pixel(0, i, true);
+            screen.set_pixel(31, i, true);
+        }
+
+        let expected_row = format!("#{}#", ".".repeat(30));
+        let ascii = crate::testing::to_ascii(&screen);
+        assert_eq!(ascii.lines().count(), 128);
+        assert!(ascii.lines().all(|row| row == expected_row));
+    }
+
+    #[test]
+    fn test_send_packets() {
+        let mock_device = MockDevice::new();
+        let mut screen = OledScreen::from_device(mock_device.clone(), 32, 128).unwrap();
+        screen.send().unwrap();
+
+        // 512 bytes split into chunks of 30, the last one padded with zeroes
+        let writes = mock_device.writes();
+        assert_eq!(writes.len(), 18);
+        assert!(writes.iter().all(|write| write.len() == PAYLOAD_SIZE));
+        assert_eq!(writes[17][..2], [SCREEN_REPORT_ID, 17]);
+    }
+
+    #[test]
+    fn test_draw_image_file() {
+        let mock_device = MockDevice::new();
+        let mut screen = OledScreen::from_device(mock_device, 32, 128).unwrap();
+        screen
+            .draw_image_file("assets/bitmaps/test_square.bmp", 0, 0, &ImageSizing::Cover)
+            .unwrap();
+
+        for i in 0..5 {
+            for j in 0..5 {
+                assert!(screen.get_pixel(j, i));
             }
         }
     }
 
+    #[test]
+    fn test_draw_image_bytes() {
+        let mut image = GrayImage::new(4, 4);
+        image.put_pixel(0, 0, image::Luma([255]));
+        let mut png = std::io::Cursor::new(vec![]);
+        DynamicImage::ImageLuma8(image)
+            .write_to(&mut png, ImageFormat::Png)
+            .unwrap();
+        let png = png.into_inner();
+
+        let mut screen = OledScreen::from_device(MockDevice::new(), 8, 8).unwrap();
+        screen
+            .draw_image_bytes(&png, None, 0, 0, &ImageSizing::Original)
+            .unwrap();
+        assert!(screen.get_pixel(0, 4));
+        assert_eq!(
+            screen.get_region(Rect::new(0, 0, 8, 8)).iter().sum::<u8>(),
+            0x80
+        );
+
+        let error = screen
+            .draw_image_bytes(&png, Some(ImageFormat::Jpeg), 0, 0, &ImageSizing::Original)
+            .unwrap_err();
+        assert!(error.to_string().starts_with("Failed to load image"));
+    }
+
+    #[test]
+    fn test_prepare_images() {
+        let square = image::open("assets/bitmaps/test_square.bmp").unwrap();
+        let images = vec![
+            square.clone(),
+            DynamicImage::new_luma8(8, 8),
+            square.clone(),
+        ];
+        let prepared = prepare_images(
+            images,
+            32,
+            128,
+            &ImageSizing::Original,
+            Binarization::Dither,
+        );
+
+        assert_eq!(prepared.len(), 3);
+        assert_eq!(
+            prepared[0],
+            prepare_image(square, &ImageSizing::Original, Binarization::Dither)
+        );
+        assert_eq!(prepared[0], prepared[2]);
+        assert_eq!((prepared[1].width(), prepared[1].height()), (8, 8));
+    }
+
+    #[test]
+    fn test_prepare_image_thresholds() {
+        let pixels = vec![10, 20, 30, 200, 210, 90, 100, 110];
+        let image = DynamicImage::ImageLuma8(GrayImage::from_raw(8, 1, pixels).unwrap());
+        let lit = |binarization| {
+            let prepared = prepare_image(image.clone(), &ImageSizing::Original, binarization);
+            prepared
+                .image
+                .pixels()
+                .map(|pixel| pixel.0[0] == 255)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            lit(Binarization::Threshold(100)),
+            [false, false, false, true, true, false, false, true]
+        );
+        assert_eq!(
+            lit(Binarization::Otsu),
+            [false, false, false, true, true, false, false, false]
+        );
+    }
+
+    #[test]
+    fn test_cover_crops_to_centre() {
+        // Three bands one above the other, with only the middle one lit
+        let image = GrayImage::from_fn(128, 96, |_, y| image::Luma([(y / 32 == 1) as u8 * 255]));
+
+        let mut screen = OledScreen::from_device(MockDevice::new(), 128, 32).unwrap();
+        screen.draw_image(DynamicImage::ImageLuma8(image), 0, 0, &ImageSizing::Cover);
+        assert!((0..128).all(|x| (3..30).all(|y| screen.get_pixel(x, y))));
+    }
+
+    #[test]
+    fn test_draw_image_in() {
+        let image = GrayImage::from_pixel(8, 8, image::Luma([255]));
+        let mut screen = OledScreen::from_device(MockDevice::new(), 32, 32).unwrap();
+        screen.draw_image_in(
+            DynamicImage::ImageLuma8(image),
+            Rect::new(4, 4, 16, 8),
+            &ImageSizing::Contain,
+        );
+
+        // Scaled to fit the height of the region and centred across it
+        let lit: Vec<_> = (0..32).filter(|x| screen.get_pixel(*x, 8)).collect();
+        assert_eq!(lit, (8..16).collect::<Vec<_>>());
+        assert!(screen.get_pixel(8, 4) && screen.get_pixel(8, 11));
+        assert!(!screen.get_pixel(8, 3) && !screen.get_pixel(8, 12));
+
+        let mut image = GrayImage::new(6, 1);
+        image.put_pixel(2, 0, image::Luma([255]));
+        let prepared = prepare_image_for(
+            DynamicImage::ImageLuma8(image),
+            2,
+            1,
+            &ImageSizing::Original,
+            Binarization::Threshold(127),
+        );
+        screen.clear();
+        screen.draw_dithered_image_in(&prepared, Rect::new(0, 0, 2, 1));
+        let lit: Vec<_> = (0..32).filter(|x| screen.get_pixel(*x, 0)).collect();
+        assert_eq!(lit, [0]);
+    }
+
+    #[test]
+    fn test_stretch() {
+        let image = GrayImage::from_pixel(10, 10, image::Luma([255]));
+
+        let mut screen = OledScreen::from_device(MockDevice::new(), 64, 16).unwrap();
+        screen.draw_image(DynamicImage::ImageLuma8(image), 0, 0, &ImageSizing::Stretch);
+        assert!((0..64).all(|x| (2..14).all(|y| screen.get_pixel(x, y))));
+    }
+
+    #[test]
+    fn test_draw_transparent_image() {
+        let pixels = vec![0, 0, 0, 0, 0, 0, 0, 255, 255, 255, 255, 100];
+        let image = DynamicImage::ImageRgba8(image::RgbaImage::from_raw(3, 1, pixels).unwrap());
+        let prepared = prepare_image(image, &ImageSizing::Original, Binarization::Dither);
+
+        let mut screen = OledScreen::from_device(MockDevice::new(), 8, 8).unwrap();
+        screen.fill_all();
+        screen.draw_dithered_image(&prepared, 0, 0);
+
+        // Only the opaque black pixel is drawn, the other two are see-through
+        assert!(screen.get_pixel(0, 1));
+        assert!(!screen.get_pixel(1, 1));
+        assert!(screen.get_pixel(2, 1));
+    }
+
     #[test]
     fn test_draw_text() {
-        let mock_device = MockHidDevice::new();
+        let mock_device = MockDevice::new();
         let mut screen = OledScreen::from_device(mock_device, 32, 128).unwrap();
-        screen.draw_text("Hey", 0, 0, 8.0, None);
+        screen
+            .draw_text("Hey", 0, 0, &TextStyle::default())
+            .unwrap();
 
         assert_eq!(
-            screen.data,
+            screen.framebuffer.data,
             vec![
                 0, 136, 8, 138, 138, 8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
                 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
@@ -391,20 +1591,599 @@ mod tests {
     }
 
     #[test]
-    fn test_packet_filtering() {
-        let mock_device = MockHidDevice::new();
+    fn test_load_errors() {
+        let mut screen = OledScreen::from_device(MockDevice::new(), 32, 128).unwrap();
+
+        let error = screen
+            .draw_image_file("assets/missing.png", 0, 0, &ImageSizing::Original)
+            .unwrap_err();
+        assert!(matches!(error, Error::Image(_)));
+
+        let error = screen
+            .draw_text(
+                "Hey",
+                0,
+                0,
+                &TextStyle::default().with_font(Some("assets/missing.ttf")),
+            )
+            .unwrap_err();
+        assert!(matches!(error, Error::Io(_)));
+
+        let style = TextStyle::default().with_font(Some("Cargo.toml"));
+        let error = text_width("Hey", &style).unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("Failed to load font: Cargo.toml"));
+    }
+
+    #[test]
+    fn test_text_width() {
+        let style = TextStyle::default();
+        let a = text_width("a", &style).unwrap();
+        assert_eq!(text_width("", &style).unwrap(), 0);
+        assert_eq!(text_width("aa", &style).unwrap(), 2 * a);
+        assert_eq!(text_width("a\naaa\n", &style).unwrap(), 3 * a);
+
+        let spaced = style.clone().with_tracking(2).with_bold(true);
+        assert_eq!(text_width("aa", &spaced).unwrap(), 2 * (a + 2) + 1);
+        let squashed = TextStyle::default().with_tracking(-100);
+        assert_eq!(text_width("aa", &squashed).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_custom_glyphs() {
+        let glyph = Glyph::from_ascii(
+            "
+            #
+            ##
+            ",
+        );
+        assert_eq!((glyph.width(), glyph.height()), (2, 2));
+
+        let style = TextStyle::default().with_glyph('▶', glyph);
+        assert_eq!(text_width("▶▶", &style).unwrap(), 6);
+        assert_eq!(
+            text_width("▶▶", &style.clone().with_tracking(-1)).unwrap(),
+            4
+        );
+
+        let mut screen = OledScreen::from_device(MockDevice::new(), 8, 8).unwrap();
+        screen.draw_text("▶▶", 0, 0, &style).unwrap();
+        assert_ascii_at(
+            &screen,
+            0,
+            1,
+            "
+            #..#..
+            ##.##.
+            ",
+        );
+    }
+
+    #[test]
+    fn test_text_style() {
+        let lit = |screen: &OledScreen| -> Vec<(usize, usize)> {
+            (0..screen.width())
+                .flat_map(|x| (0..screen.height()).map(move |y| (x, y)))
+                .filter(|&(x, y)| screen.get_pixel(x, y))
+                .collect()
+        };
+        let draw = |text: &str, x: usize, y: usize, style: &TextStyle| {
+            let mut screen = OledScreen::from_device(MockDevice::new(), 32, 32).unwrap();
+            screen.draw_text(text, x, y, style).unwrap();
+            lit(&screen)
+        };
+
+        let style = TextStyle::default();
+        let width = text_width("Hi", &style).unwrap();
+        let plain = draw("Hi", 0, 20, &style);
+        assert!(!plain.is_empty());
+
+        // Aligned text is the same, just moved along
+        let shifted = |pixels: &[(usize, usize)], dx: usize| -> Vec<(usize, usize)> {
+            pixels.iter().map(|&(x, y)| (x + dx, y)).collect()
+        };
+        let centered = draw("Hi", 16, 20, &style.clone().with_align(TextAlign::Center));
+        assert_eq!(centered, shifted(&plain, 16 - width / 2));
+        let right = draw("Hi", 20, 20, &style.clone().with_align(TextAlign::Right));
+        assert_eq!(right, shifted(&plain, 20 - width));
+
+        // The second line is drawn a line height below the first
+        let lines = draw("Hi\nHi", 0, 20, &style.clone().with_line_height(10));
+        let second_line: Vec<_> = plain.iter().map(|&(x, y)| (x, y - 10)).collect();
+        assert!(lines
+            .iter()
+            .all(|pixel| plain.contains(pixel) || second_line.contains(pixel)));
+        assert_eq!(lines.len(), 2 * plain.len());
+
+        let bold = draw("Hi", 0, 20, &style.clone().with_bold(true));
+        assert!(plain.iter().all(|pixel| bold.contains(pixel)));
+        assert!(bold.len() > plain.len());
+
+        let mut screen = OledScreen::from_device(MockDevice::new(), 32, 32).unwrap();
+        screen.fill_all();
+        screen
+            .draw_text("Hi", 0, 20, &style.clone().with_inverted(true))
+            .unwrap();
+        assert!(plain.iter().all(|&(x, y)| !screen.get_pixel(x, y)));
+    }
+
+    #[test]
+    fn test_snapshot_restore() {
+        let mock_device = MockDevice::new();
         let mut screen = OledScreen::from_device(mock_device, 32, 128).unwrap();
+        screen.set_pixel(3, 7, true);
+
+        let snapshot = screen.snapshot();
+        screen.paint_region(Rect::new(0, 0, 32, 128), true);
+        screen.restore(&snapshot);
+
+        assert!(screen.get_pixel(3, 7));
+        assert!(!screen.get_pixel(4, 7));
+        assert_eq!(snapshot, screen.snapshot());
+    }
+
+    #[test]
+    fn test_fill() {
+        let mut screen = OledScreen::from_device(MockDevice::new(), 32, 128).unwrap();
         screen.fill_all();
-        screen.send().unwrap();
+        assert!((0..32).all(|x| (0..128).all(|y| screen.get_pixel(x, y))));
+
+        screen.fill(0b01010101);
+        assert!(screen.get_pixel(0, 5) && !screen.get_pixel(1, 5));
+
+        screen.fill_pixels(false);
+        assert_eq!(screen.framebuffer.data, vec![0; 512]);
+    }
+
+    #[test]
+    fn test_fill_rows() {
+        let mut screen = OledScreen::from_device(MockDevice::new(), 16, 8).unwrap();
+        screen.fill_rows(2..4, 0xFF);
+        screen.fill_rows(6.., 0b00000011);
+        screen.fill_rows(7..20, 0b00000001);
+
+        crate::testing::assert_ascii(
+            &screen,
+            "
+            #.......#.......
+            ##......##......
+            ................
+            ................
+            ################
+            ################
+            ................
+            ................
+            ",
+        );
+    }
+
+    #[test]
+    fn test_widgets_on_top_left_screen() {
+        use crate::widgets::{Menu, Widget};
+
+        // A widget at the top of a top-left screen looks the same as one placed at the top of a
+        // bottom-left screen
+        let items = vec!["One".into(), "Two".into()];
+        let mut bottom_left = OledScreen::from_device(MockDevice::new(), 32, 32).unwrap();
+        let mut top_left = OledScreen::from_device(MockDevice::new(), 32, 32)
+            .unwrap()
+            .with_origin(Origin::TopLeft);
+        Menu::new(items.clone(), 0, 8, 32, 24, 8.0)
+            .draw(&mut bottom_left)
+            .unwrap();
+        Menu::new(items, 0, 0, 32, 24, 8.0)
+            .draw(&mut top_left)
+            .unwrap();
+        assert_eq!(top_left.snapshot(), bottom_left.snapshot());
+
+        // The screen's own origin is back in place afterwards
+        top_left.clear();
+        top_left.set_pixel(0, 0, true);
+        assert!(top_left.framebuffer().get_pixel(0, 31));
+    }
+
+    #[test]
+    fn test_top_left_origin() {
+        let mut screen = OledScreen::from_device(MockDevice::new(), 16, 8)
+            .unwrap()
+            .with_origin(Origin::TopLeft);
+        screen.set_pixel(0, 0, true);
+        screen.paint_region(Rect::new(2, 1, 2, 2), true);
+        screen.draw_line(Point::new(0, 7), Point::new(3, 7));
+        screen.fill_rows(5..6, 0b00000001);
+
+        crate::testing::assert_ascii(
+            &screen,
+            "
+            #...............
+            ..##............
+            ..##............
+            ................
+            ................
+            #.......#.......
+            ................
+            ####............
+            ",
+        );
+        assert!(screen.get_pixel(0, 0));
+        assert!(!screen.get_pixel(0, 1));
+
+        // Text hangs down from y, ending up where it would from the bottom-left equivalent
+        let style = TextStyle::default();
+        let mut bottom_left = OledScreen::from_device(MockDevice::new(), 32, 32).unwrap();
+        let mut top_left = OledScreen::from_device(MockDevice::new(), 32, 32)
+            .unwrap()
+            .with_origin(Origin::TopLeft);
+        bottom_left
+            .draw_text("Hey", 0, 32 - style.line_height() - 1, &style)
+            .unwrap();
+        top_left.draw_text("Hey", 0, 0, &style).unwrap();
+        assert_eq!(bottom_left.snapshot(), top_left.snapshot());
+    }
+
+    #[test]
+    fn test_draw_clipped() {
+        let mut screen = OledScreen::from_device(MockDevice::new(), 16, 8)
+            .unwrap()
+            .with_origin(Origin::TopLeft);
+        screen.set_pixel(0, 0, true);
+        screen.draw_clipped(Rect::new(2, 1, 3, 2), |screen| {
+            screen.clear();
+            screen.draw_line(Point::new(0, 1), Point::new(15, 1));
+        });
+
+        crate::testing::assert_ascii(
+            &screen,
+            "
+            #...............
+            ..###...........
+            ................
+            ................
+            ................
+            ................
+            ................
+            ................
+            ",
+        );
+    }
+
+    #[test]
+    fn test_get_region() {
+        let mut screen = OledScreen::from_device(MockDevice::new(), 16, 8).unwrap();
+        screen.paint_region(Rect::new(1, 1, 2, 2), true);
+        screen.set_pixel(15, 7, true);
+
+        assert_eq!(
+            screen.get_region(Rect::new(0, 1, 10, 2)),
+            [0b01100000, 0, 0b01100000, 0]
+        );
+        assert_eq!(screen.get_region(Rect::new(14, 7, 4, 2)), [0b01000000, 0]);
+        assert_eq!(screen.get_row(7), [0, 1]);
+        assert_eq!(screen.get_column(2), [0b01100000]);
+        assert_eq!(screen.get_column(16), [0]);
+
+        // Writing a region back puts the same pixels in place, skipping any off the screen
+        let region = Rect::new(0, 0, 10, 3);
+        let packed = screen.get_region(region);
+        screen.clear();
+        screen.set_region(region, &packed);
+        screen.set_region(Rect::new(12, 6, 8, 4), &[0xff; 4]);
+        assert_eq!(screen.get_region(region), packed);
+        assert!(screen.get_pixel(15, 7));
+    }
+
+    #[test]
+    fn test_invert_region() {
+        let mock_device = MockDevice::new();
+        let mut screen = OledScreen::from_device(mock_device, 32, 128).unwrap();
+        screen.set_pixel(1, 1, true);
+        screen.invert_region(Rect::new(0, 0, 2, 2));
+
+        assert!(screen.get_pixel(0, 0));
+        assert!(!screen.get_pixel(1, 1));
+        assert!(!screen.get_pixel(2, 2));
+    }
+
+    #[test]
+    fn test_draw_line() {
+        let mut screen = OledScreen::from_device(MockDevice::new(), 32, 128).unwrap();
+        screen.draw_line(Point::new(0, 0), Point::new(4, 2));
+
+        let lit: Vec<_> = (0..8)
+            .flat_map(|x| (0..4).map(move |y| (x, y)))
+            .filter(|&(x, y)| screen.get_pixel(x, y))
+            .collect();
+        assert_eq!(lit, vec![(0, 0), (1, 1), (2, 1), (3, 2), (4, 2)]);
+
+        let mut reversed = OledScreen::from_device(MockDevice::new(), 32, 128).unwrap();
+        reversed.draw_line(Point::new(4, 2), Point::new(0, 0));
+        assert!(reversed.get_pixel(0, 0) && reversed.get_pixel(4, 2));
+    }
+
+    #[test]
+    fn test_draw_rect() {
+        let mut screen = OledScreen::from_device(MockDevice::new(), 32, 128).unwrap();
+        screen.draw_rect(Rect::new(2, 2, 4, 3));
+
+        assert!(screen.get_pixel(2, 2));
+        assert!(screen.get_pixel(5, 4));
+        assert!(screen.get_pixel(2, 4));
+        assert!(!screen.get_pixel(3, 3));
+        assert!(!screen.get_pixel(6, 5));
+    }
+
+    #[test]
+    fn test_to_braille() {
+        let mut screen = OledScreen::from_device(MockDevice::new(), 8, 8).unwrap();
+        // Top left corner and bottom right corner
+        screen.set_pixel(0, 7, true);
+        screen.set_pixel(7, 0, true);
+
+        assert_eq!(screen.to_braille(), "⠁⠀⠀⠀\n⠀⠀⠀⢀");
+    }
+
+    #[test]
+    fn test_poll_event() {
+        let mock_device = MockDevice::new();
+        mock_device.queue_read([1, 0, 0, 0]);
+        mock_device.queue_read(KeyboardEvent::Keycode(42).to_bytes());
+        let screen = OledScreen::from_device(mock_device, 32, 128).unwrap();
+
+        assert_eq!(
+            screen.poll_event(0).unwrap(),
+            Some(KeyboardEvent::Keycode(42))
+        );
+        assert_eq!(screen.poll_event(0).unwrap(), None);
+    }
+
+    #[test]
+    fn test_autodetect() {
+        let mock_device = MockDevice::new();
+        mock_device.queue_read([HANDSHAKE_REPORT_ID, 1, 2, 0, 128, 0, 32, 0]);
+        let mut screen = OledScreen::autodetect(mock_device.clone()).unwrap();
+
+        assert_eq!((screen.width(), screen.height()), (128, 32));
         screen.fill_all();
         screen.send().unwrap();
+        // The handshake, then 512 bytes in chunks of 30
+        assert_eq!(mock_device.writes().len(), 19);
+    }
 
-        let device: &MockHidDevice = screen
-            .device
-            .as_any()
-            .downcast_ref::<MockHidDevice>()
+    #[test]
+    fn test_autodetect_rejects_unpackable_sizes() {
+        let mock_device = MockDevice::new();
+        mock_device.queue_read([HANDSHAKE_REPORT_ID, 1, 2, 0, 100, 0, 32, 0]);
+        assert!(OledScreen::autodetect(mock_device).is_err());
+
+        // 128x36 packs into column strips, but not into oled_write_raw's pages
+        let mock_device = MockDevice::new();
+        let answer = [HANDSHAKE_REPORT_ID, 1, 2, 0, 128, 0, 36, 0];
+        mock_device.queue_read(answer);
+        mock_device.queue_read(answer);
+        let mut screen = OledScreen::from_device(mock_device, 32, 128).unwrap();
+        screen.detect_size(Duration::from_millis(10)).unwrap();
+        screen = screen.with_layout(BufferLayout::OledWriteRaw);
+        assert!(screen.detect_size(Duration::from_millis(10)).is_err());
+        assert_eq!((screen.width(), screen.height()), (128, 36));
+    }
+
+    #[test]
+    fn test_skips_unsupported_features() {
+        let mock_device = MockDevice::new();
+        mock_device.queue_read([HANDSHAKE_REPORT_ID, 1, 3, 0, 32, 0, 128, 0, 0]);
+        mock_device.queue_read(KeyboardEvent::Keypress.to_bytes());
+        let screen = OledScreen::from_device(mock_device.clone(), 32, 128).unwrap();
+
+        screen.handshake(Duration::from_millis(10)).unwrap();
+        assert!(!screen.capabilities().brightness);
+        mock_device.take_writes();
+
+        screen
+            .send_control(&ControlMessage::Brightness(10))
+            .unwrap();
+        screen.send_control(&ControlMessage::Heartbeat).unwrap();
+        assert_eq!(mock_device.take_writes().len(), 1);
+        assert_eq!(screen.poll_event(0).unwrap(), None);
+    }
+
+    #[test]
+    fn test_handle_encoder_turns() {
+        let mock_device = MockDevice::new();
+        let turn = |index, clockwise| KeyboardEvent::Encoder { index, clockwise };
+        mock_device.queue_read(turn(0, true).to_bytes());
+        mock_device.queue_read(KeyboardEvent::Layer(1).to_bytes());
+        mock_device.queue_read(turn(0, true).to_bytes());
+        mock_device.queue_read(turn(1, false).to_bytes());
+        let screen = OledScreen::from_device(mock_device, 32, 128).unwrap();
+
+        let mut volume = [0; 2];
+        let others = screen
+            .handle_encoder_turns(0, |index, steps| volume[index as usize] += steps)
             .unwrap();
 
-        assert_eq!(18, device.write_log.borrow().len());
+        assert_eq!(volume, [2, -1]);
+        assert_eq!(others, [KeyboardEvent::Layer(1)]);
+        assert_eq!(screen.events(0).count(), 0);
+    }
+
+    #[test]
+    fn test_send_after_partial_frame() {
+        let mock_device = MockDevice::new();
+        let mut screen = OledScreen::from_device(mock_device.clone(), 32, 128).unwrap();
+        screen.fill_all();
+
+        mock_device.fail_writes(Failure::AfterWrites(5));
+        assert!(screen.send().is_err());
+        assert_eq!(mock_device.take_writes().len(), 5);
+
+        mock_device.fail_writes(Failure::Never);
+        screen.send().unwrap();
+        assert_eq!(mock_device.writes().len(), 18);
+    }
+
+    #[test]
+    fn test_metrics() {
+        let metrics = Metrics::new();
+        let mock_device = MockDevice::new();
+        let mut screen = OledScreen::from_device(mock_device.clone(), 32, 128)
+            .unwrap()
+            .with_metrics(&metrics);
+        screen.send().unwrap();
+        screen.send().unwrap();
+
+        mock_device.fail_writes(Failure::AfterWrites(0));
+        screen.set_pixel(0, 0, true);
+        assert!(screen.send().is_err());
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.frames, 2);
+        assert_eq!(snapshot.bytes, 18 * PAYLOAD_SIZE as u64);
+        assert_eq!(snapshot.dropped_frames, 1);
+    }
+
+    #[test]
+    fn test_quantum_painter_protocol() {
+        let mock_device = MockDevice::new();
+        let mut screen = OledScreen::from_device(mock_device.clone(), 8, 2)
+            .unwrap()
+            .with_protocol(FrameProtocol::QuantumPainter {
+                foreground: Rgb565::WHITE,
+                background: Rgb565::BLUE,
+            });
+
+        screen.set_pixel(1, 1, true);
+        screen.send().unwrap();
+        let writes = mock_device.take_writes();
+        // 16 pixels of two bytes each, in chunks of 14 pixels
+        assert_eq!(writes.len(), 2);
+        assert_eq!(writes[0][..7], [COLOR_REPORT_ID, 0, 0, 0, 0x1F, 0xFF, 0xFF]);
+
+        screen.set_pixel(7, 0, true);
+        screen.send().unwrap();
+        let writes = mock_device.take_writes();
+        assert_eq!(writes.len(), 1);
+        assert_eq!(writes[0][..7], [COLOR_REPORT_ID, 0, 1, 0, 0x1F, 0xFF, 0xFF]);
+    }
+
+    #[test]
+    fn test_report_prefix() {
+        let mock_device = MockDevice::new();
+        mock_device.queue_read([VIA_REPORT_PREFIX, 1, 0, 0]);
+        mock_device.queue_read(KeyboardEvent::Keypress.to_bytes());
+        let mut prefixed = KeyboardEvent::Layer(2).to_bytes();
+        prefixed.insert(0, VIA_REPORT_PREFIX);
+        mock_device.queue_read(prefixed);
+
+        let mut screen = OledScreen::from_device(mock_device.clone(), 32, 128)
+            .unwrap()
+            .with_report_prefix(VIA_REPORT_PREFIX);
+        screen.fill_all();
+        screen.send().unwrap();
+        screen.send_control(&ControlMessage::Power(true)).unwrap();
+
+        let writes = mock_device.writes();
+        // 512 bytes split into chunks of 29, followed by the control message
+        assert_eq!(writes.len(), 19);
+        assert!(writes.iter().all(|write| write.len() == PAYLOAD_SIZE));
+        assert_eq!(
+            writes[1][..4],
+            [VIA_REPORT_PREFIX, SCREEN_REPORT_ID, 1, 0xFF]
+        );
+        assert_eq!(writes[18][..3], [VIA_REPORT_PREFIX, CONTROL_REPORT_ID, 2]);
+
+        // Only events with the prefix are picked up
+        assert_eq!(screen.poll_event(0).unwrap(), Some(KeyboardEvent::Layer(2)));
+    }
+
+    #[test]
+    fn test_oled_write_raw_layout() {
+        for (width, height) in [(32, 128), (128, 32)] {
+            let mut screen = OledScreen::from_device(MockDevice::new(), width, height).unwrap();
+            // The top left pixel, and the second pixel down in the second page of the last column
+            screen.set_pixel(0, height - 1, true);
+            screen.set_pixel(width - 1, height - 10, true);
+
+            let mut encoded = vec![0xFF; width * height / 8];
+            BufferLayout::OledWriteRaw.encode_into(
+                &screen.framebuffer.data,
+                width,
+                height,
+                &mut encoded,
+            );
+            let mut expected = vec![0; width * height / 8];
+            expected[0] = 0b00000001;
+            expected[width + width - 1] = 0b00000010;
+            assert_eq!(encoded, expected);
+
+            let decoded = BufferLayout::OledWriteRaw.decode(&encoded, width, height);
+            assert_eq!(decoded, screen.framebuffer.data);
+        }
+    }
+
+    #[test]
+    fn test_send_with_layout() {
+        let mock_device = MockDevice::new();
+        let mut screen = OledScreen::from_device(mock_device.clone(), 32, 128)
+            .unwrap()
+            .with_layout(BufferLayout::OledWriteRaw);
+        screen.set_pixel(2, 127, true);
+        screen.send().unwrap();
+
+        assert_eq!(mock_device.writes()[0][..5], [SCREEN_REPORT_ID, 0, 0, 0, 1]);
+    }
+
+    #[test]
+    fn test_packet_filtering_by_index() {
+        let mock_device = MockDevice::new();
+        let mut screen = OledScreen::from_device(mock_device.clone(), 32, 128).unwrap();
+        screen.framebuffer.data[30..60].fill(0xFF);
+        screen.send().unwrap();
+        mock_device.take_writes();
+
+        // Move the second chunk's contents into the first, which leaves a payload that was
+        // already sent but at a different index
+        screen.framebuffer.data[..30].fill(0xFF);
+        screen.framebuffer.data[30..60].fill(0);
+        screen.framebuffer.data[100] = 1;
+        screen.send().unwrap();
+
+        let indexes: Vec<_> = mock_device.writes().iter().map(|write| write[1]).collect();
+        assert_eq!(indexes, vec![0, 1, 3]);
+    }
+
+    #[test]
+    fn test_clear_on_drop() {
+        let mock_device = MockDevice::new();
+        let mut screen = OledScreen::from_device(mock_device.clone(), 32, 128)
+            .unwrap()
+            .with_clear_on_drop(true);
+        screen.set_pixel(0, 0, true);
+        screen.send().unwrap();
+        mock_device.take_writes();
+        drop(screen);
+
+        // The chunk with the pixel in is cleared, then the display is turned off
+        let writes = mock_device.writes();
+        assert_eq!(writes.len(), 2);
+        assert_eq!(writes[0][..3], [SCREEN_REPORT_ID, 0, 0]);
+        assert_eq!(writes[1], ControlMessage::Power(false).to_bytes());
+
+        let mock_device = MockDevice::new();
+        let screen = OledScreen::from_device(mock_device.clone(), 32, 128).unwrap();
+        drop(screen);
+        assert!(mock_device.writes().is_empty());
+    }
+
+    #[test]
+    fn test_packet_filtering() {
+        let mock_device = MockDevice::new();
+        let mut screen = OledScreen::from_device(mock_device.clone(), 32, 128).unwrap();
+        screen.fill_all();
+        screen.send().unwrap();
+        screen.fill_all();
+        screen.send().unwrap();
+
+        assert_eq!(18, mock_device.writes().len());
     }
 }
@@ -0,0 +1,253 @@
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+
+use hidapi::HidError;
+
+use crate::control::{ControlMessage, HEARTBEAT_INTERVAL};
+use crate::metrics::Metrics;
+use crate::screen::{OledScreen, Snapshot};
+
+/// Sends frames to the keyboard from a background thread, so that drawing doesn't have to wait
+/// for the keyboard to keep up.
+///
+/// Frames are coalesced rather than queued: if a frame is submitted while the previous one is
+/// still being sent, it replaces any frame that is waiting. The keyboard always ends up showing
+/// the latest frame instead of drifting further and further behind. While there is nothing to
+/// send, a heartbeat is sent every `HEARTBEAT_INTERVAL` so the firmware knows the host is still
+/// running.
+///
+/// Screens can't be moved between threads, so the screen frames are sent to is opened on the
+/// background thread by the given closure. Draw on another screen (e.g. one opened on the same
+/// device for reading events) and submit snapshots of it:
+///
+/// ```no_run
+/// use std::ffi::CString;
+/// use qmk_oled_api::geometry::Point;
+/// use qmk_oled_api::screen::OledScreen;
+/// use qmk_oled_api::sender::BackgroundSender;
+///
+/// let path = CString::new("/dev/hidraw0").unwrap();
+/// let mut screen = OledScreen::from_path(&path, 32, 128).unwrap();
+/// let sender = BackgroundSender::spawn(move || OledScreen::from_path(&path, 32, 128));
+///
+/// for frame in 0..128 {
+///     screen.clear();
+///     screen.draw_line(Point::new(0, 0), Point::new(31, frame));
+///     sender.submit(screen.snapshot()).unwrap();
+/// }
+/// sender.finish().unwrap();
+/// ```
+pub struct BackgroundSender {
+    shared: Arc<Shared>,
+    worker: Option<JoinHandle<Result<(), HidError>>>,
+}
+
+#[derive(Default)]
+struct Shared {
+    state: Mutex<State>,
+    frame_ready: Condvar,
+}
+
+#[derive(Default)]
+struct State {
+    /// The latest frame which hasn't been sent yet
+    pending: Option<Snapshot>,
+    /// Set when the sender is finishing, or when the background thread has given up
+    stopped: bool,
+    dropped_frames: u64,
+    metrics: Option<Metrics>,
+}
+
+impl BackgroundSender {
+    /// Start a background thread which opens a screen with `open` and sends submitted frames to
+    /// it
+    pub fn spawn<F>(open: F) -> Self
+    where
+        F: FnOnce() -> Result<OledScreen, HidError> + Send + 'static,
+    {
+        let shared = Arc::new(Shared::default());
+        let worker_shared = Arc::clone(&shared);
+        let worker = thread::spawn(move || {
+            let result = open().and_then(|mut screen| send_frames(&worker_shared, &mut screen));
+            worker_shared.state.lock().unwrap().stopped = true;
+            result
+        });
+
+        Self {
+            shared,
+            worker: Some(worker),
+        }
+    }
+
+    /// Count frames replaced by a newer one before they could be sent as dropped in `metrics`.
+    /// Frames and bytes are counted by the screen, see `OledScreen::with_metrics`
+    pub fn with_metrics(self, metrics: &Metrics) -> Self {
+        self.shared.state.lock().unwrap().metrics = Some(metrics.clone());
+        self
+    }
+
+    /// Send a frame as soon as the background thread is free, replacing any frame still waiting
+    /// to be sent. Fails if the background thread has stopped, see `finish` for why
+    pub fn submit(&self, frame: Snapshot) -> Result<(), HidError> {
+        let mut state = self.shared.state.lock().unwrap();
+        if state.stopped {
+            return Err(HidError::HidApiError {
+                message: "The background sender has stopped, call finish to see why".into(),
+            });
+        }
+
+        if state.pending.replace(frame).is_some() {
+            state.dropped_frames += 1;
+            if let Some(metrics) = &state.metrics {
+                metrics.record_dropped_frame();
+            }
+        }
+        self.shared.frame_ready.notify_one();
+        Ok(())
+    }
+
+    /// How many frames were replaced by a newer one before they could be sent
+    pub fn dropped_frames(&self) -> u64 {
+        self.shared.state.lock().unwrap().dropped_frames
+    }
+
+    /// Send the frame still waiting (if there is one) and stop the background thread. Returns
+    /// the error which stopped it early, if there was one
+    pub fn finish(mut self) -> Result<(), HidError> {
+        self.stop().unwrap_or(Ok(()))
+    }
+
+    fn stop(&mut self) -> Option<Result<(), HidError>> {
+        self.shared.state.lock().unwrap().stopped = true;
+        self.shared.frame_ready.notify_one();
+
+        let result = self.worker.take()?.join();
+        Some(result.unwrap_or_else(|_| {
+            Err(HidError::HidApiError {
+                message: "The background sender panicked".into(),
+            })
+        }))
+    }
+}
+
+impl Drop for BackgroundSender {
+    fn drop(&mut self) {
+        let _ = self.stop();
+    }
+}
+
+fn send_frames(shared: &Shared, screen: &mut OledScreen) -> Result<(), HidError> {
+    loop {
+        let frame = {
+            let mut state = shared.state.lock().unwrap();
+            loop {
+                // Frames submitted before finishing are still sent
+                if let Some(frame) = state.pending.take() {
+                    break Some(frame);
+                }
+                if state.stopped {
+                    return Ok(());
+                }
+
+                let (next_state, wait) = shared
+                    .frame_ready
+                    .wait_timeout(state, HEARTBEAT_INTERVAL)
+                    .unwrap();
+                state = next_state;
+                if wait.timed_out() && state.pending.is_none() && !state.stopped {
+                    break None;
+                }
+            }
+        };
+
+        match frame {
+            Some(frame) => {
+                screen.restore(&frame);
+                screen.send()?;
+            }
+            None => screen.send_control(&ControlMessage::Heartbeat)?,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::HidAdapter;
+    use crate::testing::MockDevice;
+    use std::any::Any;
+    use std::time::Duration;
+
+    type Writes = Arc<Mutex<Vec<Vec<u8>>>>;
+
+    /// A device which can be shared between threads, holding up every write until it is opened
+    #[derive(Clone, Default)]
+    struct GatedDevice {
+        writes: Writes,
+        open: Arc<(Mutex<bool>, Condvar)>,
+    }
+
+    impl GatedDevice {
+        fn open(&self) {
+            *self.open.0.lock().unwrap() = true;
+            self.open.1.notify_all();
+        }
+    }
+
+    impl HidAdapter for GatedDevice {
+        fn write(&self, data: &[u8]) -> Result<usize, HidError> {
+            self.writes.lock().unwrap().push(data.to_vec());
+            let (open, opened) = &*self.open;
+            let _open = opened
+                .wait_while(open.lock().unwrap(), |open| !*open)
+                .unwrap();
+            Ok(data.len())
+        }
+
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+    }
+
+    #[test]
+    fn test_coalesces_frames() {
+        let device = GatedDevice::default();
+        let worker_device = device.clone();
+        let sender =
+            BackgroundSender::spawn(move || OledScreen::from_device(worker_device, 32, 128));
+
+        let mut screen = OledScreen::from_device(MockDevice::new(), 32, 128).unwrap();
+        screen.set_pixel(0, 0, true);
+        sender.submit(screen.snapshot()).unwrap();
+
+        // Wait for the first frame to get stuck on the device before piling up more
+        while device.writes.lock().unwrap().is_empty() {
+            thread::sleep(Duration::from_millis(1));
+        }
+        for y in 1..4 {
+            screen.set_pixel(0, y, true);
+            sender.submit(screen.snapshot()).unwrap();
+        }
+        assert_eq!(sender.dropped_frames(), 2);
+
+        device.open();
+        sender.finish().unwrap();
+
+        // The whole first frame, then only the chunk changed by the last one
+        let writes = device.writes.lock().unwrap();
+        assert_eq!(writes.len(), 19);
+        assert_eq!(writes[18][..7], [1, 0, 1, 1, 1, 1, 0]);
+    }
+
+    #[test]
+    fn test_open_failure() {
+        let sender = BackgroundSender::spawn(|| {
+            Err(HidError::HidApiError {
+                message: "no keyboard".into(),
+            })
+        });
+
+        let error = sender.finish().unwrap_err();
+        assert_eq!(error.to_string(), "hidapi error: no keyboard");
+    }
+}
@@ -0,0 +1,244 @@
+use std::any::Any;
+use std::cell::RefCell;
+use std::io::{self, Stdout, Write};
+use std::rc::Rc;
+
+use hidapi::HidError;
+
+use crate::data::{chunk_size, HidAdapter, SCREEN_REPORT_ID};
+use crate::screen::{render_braille, BufferLayout};
+
+/// A virtual device which decodes screen packets the same way the keyboard does and draws the
+/// resulting frame to a terminal in braille, redrawing it in place once per frame sent. Useful
+/// over SSH or anywhere a keyboard isn't plugged in:
+///
+/// ```no_run
+/// use qmk_oled_api::screen::{OledScreen, TextStyle};
+/// use qmk_oled_api::simulator::TerminalSimulator;
+///
+/// let mut screen = OledScreen::from_device(TerminalSimulator::new(32, 128), 32, 128).unwrap();
+/// screen.draw_text("Hello", 0, 100, &TextStyle::default()).unwrap();
+/// screen.send().unwrap();
+/// ```
+///
+/// Clones share the same frame and output.
+pub struct TerminalSimulator<W: Write = Stdout> {
+    state: Rc<RefCell<State<W>>>,
+}
+
+struct State<W> {
+    width: usize,
+    height: usize,
+    /// The frame as it was sent, in `layout`
+    frame: Vec<u8>,
+    layout: BufferLayout,
+    report_prefix: Option<u8>,
+    output: W,
+    /// How many lines the last render took up, so the cursor can be moved back over them
+    rendered_lines: usize,
+}
+
+impl TerminalSimulator {
+    /// Simulate a screen of the given size, drawing to stdout
+    pub fn new(width: usize, height: usize) -> Self {
+        Self::with_output(io::stdout(), width, height)
+    }
+}
+
+impl<W: Write> TerminalSimulator<W> {
+    /// Simulate a screen of the given size, drawing to any writer rather than stdout
+    pub fn with_output(output: W, width: usize, height: usize) -> Self {
+        Self {
+            state: Rc::new(RefCell::new(State {
+                width,
+                height,
+                frame: vec![0; (width * height) / 8],
+                layout: BufferLayout::default(),
+                report_prefix: None,
+                output,
+                rendered_lines: 0,
+            })),
+        }
+    }
+
+    /// Expect frames in the given layout, to match `OledScreen::with_layout`
+    pub fn with_layout(self, layout: BufferLayout) -> Self {
+        self.state.borrow_mut().layout = layout;
+        self
+    }
+
+    /// Expect every report to start with the given byte, to match
+    /// `OledScreen::with_report_prefix`. Reports without it are ignored, as the keyboard would
+    /// pass them on to VIA
+    pub fn with_report_prefix(self, prefix: u8) -> Self {
+        self.state.borrow_mut().report_prefix = Some(prefix);
+        self
+    }
+
+    /// The frame as it would currently appear on the keyboard, rendered as braille
+    pub fn to_braille(&self) -> String {
+        self.state.borrow().to_braille()
+    }
+
+    /// Consume the simulator, returning its output. Returns `None` if it has been cloned and the
+    /// clones are still around
+    pub fn into_output(self) -> Option<W> {
+        Rc::try_unwrap(self.state)
+            .ok()
+            .map(|state| state.into_inner().output)
+    }
+}
+
+impl<W: Write> State<W> {
+    fn to_braille(&self) -> String {
+        let frame = self.layout.decode(&self.frame, self.width, self.height);
+        render_braille(&frame, self.width, self.height)
+    }
+
+    fn render(&mut self) -> io::Result<()> {
+        let braille = self.to_braille();
+        let border = "─".repeat(self.width.div_ceil(2));
+
+        if self.rendered_lines > 0 {
+            // Move back to the start of the previous render and draw over it
+            write!(self.output, "\x1b[{}A\r", self.rendered_lines)?;
+        }
+
+        writeln!(self.output, "┌{border}┐")?;
+        for line in braille.lines() {
+            writeln!(self.output, "│{line}│")?;
+        }
+        writeln!(self.output, "└{border}┘")?;
+
+        self.rendered_lines = braille.lines().count() + 2;
+        self.output.flush()
+    }
+}
+
+impl<W: Write> Clone for TerminalSimulator<W> {
+    fn clone(&self) -> Self {
+        Self {
+            state: Rc::clone(&self.state),
+        }
+    }
+}
+
+impl<W: Write + 'static> HidAdapter for TerminalSimulator<W> {
+    fn write(&self, data: &[u8]) -> Result<usize, HidError> {
+        let mut state = self.state.borrow_mut();
+        let report = match state.report_prefix {
+            Some(prefix) => match data.split_first() {
+                Some((first, report)) if *first == prefix => report,
+                _ => return Ok(data.len()),
+            },
+            None => data,
+        };
+
+        // Control messages and anything else the screen doesn't draw are accepted and ignored
+        if let [SCREEN_REPORT_ID, index, payload @ ..] = report {
+            let start = *index as usize * chunk_size(state.report_prefix);
+            let end = (start + payload.len()).min(state.frame.len());
+            if start < end {
+                state.frame[start..end].copy_from_slice(&payload[..end - start]);
+            }
+        }
+
+        Ok(data.len())
+    }
+
+    fn end_frame(&self) -> Result<(), HidError> {
+        self.state
+            .borrow_mut()
+            .render()
+            .map_err(|error| HidError::HidApiError {
+                message: error.to_string(),
+            })
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::control::ControlMessage;
+    use crate::data::VIA_REPORT_PREFIX;
+    use crate::geometry::{Point, Rect};
+    use crate::screen::OledScreen;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_simulator_matches_screen() {
+        let simulator = TerminalSimulator::with_output(vec![], 32, 128);
+        let mut screen = OledScreen::from_device(simulator.clone(), 32, 128).unwrap();
+
+        screen.draw_line(Point::new(0, 0), Point::new(31, 127));
+        screen.paint_region(Rect::new(20, 100, 12, 28), true);
+        screen.send().unwrap();
+        assert_eq!(simulator.to_braille(), screen.to_braille());
+
+        screen.clear();
+        screen.send().unwrap();
+        assert_eq!(simulator.to_braille(), screen.to_braille());
+    }
+
+    #[test]
+    fn test_simulator_with_layout() {
+        let simulator =
+            TerminalSimulator::with_output(vec![], 32, 128).with_layout(BufferLayout::OledWriteRaw);
+        let mut screen = OledScreen::from_device(simulator.clone(), 32, 128)
+            .unwrap()
+            .with_layout(BufferLayout::OledWriteRaw);
+
+        screen.draw_line(Point::new(0, 0), Point::new(31, 127));
+        screen.send().unwrap();
+        assert_eq!(simulator.to_braille(), screen.to_braille());
+    }
+
+    #[test]
+    fn test_simulator_with_report_prefix() {
+        let simulator =
+            TerminalSimulator::with_output(vec![], 32, 128).with_report_prefix(VIA_REPORT_PREFIX);
+        let mut screen = OledScreen::from_device(simulator.clone(), 32, 128)
+            .unwrap()
+            .with_report_prefix(VIA_REPORT_PREFIX);
+
+        screen.draw_line(Point::new(0, 0), Point::new(31, 127));
+        screen.send().unwrap();
+        assert_eq!(simulator.to_braille(), screen.to_braille());
+    }
+
+    #[test]
+    fn test_simulator_renders_once_per_frame() {
+        let simulator = TerminalSimulator::with_output(vec![], 32, 128);
+        let mut screen = OledScreen::from_device(simulator.clone(), 32, 128).unwrap();
+        screen.fill_all();
+        screen.send().unwrap();
+        drop(screen);
+
+        let output = String::from_utf8(simulator.into_output().unwrap()).unwrap();
+        assert_eq!(output.matches('┌').count(), 1);
+    }
+
+    #[test]
+    fn test_simulator_redraws_in_place() {
+        let simulator = TerminalSimulator::with_output(vec![], 8, 8);
+        let mut screen = OledScreen::from_device(simulator.clone(), 8, 8).unwrap();
+
+        screen.set_pixel(0, 7, true);
+        screen.send().unwrap();
+        screen.set_pixel(7, 0, true);
+        screen.send().unwrap();
+        screen.send_control(&ControlMessage::Power(false)).unwrap();
+        drop(screen);
+
+        let output = String::from_utf8(simulator.into_output().unwrap()).unwrap();
+        assert_eq!(
+            output,
+            "┌────┐\n│⠁⠀⠀⠀│\n│⠀⠀⠀⠀│\n└────┘\n\
+             \x1b[4A\r┌────┐\n│⠁⠀⠀⠀│\n│⠀⠀⠀⢀│\n└────┘\n"
+        );
+    }
+}
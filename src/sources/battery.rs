@@ -0,0 +1,152 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use super::{DataSource, Value};
+
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(30);
+const POWER_SUPPLY_DIR: &str = "/sys/class/power_supply";
+
+/// Charge level and state of a laptop battery, read from `/sys/class/power_supply`.
+///
+/// Publishes `battery.percent`, `battery.status` (as reported by the kernel, e.g. `Charging`,
+/// `Discharging` or `Full`) and `battery.charging` (1 while charging, 0 otherwise)
+pub struct BatteryLevel {
+    interval: Duration,
+    power_supply_dir: PathBuf,
+    battery: Option<String>,
+}
+
+impl BatteryLevel {
+    /// Read the first battery found
+    pub fn new() -> Self {
+        Self {
+            interval: DEFAULT_INTERVAL,
+            power_supply_dir: PathBuf::from(POWER_SUPPLY_DIR),
+            battery: None,
+        }
+    }
+
+    /// Read the battery with the given name (e.g. `BAT1`) rather than the first one found
+    pub fn with_battery(mut self, name: &str) -> Self {
+        self.battery = Some(name.to_string());
+        self
+    }
+
+    /// Set how often the battery is read
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    fn battery_dir(&self) -> io::Result<PathBuf> {
+        if let Some(name) = &self.battery {
+            return Ok(self.power_supply_dir.join(name));
+        }
+
+        let mut supplies: Vec<PathBuf> = fs::read_dir(&self.power_supply_dir)?
+            .filter_map(|entry| Some(entry.ok()?.path()))
+            .filter(|path| read_attribute(path, "type").is_ok_and(|kind| kind == "Battery"))
+            .collect();
+        supplies.sort();
+
+        supplies
+            .into_iter()
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No battery found"))
+    }
+}
+
+impl Default for BatteryLevel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DataSource for BatteryLevel {
+    fn name(&self) -> &str {
+        "battery"
+    }
+
+    fn poll_interval(&self) -> Duration {
+        self.interval
+    }
+
+    fn poll(&mut self) -> io::Result<Vec<(String, Value)>> {
+        let battery_dir = self.battery_dir()?;
+        let percent: f64 = read_attribute(&battery_dir, "capacity")?
+            .parse()
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+        let status = read_attribute(&battery_dir, "status")?;
+        let charging = if status == "Charging" { 1.0 } else { 0.0 };
+
+        Ok(vec![
+            ("percent".into(), Value::Number(percent)),
+            ("status".into(), Value::Text(status)),
+            ("charging".into(), Value::Number(charging)),
+        ])
+    }
+}
+
+fn read_attribute(supply: &Path, attribute: &str) -> io::Result<String> {
+    Ok(fs::read_to_string(supply.join(attribute))?
+        .trim()
+        .to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn power_supply_dir(name: &str, supplies: &[(&str, &str, &str, &str)]) -> PathBuf {
+        let dir = env::temp_dir().join(format!("qmk-oled-api-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        for (supply, kind, capacity, status) in supplies {
+            let supply = dir.join(supply);
+            fs::create_dir_all(&supply).unwrap();
+            fs::write(supply.join("type"), format!("{kind}\n")).unwrap();
+            fs::write(supply.join("capacity"), format!("{capacity}\n")).unwrap();
+            fs::write(supply.join("status"), format!("{status}\n")).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn test_first_battery_is_read() {
+        let mut battery = BatteryLevel::new();
+        battery.power_supply_dir = power_supply_dir(
+            "battery",
+            &[
+                ("AC", "Mains", "0", "Unknown"),
+                ("BAT0", "Battery", "87", "Charging"),
+                ("BAT1", "Battery", "40", "Discharging"),
+            ],
+        );
+
+        assert_eq!(
+            battery.poll().unwrap(),
+            vec![
+                ("percent".into(), Value::Number(87.0)),
+                ("status".into(), Value::Text("Charging".into())),
+                ("charging".into(), Value::Number(1.0)),
+            ]
+        );
+
+        let mut battery = battery.with_battery("BAT1");
+        assert_eq!(
+            battery.poll().unwrap()[0],
+            ("percent".into(), Value::Number(40.0))
+        );
+    }
+
+    #[test]
+    fn test_no_battery() {
+        let mut battery = BatteryLevel::new();
+        battery.power_supply_dir = power_supply_dir("no-battery", &[("AC", "Mains", "0", "")]);
+
+        assert_eq!(battery.poll().unwrap_err().kind(), io::ErrorKind::NotFound);
+    }
+}
@@ -0,0 +1,217 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::utils::days_from_civil;
+
+use super::{DataSource, Value};
+
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// An event parsed from an iCalendar file
+#[derive(Debug, Clone, PartialEq)]
+struct Event {
+    title: String,
+    /// Seconds since the unix epoch
+    start: i64,
+}
+
+/// The next upcoming event from an iCalendar (.ics) file, e.g. one exported or synced from a
+/// calendar app.
+///
+/// Publishes `calendar.title` and `calendar.minutes` (whole minutes until the event starts,
+/// rounded up). When there is no upcoming event the title is empty and minutes is -1.
+///
+/// Times with a `Z` suffix are read as UTC and any other times as being at the configured offset
+/// from UTC. Recurring events only count their first occurrence
+pub struct Calendar {
+    path: PathBuf,
+    interval: Duration,
+    utc_offset: i64,
+}
+
+impl Calendar {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self {
+            path: path.into(),
+            interval: DEFAULT_INTERVAL,
+            utc_offset: 0,
+        }
+    }
+
+    /// Read times without a `Z` suffix as being at the given offset from UTC, in minutes
+    pub fn with_utc_offset(mut self, minutes: i64) -> Self {
+        self.utc_offset = minutes * 60;
+        self
+    }
+
+    /// Set how often the file is re-read
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+}
+
+impl DataSource for Calendar {
+    fn name(&self) -> &str {
+        "calendar"
+    }
+
+    fn poll_interval(&self) -> Duration {
+        self.interval
+    }
+
+    fn poll(&mut self) -> io::Result<Vec<(String, Value)>> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since_epoch| since_epoch.as_secs() as i64);
+        let events = parse_events(&fs::read_to_string(&self.path)?, self.utc_offset);
+
+        let next = events
+            .into_iter()
+            .filter(|event| event.start >= now)
+            .min_by_key(|event| event.start);
+
+        Ok(match next {
+            Some(event) => vec![
+                ("title".into(), Value::Text(event.title)),
+                (
+                    "minutes".into(),
+                    Value::Number(((event.start - now) as u64).div_ceil(60) as f64),
+                ),
+            ],
+            None => vec![
+                ("title".into(), Value::Text(String::new())),
+                ("minutes".into(), Value::Number(-1.0)),
+            ],
+        })
+    }
+}
+
+/// Parse every event with a title and start time out of an iCalendar file
+fn parse_events(ics: &str, utc_offset: i64) -> Vec<Event> {
+    // Long lines are folded onto continuation lines starting with whitespace
+    let unfolded = ics
+        .replace("\r\n", "\n")
+        .replace("\n ", "")
+        .replace("\n\t", "");
+
+    let mut events = vec![];
+    let mut title = None;
+    let mut start = None;
+
+    for line in unfolded.lines() {
+        let (name, value) = match line.split_once(':') {
+            Some(property) => property,
+            None => continue,
+        };
+        // Parameters such as TZID or VALUE=DATE follow the property name after a semicolon
+        let (name, _) = name.split_once(';').unwrap_or((name, ""));
+
+        match (name, value) {
+            ("BEGIN", "VEVENT") => {
+                title = None;
+                start = None;
+            }
+            ("SUMMARY", summary) => title = Some(unescape(summary)),
+            ("DTSTART", date_time) => start = parse_date_time(date_time, utc_offset),
+            ("END", "VEVENT") => {
+                if let (Some(title), Some(start)) = (title.take(), start.take()) {
+                    events.push(Event { title, start });
+                }
+            }
+            _ => (),
+        }
+    }
+
+    events
+}
+
+/// Parse a `YYYYMMDD` date or `YYYYMMDDTHHMMSS[Z]` date-time into seconds since the unix epoch
+fn parse_date_time(value: &str, utc_offset: i64) -> Option<i64> {
+    let (value, offset) = match value.strip_suffix('Z') {
+        Some(value) => (value, 0),
+        None => (value, utc_offset),
+    };
+    let (date, time) = value.split_once('T').unwrap_or((value, "000000"));
+    let digits = |text: &str| text.bytes().all(|byte| byte.is_ascii_digit());
+    if date.len() != 8 || time.len() != 6 || !digits(date) || !digits(time) {
+        return None;
+    }
+
+    let field = |digits: &str| digits.parse::<i64>().ok();
+    let days = days_from_civil(
+        field(&date[..4])?,
+        field(&date[4..6])? as u32,
+        field(&date[6..])? as u32,
+    );
+    let seconds = field(&time[..2])? * 3600 + field(&time[2..4])? * 60 + field(&time[4..])?;
+
+    Some(days * 86400 + seconds - offset)
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("\\n", " ")
+        .replace("\\,", ",")
+        .replace("\\;", ";")
+        .replace("\\\\", "\\")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ICS: &str = "BEGIN:VCALENDAR\r
+BEGIN:VEVENT\r
+SUMMARY:Standup\\, daily\r
+DTSTART:20261015T090000Z\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+SUMMARY:Long meeting title which has been\r
+  folded\r
+DTSTART;TZID=Europe/London:20261015T140000\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+SUMMARY:Holiday\r
+DTSTART;VALUE=DATE:20261016\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+DTSTART:20261017T090000Z\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+
+    #[test]
+    fn test_parse_events() {
+        let events = parse_events(ICS, 60 * 60);
+        let day = 20741 * 86400;
+
+        assert_eq!(
+            events,
+            vec![
+                Event {
+                    title: "Standup, daily".into(),
+                    start: day + 9 * 3600
+                },
+                Event {
+                    title: "Long meeting title which has been folded".into(),
+                    start: day + 13 * 3600
+                },
+                Event {
+                    title: "Holiday".into(),
+                    start: day + 86400 - 3600
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_date_time() {
+        assert_eq!(parse_date_time("19700101T000130Z", 3600), Some(90));
+        assert_eq!(parse_date_time("19700102", 0), Some(86400));
+        assert_eq!(parse_date_time("1970-01-01", 0), None);
+        assert_eq!(parse_date_time("1970é01", 0), None);
+        assert_eq!(parse_date_time("19700101T0é130", 0), None);
+    }
+}
@@ -0,0 +1,182 @@
+use std::ffi::CString;
+use std::io;
+use std::mem::MaybeUninit;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use super::{DataSource, Value};
+
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Used and total space on mounted filesystems.
+///
+/// For each configured mount publishes `<name>.used` and `<name>.total` in bytes, and
+/// `<name>.percent` as the percentage used. Like `df`, the percentage is of the space available
+/// to normal users, so it leaves out space reserved for root. Mounts which can't be measured
+/// (e.g. an unplugged drive) are skipped, and polling only fails if none of them can be
+pub struct DiskUsage {
+    interval: Duration,
+    mounts: Vec<(String, PathBuf)>,
+}
+
+impl DiskUsage {
+    /// Measure the root filesystem, published under the name `root`
+    pub fn new() -> Self {
+        Self {
+            interval: DEFAULT_INTERVAL,
+            mounts: vec![("root".into(), PathBuf::from("/"))],
+        }
+    }
+
+    /// Measure the given mount points instead, each published under its name (e.g.
+    /// `("home", "/home")`)
+    pub fn with_mounts<P: Into<PathBuf>>(mut self, mounts: Vec<(&str, P)>) -> Self {
+        self.mounts = mounts
+            .into_iter()
+            .map(|(name, path)| (name.to_string(), path.into()))
+            .collect();
+        self
+    }
+
+    /// Set how often usage is sampled
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+}
+
+impl Default for DiskUsage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DataSource for DiskUsage {
+    fn name(&self) -> &str {
+        "disk"
+    }
+
+    fn poll_interval(&self) -> Duration {
+        self.interval
+    }
+
+    fn poll(&mut self) -> io::Result<Vec<(String, Value)>> {
+        let mut values = vec![];
+        let mut last_error = None;
+
+        for (name, path) in &self.mounts {
+            let usage = match filesystem_usage(path) {
+                Ok(usage) => usage,
+                Err(error) => {
+                    last_error = Some(error);
+                    continue;
+                }
+            };
+
+            values.push((format!("{name}.used"), Value::Number(usage.used as f64)));
+            values.push((format!("{name}.total"), Value::Number(usage.total as f64)));
+            values.push((format!("{name}.percent"), Value::Number(usage.percent())));
+        }
+
+        match last_error {
+            Some(error) if values.is_empty() => Err(error),
+            _ => Ok(values),
+        }
+    }
+}
+
+/// Space on a filesystem, in bytes
+struct Usage {
+    used: u64,
+    total: u64,
+    available: u64,
+}
+
+impl Usage {
+    /// The percentage used the way `df` works it out, out of what is used plus what normal users
+    /// can still use
+    fn percent(&self) -> f64 {
+        let usable = self.used + self.available;
+        if usable == 0 {
+            0.0
+        } else {
+            self.used as f64 / usable as f64 * 100.0
+        }
+    }
+}
+
+/// Space on the filesystem containing the given path
+fn filesystem_usage(path: &Path) -> io::Result<Usage> {
+    let path = CString::new(path.as_os_str().as_bytes())
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error))?;
+    let mut stats = MaybeUninit::<libc::statvfs>::uninit();
+
+    // SAFETY: path is a valid NUL terminated string and stats is only read if statvfs succeeds
+    let stats = unsafe {
+        if libc::statvfs(path.as_ptr(), stats.as_mut_ptr()) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        stats.assume_init()
+    };
+
+    let block_size = stats.f_frsize as u64;
+    let total = stats.f_blocks as u64 * block_size;
+    let free = stats.f_bfree as u64 * block_size;
+    Ok(Usage {
+        used: total.saturating_sub(free),
+        total,
+        available: stats.f_bavail as u64 * block_size,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_poll_publishes_each_mount() {
+        let mut disk = DiskUsage::new().with_mounts(vec![("root", "/"), ("tmp", "/tmp")]);
+        let values = disk.poll().unwrap();
+
+        let keys: Vec<_> = values.iter().map(|(key, _)| key.as_str()).collect();
+        assert_eq!(
+            keys,
+            [
+                "root.used",
+                "root.total",
+                "root.percent",
+                "tmp.used",
+                "tmp.total",
+                "tmp.percent"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_missing_mounts_are_skipped() {
+        let mut disk =
+            DiskUsage::new().with_mounts(vec![("missing", "/does/not/exist"), ("root", "/")]);
+        let values = disk.poll().unwrap();
+
+        let keys: Vec<_> = values.iter().map(|(key, _)| key.as_str()).collect();
+        assert_eq!(keys, ["root.used", "root.total", "root.percent"]);
+    }
+
+    #[test]
+    fn test_percent_leaves_out_reserved_space() {
+        let usage = Usage {
+            used: 45,
+            total: 100,
+            available: 45,
+        };
+        assert_eq!(usage.percent(), 50.0);
+    }
+
+    #[test]
+    fn test_missing_mount_is_an_error() {
+        let mut disk = DiskUsage::new().with_mounts(vec![("missing", "/does/not/exist")]);
+
+        assert!(disk.poll().is_err());
+    }
+}
@@ -0,0 +1,215 @@
+#[cfg(target_os = "linux")]
+mod battery;
+mod calendar;
+#[cfg(unix)]
+mod disk;
+#[cfg(target_os = "linux")]
+mod network;
+#[cfg(target_os = "linux")]
+mod system_stats;
+
+#[cfg(target_os = "linux")]
+pub use battery::BatteryLevel;
+pub use calendar::Calendar;
+#[cfg(unix)]
+pub use disk::DiskUsage;
+#[cfg(target_os = "linux")]
+pub use network::{format_rate, NetworkThroughput};
+#[cfg(target_os = "linux")]
+pub use system_stats::SystemStats;
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::io;
+use std::time::{Duration, Instant};
+
+/// A single value produced by a data source, ready to be shown by a widget
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Text(String),
+    Number(f64),
+}
+
+impl Value {
+    /// The value as a number, if it is one
+    pub fn as_number(&self) -> Option<f64> {
+        match self {
+            Value::Number(number) => Some(*number),
+            Value::Text(_) => None,
+        }
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Text(text) => f.write_str(text),
+            Value::Number(number) => write!(f, "{number}"),
+        }
+    }
+}
+
+/// Something which periodically produces named values for display, e.g. system stats or the
+/// currently playing song
+pub trait DataSource {
+    /// A short name for the source. Values are published as `<name>.<key>`
+    fn name(&self) -> &str;
+
+    /// How often the source should be polled
+    fn poll_interval(&self) -> Duration;
+
+    /// Refresh the source, returning the latest value for each of its keys
+    fn poll(&mut self) -> io::Result<Vec<(String, Value)>>;
+}
+
+/// A set of data sources which are each polled on their own interval, keeping the latest values
+/// from all of them
+#[derive(Default)]
+pub struct Sources {
+    sources: Vec<ScheduledSource>,
+    values: HashMap<String, Value>,
+}
+
+struct ScheduledSource {
+    source: Box<dyn DataSource>,
+    last_polled: Option<Instant>,
+}
+
+impl Sources {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a source. It is polled on the next call to `poll_due`
+    pub fn add(&mut self, source: impl DataSource + 'static) {
+        self.sources.push(ScheduledSource {
+            source: Box::new(source),
+            last_polled: None,
+        });
+    }
+
+    /// Poll every source whose interval has elapsed. Returns whether any values were updated.
+    ///
+    /// A failing source doesn't stop the others from being polled, the first error is returned
+    /// once they have all been given a chance to run
+    pub fn poll_due(&mut self) -> io::Result<bool> {
+        self.poll_due_at(Instant::now())
+    }
+
+    /// How long until the next source is due to be polled
+    pub fn next_due(&self) -> Duration {
+        let now = Instant::now();
+
+        self.sources
+            .iter()
+            .map(|scheduled| match scheduled.last_polled {
+                Some(last_polled) => {
+                    (last_polled + scheduled.source.poll_interval()).saturating_duration_since(now)
+                }
+                None => Duration::ZERO,
+            })
+            .min()
+            .unwrap_or(Duration::MAX)
+    }
+
+    /// The latest value published under `<source name>.<key>`
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.values.get(key)
+    }
+
+    fn poll_due_at(&mut self, now: Instant) -> io::Result<bool> {
+        let mut updated = false;
+        let mut first_error = None;
+
+        for scheduled in &mut self.sources {
+            let due = scheduled.last_polled.is_none_or(|last_polled| {
+                now.duration_since(last_polled) >= scheduled.source.poll_interval()
+            });
+            if !due {
+                continue;
+            }
+            scheduled.last_polled = Some(now);
+
+            match scheduled.source.poll() {
+                Ok(values) => {
+                    let name = scheduled.source.name();
+                    for (key, value) in values {
+                        self.values.insert(format!("{name}.{key}"), value);
+                    }
+                    updated = true;
+                }
+                Err(error) => {
+                    first_error.get_or_insert(error);
+                }
+            }
+        }
+
+        match first_error {
+            Some(error) => Err(error),
+            None => Ok(updated),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Counter(f64);
+
+    impl DataSource for Counter {
+        fn name(&self) -> &str {
+            "counter"
+        }
+
+        fn poll_interval(&self) -> Duration {
+            Duration::from_secs(1)
+        }
+
+        fn poll(&mut self) -> io::Result<Vec<(String, Value)>> {
+            self.0 += 1.0;
+            Ok(vec![("count".into(), Value::Number(self.0))])
+        }
+    }
+
+    struct Broken;
+
+    impl DataSource for Broken {
+        fn name(&self) -> &str {
+            "broken"
+        }
+
+        fn poll_interval(&self) -> Duration {
+            Duration::from_secs(1)
+        }
+
+        fn poll(&mut self) -> io::Result<Vec<(String, Value)>> {
+            Err(io::Error::other("unavailable"))
+        }
+    }
+
+    #[test]
+    fn test_sources_polled_on_interval() {
+        let start = Instant::now();
+        let mut sources = Sources::new();
+        sources.add(Counter(0.0));
+
+        assert!(sources.poll_due_at(start).unwrap());
+        assert!(!sources
+            .poll_due_at(start + Duration::from_millis(500))
+            .unwrap());
+        assert!(sources.poll_due_at(start + Duration::from_secs(1)).unwrap());
+
+        assert_eq!(sources.get("counter.count"), Some(&Value::Number(2.0)));
+    }
+
+    #[test]
+    fn test_failing_source_does_not_block_others() {
+        let mut sources = Sources::new();
+        sources.add(Broken);
+        sources.add(Counter(0.0));
+
+        assert!(sources.poll_due_at(Instant::now()).is_err());
+        assert_eq!(sources.get("counter.count"), Some(&Value::Number(1.0)));
+    }
+}
@@ -0,0 +1,173 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::time::{Duration, Instant};
+
+use super::{DataSource, Value};
+
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Total received and transmitted bytes for each interface
+type Counters = HashMap<String, (u64, u64)>;
+
+/// Receive and transmit rates for network interfaces, read from `/proc/net/dev`.
+///
+/// For each interface publishes `<interface>.rx` and `<interface>.tx` in bytes per second, along
+/// with `<interface>.rx_human` and `<interface>.tx_human` formatted by `format_rate`. Rates are
+/// measured between polls, so they are only published from the second poll onwards
+pub struct NetworkThroughput {
+    interval: Duration,
+    interfaces: Option<Vec<String>>,
+    previous: Option<(Instant, Counters)>,
+}
+
+impl NetworkThroughput {
+    /// Measure every interface apart from loopback
+    pub fn new() -> Self {
+        Self {
+            interval: DEFAULT_INTERVAL,
+            interfaces: None,
+            previous: None,
+        }
+    }
+
+    /// Only measure the given interfaces
+    pub fn with_interfaces(mut self, interfaces: Vec<String>) -> Self {
+        self.interfaces = Some(interfaces);
+        self
+    }
+
+    /// Set how often rates are sampled
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    fn is_measured(&self, interface: &str) -> bool {
+        match &self.interfaces {
+            Some(interfaces) => interfaces.iter().any(|name| name == interface),
+            None => interface != "lo",
+        }
+    }
+}
+
+impl Default for NetworkThroughput {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DataSource for NetworkThroughput {
+    fn name(&self) -> &str {
+        "network"
+    }
+
+    fn poll_interval(&self) -> Duration {
+        self.interval
+    }
+
+    fn poll(&mut self) -> io::Result<Vec<(String, Value)>> {
+        let now = Instant::now();
+        let counters: Counters = parse_net_dev(&fs::read_to_string("/proc/net/dev")?)
+            .into_iter()
+            .filter(|(interface, _)| self.is_measured(interface))
+            .collect();
+
+        let mut values = vec![];
+        if let Some((then, previous)) = &self.previous {
+            let seconds = now.duration_since(*then).as_secs_f64();
+
+            for (interface, (rx, tx)) in &counters {
+                let (previous_rx, previous_tx) = match previous.get(interface) {
+                    Some(previous) => previous,
+                    None => continue,
+                };
+
+                // Counters reset when an interface goes down, which shouldn't show as a spike
+                let rates = [
+                    ("rx", rx.saturating_sub(*previous_rx) as f64 / seconds),
+                    ("tx", tx.saturating_sub(*previous_tx) as f64 / seconds),
+                ];
+                for (direction, rate) in rates {
+                    values.push((format!("{interface}.{direction}"), Value::Number(rate)));
+                    values.push((
+                        format!("{interface}.{direction}_human"),
+                        Value::Text(format_rate(rate)),
+                    ));
+                }
+            }
+        }
+
+        self.previous = Some((now, counters));
+        Ok(values)
+    }
+}
+
+/// Parse the received and transmitted byte counters for each interface in `/proc/net/dev`
+fn parse_net_dev(net_dev: &str) -> Vec<(String, (u64, u64))> {
+    net_dev
+        .lines()
+        .filter_map(|line| {
+            let (interface, counters) = line.split_once(':')?;
+            let counters: Vec<u64> = counters
+                .split_whitespace()
+                .filter_map(|counter| counter.parse().ok())
+                .collect();
+
+            // Received bytes are the first column, transmitted bytes the ninth
+            Some((
+                interface.trim().to_string(),
+                (*counters.first()?, *counters.get(8)?),
+            ))
+        })
+        .collect()
+}
+
+/// Format a rate in bytes per second using the largest unit that keeps it above 1, e.g. `1.5MB/s`
+pub fn format_rate(bytes_per_second: f64) -> String {
+    const UNITS: [&str; 4] = ["B/s", "KB/s", "MB/s", "GB/s"];
+
+    let mut rate = bytes_per_second;
+    let mut unit = 0;
+    while rate >= 1000.0 && unit < UNITS.len() - 1 {
+        rate /= 1000.0;
+        unit += 1;
+    }
+
+    if unit == 0 || rate >= 100.0 {
+        format!("{rate:.0}{}", UNITS[unit])
+    } else {
+        format!("{rate:.1}{}", UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_net_dev() {
+        let net_dev = "Inter-|   Receive                                                |  Transmit
+ face |bytes    packets errs drop fifo frame compressed multicast|bytes    packets errs drop fifo colls carrier compressed
+    lo:    1000      10    0    0    0     0          0         0     1000      10    0    0    0     0       0          0
+  eth0: 5000000    4000    0    0    0     0          0         0   250000    2000    0    0    0     0       0          0
+";
+
+        assert_eq!(
+            parse_net_dev(net_dev),
+            vec![
+                ("lo".to_string(), (1000, 1000)),
+                ("eth0".to_string(), (5000000, 250000))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_format_rate() {
+        assert_eq!(format_rate(512.0), "512B/s");
+        assert_eq!(format_rate(1536.0), "1.5KB/s");
+        assert_eq!(format_rate(250_000.0), "250KB/s");
+        assert_eq!(format_rate(12_300_000.0), "12.3MB/s");
+        assert_eq!(format_rate(5e12), "5000GB/s");
+    }
+}
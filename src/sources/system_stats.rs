@@ -0,0 +1,194 @@
+use std::fs;
+use std::io;
+use std::time::Duration;
+
+use super::{DataSource, Value};
+
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Busy and total time spent by a CPU, in jiffies
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct CpuTimes {
+    busy: u64,
+    total: u64,
+}
+
+/// CPU, memory, load and temperature statistics read from `/proc` and `/sys`.
+///
+/// Publishes the following keys under `system`:
+/// - `cpu` and `cpu<N>`: aggregate and per-core usage as a percentage
+/// - `memory`, `memory_used` and `memory_total`: usage as a percentage, and in bytes
+/// - `load1`, `load5` and `load15`: load averages
+/// - `temperature`: the hottest thermal zone in degrees Celsius, if any are available
+///
+/// CPU usage is measured between polls, so it is only published from the second poll onwards
+pub struct SystemStats {
+    interval: Duration,
+    previous_cpu: Vec<CpuTimes>,
+}
+
+impl SystemStats {
+    pub fn new() -> Self {
+        Self {
+            interval: DEFAULT_INTERVAL,
+            previous_cpu: vec![],
+        }
+    }
+
+    /// Set how often the stats are sampled. CPU usage is averaged over this interval
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+}
+
+impl Default for SystemStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DataSource for SystemStats {
+    fn name(&self) -> &str {
+        "system"
+    }
+
+    fn poll_interval(&self) -> Duration {
+        self.interval
+    }
+
+    fn poll(&mut self) -> io::Result<Vec<(String, Value)>> {
+        let mut values = vec![];
+
+        let cpu = parse_cpu_times(&fs::read_to_string("/proc/stat")?);
+        if cpu.len() == self.previous_cpu.len() {
+            for (index, (now, before)) in cpu.iter().zip(&self.previous_cpu).enumerate() {
+                // The first line of /proc/stat is the aggregate of all cores
+                let key = match index {
+                    0 => "cpu".to_string(),
+                    core => format!("cpu{}", core - 1),
+                };
+                values.push((key, Value::Number(cpu_usage(before, now))));
+            }
+        }
+        self.previous_cpu = cpu;
+
+        if let Some((used, total)) = parse_meminfo(&fs::read_to_string("/proc/meminfo")?) {
+            values.push(("memory_used".into(), Value::Number(used as f64)));
+            values.push(("memory_total".into(), Value::Number(total as f64)));
+            values.push((
+                "memory".into(),
+                Value::Number(used as f64 / total as f64 * 100.0),
+            ));
+        }
+
+        let loadavg = fs::read_to_string("/proc/loadavg")?;
+        for (key, load) in ["load1", "load5", "load15"]
+            .iter()
+            .zip(parse_loadavg(&loadavg))
+        {
+            values.push((key.to_string(), Value::Number(load)));
+        }
+
+        if let Some(temperature) = read_temperature() {
+            values.push(("temperature".into(), Value::Number(temperature)));
+        }
+
+        Ok(values)
+    }
+}
+
+/// Parse the `cpu` lines of `/proc/stat`, the aggregate line first followed by each core
+fn parse_cpu_times(stat: &str) -> Vec<CpuTimes> {
+    stat.lines()
+        .filter(|line| line.starts_with("cpu"))
+        .map(|line| {
+            let fields: Vec<u64> = line
+                .split_whitespace()
+                .skip(1)
+                .filter_map(|field| field.parse().ok())
+                .collect();
+
+            // Idle and iowait are the 4th and 5th fields
+            let total = fields.iter().sum();
+            let idle = fields.iter().skip(3).take(2).sum::<u64>();
+            CpuTimes {
+                busy: total - idle,
+                total,
+            }
+        })
+        .collect()
+}
+
+fn cpu_usage(before: &CpuTimes, now: &CpuTimes) -> f64 {
+    let total = now.total.saturating_sub(before.total);
+    if total == 0 {
+        return 0.0;
+    }
+    now.busy.saturating_sub(before.busy) as f64 / total as f64 * 100.0
+}
+
+/// Parse used and total memory in bytes from `/proc/meminfo`
+fn parse_meminfo(meminfo: &str) -> Option<(u64, u64)> {
+    let field = |name: &str| {
+        meminfo
+            .lines()
+            .find_map(|line| line.strip_prefix(name)?.strip_prefix(':'))
+            .and_then(|value| value.split_whitespace().next()?.parse::<u64>().ok())
+            .map(|kilobytes| kilobytes * 1024)
+    };
+
+    let total = field("MemTotal")?;
+    let available = field("MemAvailable")?;
+    Some((total.saturating_sub(available), total))
+}
+
+fn parse_loadavg(loadavg: &str) -> Vec<f64> {
+    loadavg
+        .split_whitespace()
+        .take(3)
+        .filter_map(|load| load.parse().ok())
+        .collect()
+}
+
+/// The highest temperature reported by any thermal zone, in degrees Celsius
+fn read_temperature() -> Option<f64> {
+    fs::read_dir("/sys/class/thermal")
+        .ok()?
+        .filter_map(|entry| fs::read_to_string(entry.ok()?.path().join("temp")).ok())
+        .filter_map(|millidegrees| millidegrees.trim().parse::<f64>().ok())
+        .map(|millidegrees| millidegrees / 1000.0)
+        .reduce(f64::max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cpu_usage() {
+        let before = parse_cpu_times(
+            "cpu  100 0 100 700 100 0 0 0 0 0\ncpu0 50 0 50 350 50 0 0 0 0 0\nintr 1 2 3\n",
+        );
+        let now = parse_cpu_times(
+            "cpu  200 0 200 1200 200 0 0 0 0 0\ncpu0 150 0 50 400 50 0 0 0 0 0\nintr 1 2 3\n",
+        );
+
+        assert_eq!(before.len(), 2);
+        assert_eq!(cpu_usage(&before[0], &now[0]), 25.0);
+        assert_eq!(cpu_usage(&before[1], &now[1]).round(), 67.0);
+    }
+
+    #[test]
+    fn test_parse_meminfo_and_loadavg() {
+        let meminfo =
+            "MemTotal:       16000 kB\nMemFree:         2000 kB\nMemAvailable:    4000 kB\n";
+
+        assert_eq!(parse_meminfo(meminfo), Some((12000 * 1024, 16000 * 1024)));
+        assert_eq!(parse_meminfo("MemTotal: 10 kB\n"), None);
+        assert_eq!(
+            parse_loadavg("0.52 0.58 0.59 1/467 12345\n"),
+            vec![0.52, 0.58, 0.59]
+        );
+    }
+}
@@ -0,0 +1,526 @@
+//! Helpers for testing code which draws to a screen. `MockDevice` stands in for a keyboard, and
+//! frames can be compared against ASCII art (`#` for a lit pixel, `.` for an unlit one) or an
+//! image file, with a mismatch panicking with both frames side by side.
+//!
+//! Enabled with the `testing` feature.
+
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use hidapi::HidError;
+use image::{GrayImage, ImageResult, Luma};
+
+use crate::data::HidAdapter;
+use crate::screen::OledScreen;
+
+/// A device which keeps everything written to it instead of sending it to a keyboard. Clones
+/// share the same state, so keep one around to inspect what a screen sent:
+///
+/// ```
+/// # use qmk_oled_api::screen::OledScreen;
+/// # use qmk_oled_api::testing::MockDevice;
+/// let device = MockDevice::new();
+/// let mut screen = OledScreen::from_device(device.clone(), 32, 128).unwrap();
+/// screen.send().unwrap();
+///
+/// assert_eq!(device.writes().len(), 18);
+/// ```
+#[derive(Clone, Default)]
+pub struct MockDevice {
+    state: Rc<RefCell<MockState>>,
+}
+
+#[derive(Default)]
+struct MockState {
+    writes: Vec<Vec<u8>>,
+    reads: VecDeque<Vec<u8>>,
+    failure: Failure,
+}
+
+/// Which writes to a `MockDevice` should fail, see `MockDevice::fail_writes`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Failure {
+    /// Every write succeeds
+    #[default]
+    Never,
+    /// The next write fails, after which writes succeed again
+    Once,
+    /// Every write fails, as if the keyboard had been unplugged
+    Always,
+    /// The given number of writes succeed, after which every write fails. Useful for cutting a
+    /// frame off partway through
+    AfterWrites(usize),
+}
+
+impl MockDevice {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every report successfully written so far, oldest first
+    pub fn writes(&self) -> Vec<Vec<u8>> {
+        self.state.borrow().writes.clone()
+    }
+
+    /// Remove and return every report successfully written so far, oldest first
+    pub fn take_writes(&self) -> Vec<Vec<u8>> {
+        std::mem::take(&mut self.state.borrow_mut().writes)
+    }
+
+    /// Queue up a report to be read from the device, such as a keyboard event
+    pub fn queue_read(&self, report: impl Into<Vec<u8>>) {
+        self.state.borrow_mut().reads.push_back(report.into());
+    }
+
+    /// Choose which of the following writes fail, replacing any previous choice
+    pub fn fail_writes(&self, failure: Failure) {
+        self.state.borrow_mut().failure = failure;
+    }
+
+    /// Make the next write fail, the same as `fail_writes(Failure::Once)`
+    pub fn fail_next_write(&self) {
+        self.fail_writes(Failure::Once);
+    }
+}
+
+impl HidAdapter for MockDevice {
+    fn write(&self, data: &[u8]) -> Result<usize, HidError> {
+        let mut state = self.state.borrow_mut();
+        let fails = match state.failure {
+            Failure::Never => false,
+            Failure::Once => {
+                state.failure = Failure::Never;
+                true
+            }
+            Failure::Always | Failure::AfterWrites(0) => true,
+            Failure::AfterWrites(remaining) => {
+                state.failure = Failure::AfterWrites(remaining - 1);
+                false
+            }
+        };
+        if fails {
+            return Err(HidError::HidApiError {
+                message: "Injected write failure".into(),
+            });
+        }
+
+        state.writes.push(data.into());
+        Ok(data.len())
+    }
+
+    fn read_timeout(&self, buf: &mut [u8], _timeout: i32) -> Result<usize, HidError> {
+        match self.state.borrow_mut().reads.pop_front() {
+            Some(report) => {
+                let length = report.len().min(buf.len());
+                buf[..length].copy_from_slice(&report[..length]);
+                Ok(length)
+            }
+            None => Ok(0),
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Rows of pixels, from the top of the screen (the highest y coordinate) down
+type Rows = Vec<Vec<bool>>;
+
+/// Render the whole screen as ASCII art, with the top of the screen on the first line
+pub fn to_ascii(screen: &OledScreen) -> String {
+    rows_to_ascii(&read_region(screen, 0, 0, screen.width(), screen.height()))
+}
+
+/// Assert that the whole screen matches some ASCII art. Indentation and blank lines around the
+/// art are ignored, so it can be written inline:
+///
+/// ```
+/// # use qmk_oled_api::geometry::Rect;
+/// # use qmk_oled_api::screen::OledScreen;
+/// # use qmk_oled_api::testing::{assert_ascii, MockDevice};
+/// let mut screen = OledScreen::from_device(MockDevice::new(), 8, 2).unwrap();
+/// screen.paint_region(Rect::new(4, 1, 4, 1), true);
+///
+/// assert_ascii(
+///     &screen,
+///     "
+///     ....####
+///     ........
+///     ",
+/// );
+/// ```
+#[track_caller]
+pub fn assert_ascii(screen: &OledScreen, expected: &str) {
+    let expected = parse_ascii(expected);
+    let width = expected.first().map_or(0, Vec::len);
+    if (width, expected.len()) != (screen.width(), screen.height()) {
+        panic!(
+            "expected ASCII art of {width}x{}, but the screen is {}x{}",
+            expected.len(),
+            screen.width(),
+            screen.height()
+        );
+    }
+
+    assert_ascii_at(screen, 0, 0, &rows_to_ascii(&expected));
+}
+
+/// Assert that part of the screen matches some ASCII art, with the bottom left character of the
+/// art at (x, y) measured from the bottom left of the screen, whatever its origin is
+#[track_caller]
+pub fn assert_ascii_at(screen: &OledScreen, x: usize, y: usize, expected: &str) {
+    let expected = parse_ascii(expected);
+    let width = expected.first().map_or(0, Vec::len);
+    let actual = read_region(screen, x, y, width, expected.len());
+
+    if let Some(diff) = diff(&expected, &actual) {
+        panic!("screen doesn't match the expected ASCII art\n{diff}");
+    }
+}
+
+/// Assert that the screen matches an image file such as a PNG or a PBM, where white pixels are
+/// lit. The image must be the same size as the screen
+#[track_caller]
+pub fn assert_image<P: AsRef<Path>>(screen: &OledScreen, path: P) {
+    let path = path.as_ref();
+    let expected = match load_image(path) {
+        Ok(expected) => expected,
+        Err(error) => panic!("couldn't open {}: {error}", path.display()),
+    };
+
+    if let Some(message) = compare_image(screen, path, &expected) {
+        panic!("{message}");
+    }
+}
+
+/// Assert that the screen matches a golden frame stored as an image, see `assert_screen_eq!`
+#[track_caller]
+pub fn assert_golden<P: AsRef<Path>>(screen: &OledScreen, path: P) {
+    let path = path.as_ref();
+    let actual_path = actual_frame_path(path);
+
+    let message = match load_image(path) {
+        Ok(expected) => match compare_image(screen, path, &expected) {
+            Some(message) => message,
+            None => {
+                // Clean up after a previous failure, now that the golden frame has been updated
+                let _ = fs::remove_file(&actual_path);
+                return;
+            }
+        },
+        Err(error) => format!("couldn't open golden frame {}: {error}", path.display()),
+    };
+
+    let actual = read_region(screen, 0, 0, screen.width(), screen.height());
+    if let Err(error) = save_image(&actual, &actual_path) {
+        panic!(
+            "{message}\ncouldn't write the actual frame to {}: {error}",
+            actual_path.display()
+        );
+    }
+    panic!(
+        "{message}\nthe actual frame has been written to {}, replace the golden frame with it if \
+         the change is expected",
+        actual_path.display()
+    );
+}
+
+/// Assert that a screen matches a golden frame stored as an image (e.g. a PBM or PNG), where
+/// white pixels are lit. If it doesn't, or the golden frame doesn't exist yet, the actual frame is
+/// written next to it with `.actual` added to the name (e.g. `clock.actual.pbm`) for inspection.
+///
+/// ```no_run
+/// # use qmk_oled_api::assert_screen_eq;
+/// # use qmk_oled_api::screen::{OledScreen, TextStyle};
+/// # use qmk_oled_api::testing::MockDevice;
+/// let mut screen = OledScreen::from_device(MockDevice::new(), 32, 128).unwrap();
+/// screen.draw_text("12:34", 0, 100, &TextStyle::default()).unwrap();
+///
+/// assert_screen_eq!(screen, "tests/golden/clock.pbm");
+/// ```
+#[macro_export]
+macro_rules! assert_screen_eq {
+    ($screen:expr, $path:expr $(,)?) => {
+        $crate::testing::assert_golden(&$screen, $path)
+    };
+}
+
+fn load_image(path: &Path) -> ImageResult<Rows> {
+    Ok(image::open(path)?
+        .into_luma8()
+        .rows()
+        .map(|row| row.map(|pixel| pixel.0[0] >= 128).collect())
+        .collect())
+}
+
+fn save_image(rows: &Rows, path: &Path) -> ImageResult<()> {
+    let width = rows.first().map_or(0, Vec::len);
+    let image = GrayImage::from_fn(width as u32, rows.len() as u32, |x, y| {
+        Luma([if rows[y as usize][x as usize] { 255 } else { 0 }])
+    });
+    image.save(path)
+}
+
+/// Compare the whole screen against an image, returning a message describing any differences
+fn compare_image(screen: &OledScreen, path: &Path, expected: &Rows) -> Option<String> {
+    let width = expected.first().map_or(0, Vec::len);
+    if (width, expected.len()) != (screen.width(), screen.height()) {
+        return Some(format!(
+            "{} is {width}x{}, but the screen is {}x{}",
+            path.display(),
+            expected.len(),
+            screen.width(),
+            screen.height()
+        ));
+    }
+
+    let actual = read_region(screen, 0, 0, screen.width(), screen.height());
+    diff(expected, &actual).map(|diff| format!("screen doesn't match {}\n{diff}", path.display()))
+}
+
+/// Where the actual frame is written when it doesn't match a golden frame, e.g.
+/// `golden/clock.actual.pbm` for `golden/clock.pbm`
+fn actual_frame_path(path: &Path) -> PathBuf {
+    let mut name = path.file_stem().unwrap_or_default().to_os_string();
+    name.push(".actual");
+    if let Some(extension) = path.extension() {
+        name.push(".");
+        name.push(extension);
+    }
+    path.with_file_name(name)
+}
+
+/// Read a region of the screen with its bottom left corner at (x, y), which is always measured
+/// from the bottom left whatever the screen's origin is. Pixels outside the screen are unlit
+fn read_region(screen: &OledScreen, x: usize, y: usize, width: usize, height: usize) -> Rows {
+    (0..height)
+        .rev()
+        .map(|row| {
+            (0..width)
+                .map(|col| {
+                    let (x, y) = (x + col, y + row);
+                    x < screen.width()
+                        && y < screen.height()
+                        && screen.framebuffer().get_pixel(x, y)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[track_caller]
+fn parse_ascii(art: &str) -> Rows {
+    let rows: Rows = art
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            line.chars()
+                .map(|pixel| match pixel {
+                    '#' => true,
+                    '.' => false,
+                    other => panic!("unexpected {other:?} in ASCII art, use '#' and '.'"),
+                })
+                .collect()
+        })
+        .collect();
+
+    if rows.iter().any(|row| row.len() != rows[0].len()) {
+        panic!("every line of ASCII art must be the same length");
+    }
+    rows
+}
+
+fn rows_to_ascii(rows: &Rows) -> String {
+    rows.iter()
+        .map(|row| {
+            row.iter()
+                .map(|&lit| if lit { '#' } else { '.' })
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Lay out two frames side by side, marking the rows which differ. Returns `None` if the frames
+/// match
+fn diff(expected: &Rows, actual: &Rows) -> Option<String> {
+    let differing_pixels = expected
+        .iter()
+        .flatten()
+        .zip(actual.iter().flatten())
+        .filter(|(expected, actual)| expected != actual)
+        .count();
+    if differing_pixels == 0 {
+        return None;
+    }
+
+    let expected_ascii = rows_to_ascii(expected);
+    let actual_ascii = rows_to_ascii(actual);
+    let width = expected.first().map_or(0, Vec::len).max("expected".len());
+
+    let mut output = format!(
+        "{differing_pixels} pixel(s) differ\n  {:width$}  actual\n",
+        "expected"
+    );
+    for (expected, actual) in expected_ascii.lines().zip(actual_ascii.lines()) {
+        let marker = if expected == actual { ' ' } else { '>' };
+        let _ = writeln!(output, "{marker} {expected:width$}  {actual}");
+    }
+    Some(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::control::ControlMessage;
+    use crate::geometry::{Point, Rect};
+    use pretty_assertions::assert_eq;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    #[test]
+    fn test_mock_device_fail_next_write() {
+        let device = MockDevice::new();
+        let mut screen = OledScreen::from_device(device.clone(), 32, 128).unwrap();
+
+        device.fail_next_write();
+        assert!(screen.send().is_err());
+        assert!(device.writes().is_empty());
+
+        screen.send_control(&ControlMessage::Power(true)).unwrap();
+        assert_eq!(device.writes().len(), 1);
+    }
+
+    #[test]
+    fn test_mock_device_failures() {
+        let device = MockDevice::new();
+
+        device.fail_writes(Failure::Always);
+        assert!(device.write(&[1]).is_err());
+        assert!(device.write(&[1]).is_err());
+
+        device.fail_writes(Failure::AfterWrites(2));
+        assert!(device.write(&[1]).is_ok());
+        assert!(device.write(&[2]).is_ok());
+        assert!(device.write(&[3]).is_err());
+        assert!(device.write(&[3]).is_err());
+
+        device.fail_writes(Failure::Never);
+        assert!(device.write(&[4]).is_ok());
+        assert_eq!(device.writes(), vec![vec![1], vec![2], vec![4]]);
+    }
+
+    fn screen() -> OledScreen {
+        OledScreen::from_device(MockDevice::new(), 8, 4).unwrap()
+    }
+
+    #[test]
+    fn test_to_ascii() {
+        let mut screen = screen();
+        screen.set_pixel(0, 3, true);
+        screen.set_pixel(7, 0, true);
+
+        assert_eq!(to_ascii(&screen), "#.......\n........\n........\n.......#");
+    }
+
+    #[test]
+    fn test_assert_ascii() {
+        let mut screen = screen();
+        screen.draw_rect(Rect::new(0, 0, 4, 3));
+
+        assert_ascii(
+            &screen,
+            "
+            ........
+            ####....
+            #..#....
+            ####....
+            ",
+        );
+        assert_ascii_at(&screen, 2, 1, "##\n.#");
+    }
+
+    #[test]
+    #[should_panic(expected = "1 pixel(s) differ")]
+    fn test_assert_ascii_mismatch() {
+        let mut screen = screen();
+        screen.set_pixel(1, 0, true);
+
+        assert_ascii_at(&screen, 0, 0, "..");
+    }
+
+    #[test]
+    #[should_panic(expected = "but the screen is 8x4")]
+    fn test_assert_ascii_wrong_size() {
+        assert_ascii(&screen(), "#.");
+    }
+
+    #[test]
+    fn test_diff() {
+        let expected = parse_ascii("##\n..");
+        let actual = parse_ascii("##\n.#");
+
+        assert_eq!(diff(&expected, &expected), None);
+        assert_eq!(
+            diff(&expected, &actual).unwrap(),
+            "1 pixel(s) differ\n  expected  actual\n  ##        ##\n> ..        .#\n"
+        );
+    }
+
+    #[test]
+    fn test_assert_image() {
+        let path = std::env::temp_dir().join("qmk-oled-api-test-assert-image.png");
+        // The top left pixel is white, so lit
+        let mut image = GrayImage::new(8, 4);
+        image.put_pixel(0, 0, Luma([255]));
+        image.save(&path).unwrap();
+
+        let mut screen = screen();
+        screen.set_pixel(0, 3, true);
+        assert_image(&screen, &path);
+    }
+
+    #[test]
+    fn test_actual_frame_path() {
+        assert_eq!(
+            actual_frame_path(Path::new("tests/golden/clock.pbm")),
+            Path::new("tests/golden/clock.actual.pbm")
+        );
+        assert_eq!(
+            actual_frame_path(Path::new("clock")),
+            Path::new("clock.actual")
+        );
+    }
+
+    #[test]
+    fn test_assert_screen_eq() {
+        let directory = std::env::temp_dir().join("qmk-oled-api-test-assert-screen-eq");
+        fs::create_dir_all(&directory).unwrap();
+        let golden = directory.join("frame.pbm");
+        let actual = directory.join("frame.actual.pbm");
+        let _ = fs::remove_file(&golden);
+
+        let mut screen = screen();
+        screen.draw_line(Point::new(0, 0), Point::new(7, 3));
+
+        // Without a golden frame the assertion fails, writing out the actual frame
+        let result = catch_unwind(AssertUnwindSafe(|| assert_screen_eq!(screen, &golden)));
+        assert!(result.is_err());
+
+        // Which then passes once accepted, cleaning up after itself
+        fs::rename(&actual, &golden).unwrap();
+        assert_screen_eq!(screen, &golden);
+        assert!(!actual.exists());
+
+        screen.set_pixel(7, 0, true);
+        let result = catch_unwind(AssertUnwindSafe(|| assert_screen_eq!(screen, &golden)));
+        assert!(result.is_err());
+        assert!(actual.exists());
+    }
+}
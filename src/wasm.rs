@@ -0,0 +1,153 @@
+//! Bindings for drawing on a `Framebuffer` from JavaScript, used by the previewer in `web/`. They
+//! only use numbers and pointers so that no binding generator is needed to call them. Build them
+//! with:
+//!
+//! ```sh
+//! cargo rustc --lib --release --no-default-features --target wasm32-unknown-unknown --crate-type cdylib
+//! ```
+
+// Links the panic handler and allocator, which the browser provides everything needed for
+extern crate std;
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+use crate::framebuffer::Framebuffer;
+use crate::geometry::{Point, Rect};
+
+/// Create a framebuffer, which must be freed with `framebuffer_free`
+#[no_mangle]
+pub extern "C" fn framebuffer_new(width: usize, height: usize) -> *mut Framebuffer {
+    Box::into_raw(Box::new(Framebuffer::new(width, height)))
+}
+
+/// # Safety
+/// `framebuffer` must have come from `framebuffer_new` and not have been freed already
+#[no_mangle]
+pub unsafe extern "C" fn framebuffer_free(framebuffer: *mut Framebuffer) {
+    drop(Box::from_raw(framebuffer));
+}
+
+/// # Safety
+/// `framebuffer` must have come from `framebuffer_new` and not have been freed
+#[no_mangle]
+pub unsafe extern "C" fn framebuffer_get_pixel(
+    framebuffer: *const Framebuffer,
+    x: usize,
+    y: usize,
+) -> bool {
+    (*framebuffer).get_pixel(x, y)
+}
+
+/// # Safety
+/// `framebuffer` must have come from `framebuffer_new` and not have been freed
+#[no_mangle]
+pub unsafe extern "C" fn framebuffer_set_pixel(
+    framebuffer: *mut Framebuffer,
+    x: usize,
+    y: usize,
+    enabled: bool,
+) {
+    (*framebuffer).set_pixel(x, y, enabled);
+}
+
+/// # Safety
+/// `framebuffer` must have come from `framebuffer_new` and not have been freed
+#[no_mangle]
+pub unsafe extern "C" fn framebuffer_fill(framebuffer: *mut Framebuffer, byte: u8) {
+    (*framebuffer).fill(byte);
+}
+
+/// # Safety
+/// `framebuffer` must have come from `framebuffer_new` and not have been freed
+#[no_mangle]
+pub unsafe extern "C" fn framebuffer_draw_line(
+    framebuffer: *mut Framebuffer,
+    x1: usize,
+    y1: usize,
+    x2: usize,
+    y2: usize,
+) {
+    (*framebuffer).draw_line(Point::new(x1, y1), Point::new(x2, y2));
+}
+
+/// # Safety
+/// `framebuffer` must have come from `framebuffer_new` and not have been freed
+#[no_mangle]
+pub unsafe extern "C" fn framebuffer_draw_rect(
+    framebuffer: *mut Framebuffer,
+    min_x: usize,
+    min_y: usize,
+    max_x: usize,
+    max_y: usize,
+) {
+    (*framebuffer).draw_rect(Rect::from_corners(
+        Point::new(min_x, min_y),
+        Point::new(max_x, max_y),
+    ));
+}
+
+/// # Safety
+/// `framebuffer` must have come from `framebuffer_new` and not have been freed
+#[no_mangle]
+pub unsafe extern "C" fn framebuffer_paint_region(
+    framebuffer: *mut Framebuffer,
+    min_x: usize,
+    min_y: usize,
+    max_x: usize,
+    max_y: usize,
+    enabled: bool,
+) {
+    (*framebuffer).paint_region(
+        Rect::from_corners(Point::new(min_x, min_y), Point::new(max_x, max_y)),
+        enabled,
+    );
+}
+
+/// # Safety
+/// `framebuffer` must have come from `framebuffer_new` and not have been freed
+#[no_mangle]
+pub unsafe extern "C" fn framebuffer_invert_region(
+    framebuffer: *mut Framebuffer,
+    min_x: usize,
+    min_y: usize,
+    max_x: usize,
+    max_y: usize,
+) {
+    (*framebuffer).invert_region(Rect::from_corners(
+        Point::new(min_x, min_y),
+        Point::new(max_x, max_y),
+    ));
+}
+
+/// The frame as a C array (see `Framebuffer::to_qmk_array`), in a buffer which must be read with
+/// `bytes_ptr` and `bytes_len` then freed with `bytes_free`
+///
+/// # Safety
+/// `framebuffer` must have come from `framebuffer_new` and not have been freed
+#[no_mangle]
+pub unsafe extern "C" fn framebuffer_qmk_array(framebuffer: *const Framebuffer) -> *mut Vec<u8> {
+    let array = (*framebuffer).to_qmk_array("frame");
+    Box::into_raw(Box::new(array.into_bytes()))
+}
+
+/// # Safety
+/// `bytes` must have come from this module and not have been freed
+#[no_mangle]
+pub unsafe extern "C" fn bytes_ptr(bytes: *const Vec<u8>) -> *const u8 {
+    (*bytes).as_ptr()
+}
+
+/// # Safety
+/// `bytes` must have come from this module and not have been freed
+#[no_mangle]
+pub unsafe extern "C" fn bytes_len(bytes: *const Vec<u8>) -> usize {
+    (*bytes).len()
+}
+
+/// # Safety
+/// `bytes` must have come from this module and not have been freed already
+#[no_mangle]
+pub unsafe extern "C" fn bytes_free(bytes: *mut Vec<u8>) {
+    drop(Box::from_raw(bytes));
+}
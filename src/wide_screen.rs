@@ -0,0 +1,189 @@
+use std::ffi::CStr;
+
+use hidapi::{HidApi, HidError};
+use image::DynamicImage;
+
+use crate::control::ControlMessage;
+use crate::data::{send_wide_frame, write_report, HidAdapter};
+use crate::geometry::{Point, Rect};
+
+/// A framebuffer which is sent a chunk at a time in wide reports (see `send_wide_frame`), so that
+/// a `WideScreen` can draw on it and send it. Implemented for `ColorFramebuffer` in
+/// `color_screen` and `GrayFramebuffer` in `gray_screen`
+pub trait WideFramebuffer {
+    /// What a single pixel is set to, e.g. a colour or a gray level. The default is unlit
+    type Pixel: Copy + Default;
+
+    /// The report ID frames are sent with
+    const REPORT_ID: u8;
+
+    fn new(width: usize, height: usize) -> Self;
+
+    fn width(&self) -> usize;
+
+    fn height(&self) -> usize;
+
+    /// The pixels in the order they are sent. Framebuffers which aren't stored that way encode
+    /// into `buffer`, which is kept between sends
+    fn encode<'a>(&'a self, buffer: &'a mut Vec<u8>) -> &'a [u8];
+
+    /// The nearest pixel to a colour from an image
+    fn pixel_from_rgb(red: u8, green: u8, blue: u8) -> Self::Pixel;
+
+    fn fill(&mut self, pixel: Self::Pixel);
+
+    fn paint_region(&mut self, region: Rect, pixel: Self::Pixel);
+
+    fn draw_line(&mut self, from: Point, to: Point, pixel: Self::Pixel);
+
+    fn draw_rect(&mut self, region: Rect, pixel: Self::Pixel);
+
+    fn get_pixel(&self, x: usize, y: usize) -> Self::Pixel;
+
+    fn set_pixel(&mut self, x: usize, y: usize, pixel: Self::Pixel);
+}
+
+/// A screen with more than one bit to a pixel, drawn on through a `WideFramebuffer` and sent
+/// to the device in its wide reports. Like `OledScreen`, only the parts which changed since the
+/// last frame are sent. Used through `ColorScreen` and `GrayScreen`
+pub struct WideScreen<F: WideFramebuffer> {
+    framebuffer: F,
+    /// The framebuffer as it is sent, for framebuffers which aren't stored that way
+    encoded: Vec<u8>,
+    /// What was sent last time, if all of it made it
+    prev_frame: Option<Vec<u8>>,
+    device: Box<dyn HidAdapter>,
+    report_prefix: Option<u8>,
+}
+
+impl<F: WideFramebuffer> WideScreen<F> {
+    /// Load a device from its path (e.g. /dev/hidraw3)
+    pub fn from_path(device_path: &CStr, width: usize, height: usize) -> Result<Self, HidError> {
+        let api = HidApi::new()?;
+        let device = api.open_path(device_path)?;
+        Self::from_device(device, width, height)
+    }
+
+    /// Load from a hidapi device
+    pub fn from_device(
+        device: impl HidAdapter + 'static,
+        width: usize,
+        height: usize,
+    ) -> Result<Self, HidError> {
+        Ok(Self {
+            framebuffer: F::new(width, height),
+            encoded: vec![],
+            prev_frame: None,
+            device: Box::new(device),
+            report_prefix: None,
+        })
+    }
+
+    /// Start every report sent to the device with the given byte, see
+    /// `OledScreen::with_report_prefix`
+    pub fn with_report_prefix(mut self, prefix: u8) -> Self {
+        self.report_prefix = Some(prefix);
+        self
+    }
+
+    /// The pixels being drawn, for drawing code shared with hosts which don't have the standard
+    /// library (see `color` and `gray`)
+    pub fn framebuffer(&self) -> &F {
+        &self.framebuffer
+    }
+
+    /// The pixels being drawn, see `framebuffer`
+    pub fn framebuffer_mut(&mut self) -> &mut F {
+        &mut self.framebuffer
+    }
+
+    /// The device the screen is drawn on
+    pub fn device(&self) -> &dyn HidAdapter {
+        self.device.as_ref()
+    }
+
+    /// Send the current state of the screen to the wrapped HID device
+    pub fn send(&mut self) -> Result<(), HidError> {
+        send_wide_frame(
+            self.device.as_ref(),
+            self.report_prefix,
+            F::REPORT_ID,
+            self.framebuffer.encode(&mut self.encoded),
+            &mut self.prev_frame,
+        )?;
+        Ok(())
+    }
+
+    /// Send a control command (e.g. a brightness change) to the device, which takes effect
+    /// immediately
+    pub fn send_control(&self, message: &ControlMessage) -> Result<(), HidError> {
+        write_report(
+            self.device.as_ref(),
+            self.report_prefix,
+            &message.to_bytes(),
+        )?;
+        Ok(())
+    }
+
+    /// Draw an image at its original size with its bottom left corner at x, y, with each pixel
+    /// set to the nearest one the screen can show. Pixels which are less than half opaque are
+    /// skipped
+    pub fn draw_image(&mut self, image: &DynamicImage, x: usize, y: usize) {
+        let image = image.to_rgba8();
+        let height = image.height() as usize;
+
+        for (col, row, pixel) in image.enumerate_pixels() {
+            let [red, green, blue, alpha] = pixel.0;
+            if alpha >= 128 {
+                let pixel = F::pixel_from_rgb(red, green, blue);
+                self.set_pixel(x + col as usize, y + height - 1 - row as usize, pixel);
+            }
+        }
+    }
+
+    /// Turn every pixel off
+    pub fn clear(&mut self) {
+        self.framebuffer.fill(F::Pixel::default());
+    }
+
+    /// Set every pixel to the given value
+    pub fn fill(&mut self, pixel: F::Pixel) {
+        self.framebuffer.fill(pixel);
+    }
+
+    /// Paint a square region on the screen
+    pub fn paint_region(&mut self, region: Rect, pixel: F::Pixel) {
+        self.framebuffer.paint_region(region, pixel);
+    }
+
+    /// Draw a one pixel wide straight line between two points, including both ends
+    pub fn draw_line(&mut self, from: Point, to: Point, pixel: F::Pixel) {
+        self.framebuffer.draw_line(from, to, pixel);
+    }
+
+    /// Draw the outline of a square region on the screen, use `paint_region` to fill it in
+    /// instead
+    pub fn draw_rect(&mut self, region: Rect, pixel: F::Pixel) {
+        self.framebuffer.draw_rect(region, pixel);
+    }
+
+    /// The width of the screen in pixels
+    pub fn width(&self) -> usize {
+        self.framebuffer.width()
+    }
+
+    /// The height of the screen in pixels
+    pub fn height(&self) -> usize {
+        self.framebuffer.height()
+    }
+
+    /// Get the current value of a pixel from the local framebuffer
+    pub fn get_pixel(&self, x: usize, y: usize) -> F::Pixel {
+        self.framebuffer.get_pixel(x, y)
+    }
+
+    /// Set the value of a pixel. Pixels outside of the screen are ignored
+    pub fn set_pixel(&mut self, x: usize, y: usize, pixel: F::Pixel) {
+        self.framebuffer.set_pixel(x, y, pixel);
+    }
+}
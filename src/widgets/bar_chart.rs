@@ -0,0 +1,181 @@
+use crate::error::Error;
+use crate::geometry::Rect;
+use crate::screen::OledScreen;
+
+use super::Widget;
+
+/// The gap in pixels between groups of bars
+const GROUP_GAP: usize = 1;
+
+/// How the inside of a bar is filled. Patterns are aligned to the screen rather than the bar, so
+/// neighbouring bars with the same pattern line up
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pattern {
+    Solid,
+    Checkerboard,
+    /// Every other row lit
+    HorizontalStripes,
+    /// Every other column lit
+    VerticalStripes,
+    /// One pixel lit in each 2x2 block
+    Dots,
+}
+
+impl Pattern {
+    /// Whether the pixel at the given position is lit by this pattern
+    pub fn is_set(&self, x: usize, y: usize) -> bool {
+        match self {
+            Pattern::Solid => true,
+            Pattern::Checkerboard => (x + y).is_multiple_of(2),
+            Pattern::HorizontalStripes => y.is_multiple_of(2),
+            Pattern::VerticalStripes => x.is_multiple_of(2),
+            Pattern::Dots => x.is_multiple_of(2) && y.is_multiple_of(2),
+        }
+    }
+}
+
+/// A chart of vertical bars growing upwards from the bottom of its region. Each series of values
+/// gets its own fill pattern, and bars at the same index in each series are grouped together
+pub struct BarChart {
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+    series: Vec<Vec<f32>>,
+    patterns: Vec<Pattern>,
+    max: Option<f32>,
+}
+
+impl BarChart {
+    /// Create an empty chart occupying the given region of the screen. Bars are scaled so that
+    /// the largest value fills the full height
+    pub fn new(x: usize, y: usize, width: usize, height: usize) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+            series: vec![],
+            patterns: vec![
+                Pattern::Solid,
+                Pattern::Checkerboard,
+                Pattern::VerticalStripes,
+                Pattern::Dots,
+            ],
+            max: None,
+        }
+    }
+
+    /// Use a fixed value for the top of the chart instead of scaling to the largest value (e.g.
+    /// 100 for percentages). Values above it are clamped
+    pub fn with_max(mut self, max: f32) -> Self {
+        self.max = Some(max);
+        self
+    }
+
+    /// Set the fill patterns used by each series, in order. Patterns are reused if there are
+    /// more series than patterns
+    pub fn with_patterns(mut self, patterns: Vec<Pattern>) -> Self {
+        self.patterns = patterns;
+        self
+    }
+
+    /// Display a single series of values
+    pub fn set_values(&mut self, values: Vec<f32>) {
+        self.series = vec![values];
+    }
+
+    /// Display several series of values side by side
+    pub fn set_series(&mut self, series: Vec<Vec<f32>>) {
+        self.series = series;
+    }
+
+    fn scale(&self) -> f32 {
+        self.max
+            .unwrap_or_else(|| self.series.iter().flatten().copied().fold(0.0, f32::max))
+    }
+
+    fn pattern(&self, series: usize) -> Pattern {
+        if self.patterns.is_empty() {
+            Pattern::Solid
+        } else {
+            self.patterns[series % self.patterns.len()]
+        }
+    }
+}
+
+impl Widget for BarChart {
+    fn draw(&self, screen: &mut OledScreen) -> Result<(), Error> {
+        let bounds = Rect::new(self.x, self.y, self.width, self.height);
+        screen.draw_widget(bounds, |screen, bounds| {
+            screen.paint_region(bounds, false);
+
+            let groups = self.series.iter().map(Vec::len).max().unwrap_or(0);
+            let scale = self.scale();
+            if groups == 0 || scale <= 0.0 {
+                return Ok(());
+            }
+
+            let bars = groups * self.series.len();
+            let available = bounds.width().saturating_sub((groups - 1) * GROUP_GAP);
+            let bar_width = (available / bars).max(1);
+            let group_width = bar_width * self.series.len() + GROUP_GAP;
+
+            for (series_index, series) in self.series.iter().enumerate() {
+                let pattern = self.pattern(series_index);
+
+                for (group, value) in series.iter().enumerate() {
+                    let bar_height =
+                        ((value / scale).clamp(0.0, 1.0) * bounds.height() as f32).round();
+                    let min_x = bounds.min_x() + group * group_width + series_index * bar_width;
+                    let max_x = (min_x + bar_width).min(bounds.max_x());
+
+                    for x in min_x..max_x {
+                        for y in bounds.min_y()..bounds.min_y() + bar_height as usize {
+                            if pattern.is_set(x, y) {
+                                screen.set_pixel(x, y, true);
+                            }
+                        }
+                    }
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockDevice;
+
+    #[test]
+    fn test_bars_are_auto_scaled() {
+        let mut screen = OledScreen::from_device(MockDevice::new(), 32, 128).unwrap();
+        let mut chart = BarChart::new(0, 0, 32, 20);
+        chart.set_values(vec![10.0, 40.0, 20.0, 0.0]);
+        chart.draw(&mut screen).unwrap();
+
+        // 4 bars of 7 pixels with a 1 pixel gap between them
+        assert!(screen.get_pixel(0, 4));
+        assert!(!screen.get_pixel(0, 5));
+        assert!(!screen.get_pixel(7, 0));
+        assert!(screen.get_pixel(8, 19));
+        assert!(!screen.get_pixel(8, 20));
+        assert!(!screen.get_pixel(24, 0));
+    }
+
+    #[test]
+    fn test_series_use_patterns() {
+        let mut screen = OledScreen::from_device(MockDevice::new(), 32, 128).unwrap();
+        let mut chart = BarChart::new(0, 0, 32, 20)
+            .with_max(100.0)
+            .with_patterns(vec![Pattern::Solid, Pattern::HorizontalStripes]);
+        chart.set_series(vec![vec![100.0], vec![100.0]]);
+        chart.draw(&mut screen).unwrap();
+
+        assert!(screen.get_pixel(0, 1));
+        assert!(screen.get_pixel(20, 0));
+        assert!(!screen.get_pixel(20, 1));
+    }
+}
@@ -0,0 +1,130 @@
+use crate::error::Error;
+use crate::geometry::{Point, Rect};
+use crate::screen::{text_width, OledScreen, TextStyle};
+
+use super::Widget;
+
+/// The width in pixels of the terminal nub on the right of the battery icon
+const NUB_WIDTH: usize = 2;
+
+/// A battery icon filled to the charge level, with the percentage underneath. While charging the
+/// percentage is prefixed with a `+`
+pub struct Battery {
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+    size: f32,
+    font_path: Option<String>,
+    percent: f32,
+    charging: bool,
+}
+
+impl Battery {
+    /// Create an empty battery occupying the given region of the screen. The percentage is drawn
+    /// at the given text size, and the icon takes up whatever height is left above it
+    pub fn new(x: usize, y: usize, width: usize, height: usize, size: f32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+            size,
+            font_path: None,
+            percent: 0.0,
+            charging: false,
+        }
+    }
+
+    /// Use a font loaded from the given path instead of the bundled font
+    pub fn with_font(mut self, font_path: &str) -> Self {
+        self.font_path = Some(font_path.to_string());
+        self
+    }
+
+    /// Set the charge level, from 0 to 100, and whether the battery is charging
+    pub fn set_level(&mut self, percent: f32, charging: bool) {
+        self.percent = percent.clamp(0.0, 100.0);
+        self.charging = charging;
+    }
+}
+
+impl Widget for Battery {
+    fn draw(&self, screen: &mut OledScreen) -> Result<(), Error> {
+        let bounds = Rect::new(self.x, self.y, self.width, self.height);
+        screen.draw_widget(bounds, |screen, bounds| {
+            let max_x = bounds.max_x();
+            let max_y = bounds.max_y();
+            screen.paint_region(bounds, false);
+
+            // Text is drawn upwards from its y coordinate, so the percentage goes at the bottom of
+            // the region and the icon above it
+            let label = format!(
+                "{}{:.0}%",
+                if self.charging { "+" } else { "" },
+                self.percent
+            );
+            let style = TextStyle::new(self.size).with_font(self.font_path.as_deref());
+            let label_width = text_width(&label, &style)?;
+            let label_x = bounds.max_x().saturating_sub(label_width) / 2;
+            screen.draw_text(&label, label_x, bounds.min_y(), &style)?;
+
+            let icon_min_y = bounds.min_y() + self.size.ceil() as usize + 2;
+            let body_max_x = max_x.saturating_sub(NUB_WIDTH);
+            if max_y < icon_min_y + 5 || body_max_x < bounds.min_x() + 3 {
+                return Ok(());
+            }
+
+            // A one pixel outline with a nub in the middle of the right hand side
+            let body = Rect::from_corners(
+                Point::new(bounds.min_x(), icon_min_y),
+                Point::new(body_max_x, max_y),
+            );
+            screen.paint_region(body, true);
+            screen.paint_region(body.inset(1), false);
+            let nub_inset = (max_y - icon_min_y) / 3;
+            screen.paint_region(
+                Rect::from_corners(
+                    Point::new(body_max_x, icon_min_y + nub_inset),
+                    Point::new(max_x, max_y - nub_inset),
+                ),
+                true,
+            );
+
+            // The fill is inset by a further pixel so that it reads as separate from the outline
+            let inner_width = body.width().saturating_sub(4);
+            let filled = (inner_width as f32 * self.percent / 100.0).round() as usize;
+            screen.paint_region(
+                Rect::new(
+                    bounds.min_x() + 2,
+                    icon_min_y + 2,
+                    filled,
+                    body.height() - 4,
+                ),
+                true,
+            );
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockDevice;
+
+    #[test]
+    fn test_icon_fill() {
+        let mut screen = OledScreen::from_device(MockDevice::new(), 32, 128).unwrap();
+        let mut battery = Battery::new(0, 0, 32, 24, 8.0);
+        battery.set_level(50.0, true);
+        battery.draw(&mut screen).unwrap();
+
+        // The body spans x 0..30 with 26 pixels of fill space starting at x = 2
+        assert!(screen.get_pixel(0, 23));
+        assert!(screen.get_pixel(14, 15));
+        assert!(!screen.get_pixel(15, 15));
+        assert!(screen.get_pixel(31, 16));
+        assert!(!screen.get_pixel(31, 10));
+    }
+}
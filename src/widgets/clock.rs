@@ -0,0 +1,149 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::error::Error;
+use crate::geometry::Rect;
+use crate::screen::{OledScreen, TextStyle};
+use crate::utils::civil_from_days;
+
+use super::Widget;
+
+/// The default number of pixels the clock wanders by in each direction
+const DEFAULT_DRIFT: usize = 3;
+
+/// A clock showing the time and date, which shifts its position slightly every minute so that it
+/// can be left on screen without burning in.
+///
+/// Times are shown using a fixed offset from UTC, set with `with_utc_offset`
+pub struct Clock {
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+    size: f32,
+    font_path: Option<String>,
+    utc_offset: i64,
+    drift: usize,
+    show_date: bool,
+}
+
+impl Clock {
+    /// Create a clock occupying the given region of the screen, showing UTC time
+    pub fn new(x: usize, y: usize, width: usize, height: usize, size: f32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+            size,
+            font_path: None,
+            utc_offset: 0,
+            drift: DEFAULT_DRIFT,
+            show_date: true,
+        }
+    }
+
+    /// Use a font loaded from the given path instead of the bundled font
+    pub fn with_font(mut self, font_path: &str) -> Self {
+        self.font_path = Some(font_path.to_string());
+        self
+    }
+
+    /// Show the time at the given offset from UTC, in minutes (e.g. 60 for UTC+1)
+    pub fn with_utc_offset(mut self, minutes: i64) -> Self {
+        self.utc_offset = minutes * 60;
+        self
+    }
+
+    /// Set how many pixels the clock may move by in each direction. 0 keeps it still
+    pub fn with_drift(mut self, drift: usize) -> Self {
+        self.drift = drift;
+        self
+    }
+
+    /// Only show the time, without the date underneath
+    pub fn without_date(mut self) -> Self {
+        self.show_date = false;
+        self
+    }
+
+    /// Where the clock should be drawn relative to its region at the given minute. Walks through
+    /// every position in the drift square, one step per minute
+    fn offset(&self, minutes: i64) -> (usize, usize) {
+        let positions = (self.drift + 1) as i64;
+        let step = minutes.rem_euclid(positions * positions);
+        ((step % positions) as usize, (step / positions) as usize)
+    }
+
+    fn draw_at(&self, screen: &mut OledScreen, unix_seconds: i64) -> Result<(), Error> {
+        let bounds = Rect::new(self.x, self.y, self.width, self.height);
+        screen.draw_widget(bounds, |screen, bounds| {
+            screen.paint_region(bounds, false);
+
+            let local = unix_seconds + self.utc_offset;
+            let (dx, dy) = self.offset(local.div_euclid(60));
+            let (_, month, day) = civil_from_days(local.div_euclid(86400));
+            let seconds_of_day = local.rem_euclid(86400);
+
+            let mut lines = vec![format!(
+                "{:02}:{:02}",
+                seconds_of_day / 3600,
+                seconds_of_day % 3600 / 60
+            )];
+            if self.show_date {
+                lines.push(format!("{day:02}/{month:02}"));
+            }
+
+            // Text is drawn upwards from its y coordinate, so the first line is the highest one
+            let line_height = self.size.ceil() as usize + 2;
+            let top = bounds.max_y().saturating_sub(self.drift);
+            for (index, line) in lines.iter().enumerate() {
+                if let Some(line_y) = top.checked_sub((index + 1) * line_height) {
+                    screen.draw_text(
+                        line,
+                        bounds.min_x() + dx,
+                        line_y + dy,
+                        &TextStyle::new(self.size).with_font(self.font_path.as_deref()),
+                    )?;
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+impl Widget for Clock {
+    fn draw(&self, screen: &mut OledScreen) -> Result<(), Error> {
+        let unix_seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since_epoch| since_epoch.as_secs() as i64);
+
+        self.draw_at(screen, unix_seconds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockDevice;
+
+    #[test]
+    fn test_offset_walks_drift_square() {
+        let clock = Clock::new(0, 0, 32, 32, 8.0).with_drift(1);
+
+        assert_eq!(clock.offset(0), (0, 0));
+        assert_eq!(clock.offset(1), (1, 0));
+        assert_eq!(clock.offset(3), (1, 1));
+        assert_eq!(clock.offset(4), (0, 0));
+    }
+
+    #[test]
+    fn test_clock_moves_each_minute() {
+        let clock = Clock::new(0, 0, 32, 32, 8.0).with_utc_offset(60);
+        let mut first = OledScreen::from_device(MockDevice::new(), 32, 128).unwrap();
+        let mut second = OledScreen::from_device(MockDevice::new(), 32, 128).unwrap();
+
+        clock.draw_at(&mut first, 82800).unwrap();
+        clock.draw_at(&mut second, 82800 + 60).unwrap();
+        assert_ne!(first.snapshot(), second.snapshot());
+    }
+}
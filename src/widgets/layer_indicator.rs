@@ -0,0 +1,129 @@
+use crate::error::Error;
+use crate::events::KeyboardEvent;
+use crate::geometry::Rect;
+use crate::screen::{text_width, OledScreen, TextStyle};
+
+use super::Widget;
+
+/// Shows the keyboard's active layer, by name if one has been given or by number otherwise.
+/// Kept up to date with layer change events sent by the keymap
+pub struct LayerIndicator {
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+    size: f32,
+    font_path: Option<String>,
+    names: Vec<String>,
+    layer: u8,
+}
+
+impl LayerIndicator {
+    /// Create an indicator occupying the given region of the screen, starting on layer 0
+    pub fn new(x: usize, y: usize, width: usize, height: usize, size: f32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+            size,
+            font_path: None,
+            names: vec![],
+            layer: 0,
+        }
+    }
+
+    /// Use a font loaded from the given path instead of the bundled font
+    pub fn with_font(mut self, font_path: &str) -> Self {
+        self.font_path = Some(font_path.to_string());
+        self
+    }
+
+    /// Name the layers in order, starting from layer 0. Layers without a name show their number
+    pub fn with_names(mut self, names: Vec<String>) -> Self {
+        self.names = names;
+        self
+    }
+
+    /// The active layer
+    pub fn layer(&self) -> u8 {
+        self.layer
+    }
+
+    pub fn set_layer(&mut self, layer: u8) {
+        self.layer = layer;
+    }
+
+    /// Follow layer change events. Returns whether the event was handled
+    pub fn handle_event(&mut self, event: &KeyboardEvent) -> bool {
+        match event {
+            KeyboardEvent::Layer(layer) => {
+                self.set_layer(*layer);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// The text shown for the active layer
+    pub fn label(&self) -> String {
+        match self.names.get(self.layer as usize) {
+            Some(name) => name.clone(),
+            None => self.layer.to_string(),
+        }
+    }
+}
+
+impl Widget for LayerIndicator {
+    fn draw(&self, screen: &mut OledScreen) -> Result<(), Error> {
+        let bounds = Rect::new(self.x, self.y, self.width, self.height);
+        screen.draw_widget(bounds, |screen, bounds| {
+            screen.paint_region(bounds, false);
+
+            let label = self.label();
+            let style = TextStyle::new(self.size).with_font(self.font_path.as_deref());
+
+            // Centre the label in the region, falling back to the left edge if it is too wide
+            let width = text_width(&label, &style)?;
+            let x = bounds.max_x().saturating_sub(width) / 2;
+            let y = bounds.max_y().saturating_sub(self.size.ceil() as usize) / 2;
+            screen.draw_text(&label, x, y, &style)?;
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockDevice;
+
+    #[test]
+    fn test_label_follows_events() {
+        let mut indicator =
+            LayerIndicator::new(0, 0, 32, 16, 8.0).with_names(vec!["BASE".into(), "NAV".into()]);
+        assert_eq!(indicator.label(), "BASE");
+
+        assert!(indicator.handle_event(&KeyboardEvent::Layer(1)));
+        assert_eq!(indicator.label(), "NAV");
+
+        assert!(!indicator.handle_event(&KeyboardEvent::Keypress));
+        indicator.set_layer(5);
+        assert_eq!(indicator.label(), "5");
+    }
+
+    #[test]
+    fn test_draw_stays_within_region() {
+        let mut screen = OledScreen::from_device(MockDevice::new(), 32, 128).unwrap();
+        let mut indicator = LayerIndicator::new(0, 0, 32, 16, 8.0);
+        indicator.set_layer(2);
+        indicator.draw(&mut screen).unwrap();
+
+        let lit = (0..32)
+            .flat_map(|x| (0..128).map(move |y| (x, y)))
+            .filter(|(x, y)| screen.get_pixel(*x, *y))
+            .collect::<Vec<_>>();
+        assert!(!lit.is_empty());
+        assert!(lit.iter().all(|(_, y)| *y < 16));
+    }
+}
@@ -0,0 +1,116 @@
+use crate::error::Error;
+use crate::events::KeyboardEvent;
+use crate::geometry::Rect;
+use crate::screen::{text_width, OledScreen, TextStyle};
+
+use super::Widget;
+
+/// A strip of Caps, Num and Scroll lock indicators. Each lock gets an equal share of the strip and
+/// is highlighted (inverted) while it is on
+pub struct LockIndicators {
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+    size: f32,
+    font_path: Option<String>,
+    caps: bool,
+    num: bool,
+    scroll: bool,
+}
+
+impl LockIndicators {
+    /// Create indicators occupying the given region of the screen, with every lock off
+    pub fn new(x: usize, y: usize, width: usize, height: usize, size: f32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+            size,
+            font_path: None,
+            caps: false,
+            num: false,
+            scroll: false,
+        }
+    }
+
+    /// Use a font loaded from the given path instead of the bundled font
+    pub fn with_font(mut self, font_path: &str) -> Self {
+        self.font_path = Some(font_path.to_string());
+        self
+    }
+
+    pub fn set_locks(&mut self, caps: bool, num: bool, scroll: bool) {
+        self.caps = caps;
+        self.num = num;
+        self.scroll = scroll;
+    }
+
+    /// Follow lock state events. Returns whether the event was handled
+    pub fn handle_event(&mut self, event: &KeyboardEvent) -> bool {
+        match *event {
+            KeyboardEvent::Locks { num, caps, scroll } => {
+                self.set_locks(caps, num, scroll);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Widget for LockIndicators {
+    fn draw(&self, screen: &mut OledScreen) -> Result<(), Error> {
+        let bounds = Rect::new(self.x, self.y, self.width, self.height);
+        screen.draw_widget(bounds, |screen, bounds| {
+            screen.paint_region(bounds, false);
+
+            let style = TextStyle::new(self.size).with_font(self.font_path.as_deref());
+            let cell_width = bounds.width() / 3;
+            let text_y = bounds.max_y().saturating_sub(self.size.ceil() as usize) / 2;
+            let locks = [("C", self.caps), ("N", self.num), ("S", self.scroll)];
+
+            for (index, (label, enabled)) in locks.into_iter().enumerate() {
+                let min_x = bounds.min_x() + index * cell_width;
+                let label_width = text_width(label, &style)?;
+                let label_x = min_x + cell_width.saturating_sub(label_width) / 2;
+                screen.draw_text(label, label_x, text_y, &style)?;
+
+                if enabled {
+                    screen.invert_region(Rect::new(
+                        min_x,
+                        bounds.min_y(),
+                        cell_width,
+                        bounds.height(),
+                    ));
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockDevice;
+
+    #[test]
+    fn test_enabled_locks_are_inverted() {
+        let mut screen = OledScreen::from_device(MockDevice::new(), 32, 128).unwrap();
+        let mut indicators = LockIndicators::new(0, 0, 30, 12, 8.0);
+
+        assert!(indicators.handle_event(&KeyboardEvent::Locks {
+            num: false,
+            caps: true,
+            scroll: true,
+        }));
+        indicators.draw(&mut screen).unwrap();
+
+        // The corners of each cell are never covered by its label
+        assert!(screen.get_pixel(0, 0));
+        assert!(!screen.get_pixel(10, 0));
+        assert!(screen.get_pixel(20, 11));
+        assert!(!screen.get_pixel(30, 0));
+    }
+}
@@ -0,0 +1,249 @@
+use crate::error::Error;
+use crate::events::KeyboardEvent;
+use crate::geometry::Rect;
+use crate::screen::{OledScreen, TextStyle};
+
+use super::Widget;
+
+/// The gap in pixels left around an item's text inside its row
+const PADDING: usize = 1;
+
+/// A scrollable list of items with one highlighted (inverted) selection, which can be moved
+/// using events received from the keyboard
+pub struct Menu {
+    items: Vec<String>,
+    selected: usize,
+    scroll: usize,
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+    size: f32,
+    font_path: Option<String>,
+    encoder: Option<u8>,
+    keycodes: Option<(u16, u16)>,
+}
+
+impl Menu {
+    /// Create a menu occupying the given region of the screen, with the first item selected.
+    /// Items are listed top-down and the menu scrolls once they no longer fit.
+    ///
+    /// By default, turning any encoder moves the selection
+    pub fn new(
+        items: Vec<String>,
+        x: usize,
+        y: usize,
+        width: usize,
+        height: usize,
+        size: f32,
+    ) -> Self {
+        Self {
+            items,
+            selected: 0,
+            scroll: 0,
+            x,
+            y,
+            width,
+            height,
+            size,
+            font_path: None,
+            encoder: None,
+            keycodes: None,
+        }
+    }
+
+    /// Use a font loaded from the given path instead of the bundled font
+    pub fn with_font(mut self, font_path: &str) -> Self {
+        self.font_path = Some(font_path.to_string());
+        self
+    }
+
+    /// Only respond to turns of the encoder with the given index
+    pub fn with_encoder(mut self, index: u8) -> Self {
+        self.encoder = Some(index);
+        self
+    }
+
+    /// Move the selection up and down when the given custom keycodes are received
+    pub fn with_keycodes(mut self, up: u16, down: u16) -> Self {
+        self.keycodes = Some((up, down));
+        self
+    }
+
+    /// The index of the currently selected item
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    /// The text of the currently selected item, if the menu has any items
+    pub fn selected_item(&self) -> Option<&str> {
+        self.items.get(self.selected).map(String::as_str)
+    }
+
+    /// Move the selection down one item, stopping at the last item
+    pub fn select_next(&mut self) {
+        if self.selected + 1 < self.items.len() {
+            self.select(self.selected + 1);
+        }
+    }
+
+    /// Move the selection up one item, stopping at the first item
+    pub fn select_previous(&mut self) {
+        if self.selected > 0 {
+            self.select(self.selected - 1);
+        }
+    }
+
+    /// Select the item at the given index, scrolling it into view. Out of range indices are
+    /// ignored
+    pub fn select(&mut self, index: usize) {
+        if index >= self.items.len() {
+            return;
+        }
+        self.selected = index;
+
+        let visible = self.visible_rows();
+        if index < self.scroll {
+            self.scroll = index;
+        } else if index >= self.scroll + visible {
+            self.scroll = index + 1 - visible;
+        }
+    }
+
+    /// Move the selection in response to an event from the keyboard. Returns whether the event
+    /// was handled by the menu
+    pub fn handle_event(&mut self, event: &KeyboardEvent) -> bool {
+        match *event {
+            KeyboardEvent::Encoder { index, clockwise }
+                if self.encoder.is_none_or(|encoder| encoder == index) =>
+            {
+                if clockwise {
+                    self.select_next()
+                } else {
+                    self.select_previous()
+                }
+                true
+            }
+            KeyboardEvent::Keycode(keycode) => match self.keycodes {
+                Some((up, _)) if keycode == up => {
+                    self.select_previous();
+                    true
+                }
+                Some((_, down)) if keycode == down => {
+                    self.select_next();
+                    true
+                }
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    fn row_height(&self) -> usize {
+        self.size.ceil() as usize + PADDING * 2
+    }
+
+    fn visible_rows(&self) -> usize {
+        (self.height / self.row_height()).max(1)
+    }
+}
+
+impl Widget for Menu {
+    fn draw(&self, screen: &mut OledScreen) -> Result<(), Error> {
+        let bounds = Rect::new(self.x, self.y, self.width, self.height);
+        screen.draw_widget(bounds, |screen, bounds| {
+            let max_y = bounds.max_y();
+            let row_height = self.row_height();
+
+            screen.paint_region(bounds, false);
+
+            let rows = self.items.iter().enumerate().skip(self.scroll);
+            for (row, (index, item)) in rows.take(self.visible_rows()).enumerate() {
+                // Text is drawn upwards from its y coordinate, so the first row is the highest one
+                let row_max_y = match max_y.checked_sub(row * row_height) {
+                    Some(row_max_y) if row_max_y >= bounds.min_y() + row_height => row_max_y,
+                    _ => break,
+                };
+                let row_min_y = row_max_y - row_height;
+
+                screen.draw_text(
+                    item,
+                    bounds.min_x() + PADDING,
+                    row_min_y + PADDING - 1,
+                    &TextStyle::new(self.size).with_font(self.font_path.as_deref()),
+                )?;
+
+                if index == self.selected {
+                    screen.invert_region(Rect::new(
+                        bounds.min_x(),
+                        row_min_y,
+                        bounds.width(),
+                        row_height,
+                    ));
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockDevice;
+
+    fn menu() -> Menu {
+        let items = ["Play", "Next", "Prev", "Stop", "Quit"];
+        Menu::new(items.map(String::from).to_vec(), 0, 0, 32, 20, 8.0)
+    }
+
+    #[test]
+    fn test_selection_scrolls() {
+        let mut menu = menu();
+        assert_eq!(menu.visible_rows(), 2);
+
+        menu.select_next();
+        menu.select_next();
+        assert_eq!(menu.selected_item(), Some("Prev"));
+        assert_eq!(menu.scroll, 1);
+
+        menu.select(4);
+        menu.select_next();
+        assert_eq!(menu.selected(), 4);
+
+        menu.select(0);
+        menu.select_previous();
+        assert_eq!((menu.selected(), menu.scroll), (0, 0));
+    }
+
+    #[test]
+    fn test_handle_event() {
+        let mut menu = menu().with_encoder(1).with_keycodes(10, 11);
+
+        assert!(menu.handle_event(&KeyboardEvent::Encoder {
+            index: 1,
+            clockwise: true
+        }));
+        assert!(!menu.handle_event(&KeyboardEvent::Encoder {
+            index: 0,
+            clockwise: true
+        }));
+        assert_eq!(menu.selected(), 1);
+
+        assert!(menu.handle_event(&KeyboardEvent::Keycode(11)));
+        assert!(menu.handle_event(&KeyboardEvent::Keycode(10)));
+        assert!(!menu.handle_event(&KeyboardEvent::Keycode(12)));
+        assert_eq!(menu.selected(), 1);
+    }
+
+    #[test]
+    fn test_selected_row_is_inverted() {
+        let mut screen = OledScreen::from_device(MockDevice::new(), 32, 128).unwrap();
+        menu().draw(&mut screen).unwrap();
+
+        // The first row occupies the top of the region, with its background lit
+        assert!(screen.get_pixel(31, 19));
+        assert!(screen.get_pixel(31, 10));
+        assert!(!screen.get_pixel(31, 9));
+    }
+}
@@ -0,0 +1,47 @@
+mod bar_chart;
+mod battery;
+mod clock;
+mod layer_indicator;
+mod lock_indicators;
+mod menu;
+mod next_event;
+mod pomodoro;
+mod progress_bar;
+mod sparkline;
+mod ticker;
+mod toast;
+mod wpm_meter;
+
+pub use bar_chart::{BarChart, Pattern};
+pub use battery::Battery;
+pub use clock::Clock;
+pub use layer_indicator::LayerIndicator;
+pub use lock_indicators::LockIndicators;
+pub use menu::Menu;
+pub use next_event::NextEvent;
+pub use pomodoro::{Phase, Pomodoro};
+pub use progress_bar::ProgressBar;
+pub use sparkline::Sparkline;
+pub use ticker::Ticker;
+pub use toast::Toast;
+pub use wpm_meter::WpmMeter;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::error::Error;
+use crate::screen::OledScreen;
+
+/// A self-contained element which knows how to draw itself onto a region of the screen
+pub trait Widget {
+    /// Draw the current state of the widget to the screen. This only modifies the local
+    /// framebuffer, call `OledScreen::send` to push the result to the device. Fails if something
+    /// the widget draws with can't be loaded, such as a font
+    fn draw(&self, screen: &mut OledScreen) -> Result<(), Error>;
+}
+
+impl<W: Widget> Widget for Rc<RefCell<W>> {
+    fn draw(&self, screen: &mut OledScreen) -> Result<(), Error> {
+        self.borrow().draw(screen)
+    }
+}
@@ -0,0 +1,128 @@
+use crate::error::Error;
+use crate::geometry::Rect;
+use crate::screen::{OledScreen, TextStyle};
+
+use super::Widget;
+
+/// Events starting sooner than this many minutes are emphasised by default
+const DEFAULT_URGENT_MINUTES: u64 = 5;
+
+/// Shows the title of the next event and how long until it starts, inverting the whole widget
+/// when the event is about to begin
+pub struct NextEvent {
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+    size: f32,
+    font_path: Option<String>,
+    urgent_minutes: u64,
+    event: Option<(String, u64)>,
+}
+
+impl NextEvent {
+    /// Create a widget occupying the given region of the screen, with no event to show
+    pub fn new(x: usize, y: usize, width: usize, height: usize, size: f32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+            size,
+            font_path: None,
+            urgent_minutes: DEFAULT_URGENT_MINUTES,
+            event: None,
+        }
+    }
+
+    /// Use a font loaded from the given path instead of the bundled font
+    pub fn with_font(mut self, font_path: &str) -> Self {
+        self.font_path = Some(font_path.to_string());
+        self
+    }
+
+    /// Emphasise events starting in fewer than this many minutes
+    pub fn with_urgent_minutes(mut self, minutes: u64) -> Self {
+        self.urgent_minutes = minutes;
+        self
+    }
+
+    /// Set the event to show and the number of minutes until it starts, or `None` to show
+    /// nothing
+    pub fn set_event(&mut self, event: Option<(&str, u64)>) {
+        self.event = event.map(|(title, minutes)| (title.to_string(), minutes));
+    }
+
+    /// Whether the event is close enough to be emphasised
+    pub fn is_urgent(&self) -> bool {
+        self.event
+            .as_ref()
+            .is_some_and(|(_, minutes)| *minutes < self.urgent_minutes)
+    }
+}
+
+impl Widget for NextEvent {
+    fn draw(&self, screen: &mut OledScreen) -> Result<(), Error> {
+        let bounds = Rect::new(self.x, self.y, self.width, self.height);
+        screen.draw_widget(bounds, |screen, bounds| {
+            let max_y = bounds.max_y();
+            screen.paint_region(bounds, false);
+
+            let (title, minutes) = match &self.event {
+                Some(event) => event,
+                None => return Ok(()),
+            };
+            let countdown = match minutes {
+                0 => "now".to_string(),
+                minutes if *minutes < 60 => format!("{minutes}m"),
+                minutes => format!("{}h{:02}", minutes / 60, minutes % 60),
+            };
+
+            // Text is drawn upwards from its y coordinate, so the first line is the highest one
+            let line_height = self.size.ceil() as usize + 1;
+            for (index, line) in [title, &countdown].into_iter().enumerate() {
+                if let Some(line_y) = max_y.checked_sub((index + 1) * line_height) {
+                    if line_y >= bounds.min_y() {
+                        screen.draw_text(
+                            line,
+                            bounds.min_x(),
+                            line_y,
+                            &TextStyle::new(self.size).with_font(self.font_path.as_deref()),
+                        )?;
+                    }
+                }
+            }
+
+            if self.is_urgent() {
+                screen.invert_region(bounds);
+            }
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockDevice;
+
+    #[test]
+    fn test_urgent_events_are_inverted() {
+        let mut screen = OledScreen::from_device(MockDevice::new(), 32, 128).unwrap();
+        let mut next_event = NextEvent::new(0, 0, 32, 20, 8.0);
+
+        next_event.set_event(Some(("Standup", 30)));
+        next_event.draw(&mut screen).unwrap();
+        assert!(!next_event.is_urgent());
+        assert!(!screen.get_pixel(31, 0));
+
+        next_event.set_event(Some(("Standup", 4)));
+        next_event.draw(&mut screen).unwrap();
+        assert!(next_event.is_urgent());
+        assert!(screen.get_pixel(31, 0));
+
+        next_event.set_event(None);
+        next_event.draw(&mut screen).unwrap();
+        assert!(!screen.get_pixel(31, 0));
+    }
+}
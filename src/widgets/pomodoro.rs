@@ -0,0 +1,358 @@
+use std::f32::consts::TAU;
+use std::time::{Duration, Instant};
+
+use crate::error::Error;
+use crate::events::KeyboardEvent;
+use crate::geometry::Rect;
+use crate::screen::{text_width, OledScreen, TextStyle};
+
+use super::Widget;
+
+const DEFAULT_WORK: Duration = Duration::from_secs(25 * 60);
+const DEFAULT_BREAK: Duration = Duration::from_secs(5 * 60);
+
+/// Which part of the pomodoro cycle the timer is in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Work,
+    Break,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Paused { remaining: Duration },
+    Running { ends_at: Instant },
+}
+
+/// A pomodoro focus timer, showing the remaining minutes in large digits inside a ring which
+/// empties as the phase counts down. Work and break phases alternate automatically
+pub struct Pomodoro {
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+    font_path: Option<String>,
+    work: Duration,
+    rest: Duration,
+    phase: Phase,
+    state: State,
+    keycodes: Option<(u16, u16)>,
+}
+
+impl Pomodoro {
+    /// Create a paused timer occupying the given region of the screen, using 25 minute work and
+    /// 5 minute break phases
+    pub fn new(x: usize, y: usize, width: usize, height: usize) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+            font_path: None,
+            work: DEFAULT_WORK,
+            rest: DEFAULT_BREAK,
+            phase: Phase::Work,
+            state: State::Paused {
+                remaining: DEFAULT_WORK,
+            },
+            keycodes: None,
+        }
+    }
+
+    /// Set the length of the work and break phases. This resets the timer
+    pub fn with_durations(mut self, work: Duration, rest: Duration) -> Self {
+        self.work = work;
+        self.rest = rest;
+        self.reset();
+        self
+    }
+
+    /// Use a font loaded from the given path instead of the bundled font
+    pub fn with_font(mut self, font_path: &str) -> Self {
+        self.font_path = Some(font_path.to_string());
+        self
+    }
+
+    /// Toggle the timer when the first keycode is received and reset it on the second
+    pub fn with_keycodes(mut self, toggle: u16, reset: u16) -> Self {
+        self.keycodes = Some((toggle, reset));
+        self
+    }
+
+    /// The phase currently being counted down
+    pub fn phase(&self) -> Phase {
+        self.phase
+    }
+
+    pub fn is_running(&self) -> bool {
+        matches!(self.state, State::Running { .. })
+    }
+
+    /// Start the timer if it is paused, or pause it if it is running
+    pub fn toggle(&mut self) {
+        self.toggle_at(Instant::now())
+    }
+
+    /// Start counting down, if the timer isn't already running
+    pub fn start(&mut self) {
+        self.start_at(Instant::now())
+    }
+
+    /// Stop counting down, keeping the time left for when the timer is started again
+    pub fn pause(&mut self) {
+        self.pause_at(Instant::now())
+    }
+
+    /// Pause the timer and go back to the start of a work phase
+    pub fn reset(&mut self) {
+        self.phase = Phase::Work;
+        self.state = State::Paused {
+            remaining: self.work,
+        };
+    }
+
+    /// Start, pause or reset the timer in response to the configured keycodes. Returns whether
+    /// the event was handled
+    pub fn handle_event(&mut self, event: &KeyboardEvent) -> bool {
+        match (event, self.keycodes) {
+            (KeyboardEvent::Keycode(keycode), Some((toggle, _))) if *keycode == toggle => {
+                self.toggle();
+                true
+            }
+            (KeyboardEvent::Keycode(keycode), Some((_, reset))) if *keycode == reset => {
+                self.reset();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Move on to the next phase if the current one has finished. Call this regularly (e.g. once
+    /// per frame). Returns whether the phase changed, which is a good time to notify the user
+    pub fn update(&mut self) -> bool {
+        self.update_at(Instant::now())
+    }
+
+    /// The time left in the current phase
+    pub fn remaining(&self) -> Duration {
+        self.remaining_at(Instant::now())
+    }
+
+    fn phase_length(&self) -> Duration {
+        match self.phase {
+            Phase::Work => self.work,
+            Phase::Break => self.rest,
+        }
+    }
+
+    fn toggle_at(&mut self, now: Instant) {
+        if self.is_running() {
+            self.pause_at(now);
+        } else {
+            self.start_at(now);
+        }
+    }
+
+    fn start_at(&mut self, now: Instant) {
+        if let State::Paused { remaining } = self.state {
+            self.state = State::Running {
+                ends_at: now + remaining,
+            };
+        }
+    }
+
+    fn pause_at(&mut self, now: Instant) {
+        if let State::Running { ends_at } = self.state {
+            self.state = State::Paused {
+                remaining: ends_at.saturating_duration_since(now),
+            };
+        }
+    }
+
+    fn update_at(&mut self, now: Instant) -> bool {
+        match self.state {
+            State::Running { ends_at } if now >= ends_at => {
+                self.phase = match self.phase {
+                    Phase::Work => Phase::Break,
+                    Phase::Break => Phase::Work,
+                };
+                // Start the next phase from when the last one ended rather than from now, so
+                // a late update doesn't make the timer drift
+                self.state = State::Running {
+                    ends_at: ends_at + self.phase_length(),
+                };
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn remaining_at(&self, now: Instant) -> Duration {
+        match self.state {
+            State::Paused { remaining } => remaining,
+            State::Running { ends_at } => ends_at.saturating_duration_since(now),
+        }
+    }
+
+    fn draw_at(&self, screen: &mut OledScreen, now: Instant) -> Result<(), Error> {
+        let bounds = Rect::new(self.x, self.y, self.width, self.height);
+        screen.draw_widget(bounds, |screen, bounds| {
+            screen.paint_region(bounds, false);
+
+            let remaining = self.remaining_at(now);
+            let progress = if self.phase_length().is_zero() {
+                0.0
+            } else {
+                remaining.as_secs_f32() / self.phase_length().as_secs_f32()
+            };
+
+            // The ring sits at the top of the region, with the seconds underneath it
+            let diameter = bounds.width().min(bounds.height());
+            let radius = diameter as f32 / 2.0 - 1.0;
+            let center_x = bounds.min_x() as f32 + bounds.width() as f32 / 2.0;
+            let center_y = bounds.max_y() as f32 - diameter as f32 / 2.0;
+            draw_ring(screen, center_x, center_y, radius, progress);
+
+            let (minutes, seconds) = readout(remaining);
+            let minutes_size = (radius * 0.9).max(1.0);
+            let seconds_size = (minutes_size / 2.0).max(8.0);
+
+            let font_path = self.font_path.as_deref();
+            let lines = [
+                (minutes, minutes_size, center_y - minutes_size / 2.0),
+                (
+                    seconds,
+                    seconds_size,
+                    center_y - radius - seconds_size - 2.0,
+                ),
+            ];
+            for (text, size, y) in lines {
+                let style = TextStyle::new(size).with_font(font_path);
+                let width = text_width(&text, &style)?;
+                let x = (center_x - width as f32 / 2.0).max(bounds.min_x() as f32);
+                if y >= bounds.min_y() as f32 {
+                    screen.draw_text(&text, x as usize, y as usize, &style)?;
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+/// The whole minutes and the seconds past them, as shown inside and underneath the ring
+fn readout(remaining: Duration) -> (String, String) {
+    let seconds = remaining.as_secs();
+    (
+        format!("{:02}", (seconds / 60).min(99)),
+        format!(":{:02}", seconds % 60),
+    )
+}
+
+/// Draw the outline of a circle, starting at the top and going clockwise for the given fraction
+/// of a full turn. Text is drawn upwards, so "top" is the highest y coordinate
+fn draw_ring(screen: &mut OledScreen, center_x: f32, center_y: f32, radius: f32, fraction: f32) {
+    let steps = (TAU * radius).ceil().max(1.0) as usize * 2;
+    let end = (steps as f32 * fraction.clamp(0.0, 1.0)).round() as usize;
+
+    for step in 0..end {
+        let angle = TAU * step as f32 / steps as f32;
+        let x = center_x + radius * angle.sin();
+        let y = center_y + radius * angle.cos();
+        if x >= 0.0 && y >= 0.0 {
+            screen.set_pixel(x.round() as usize, y.round() as usize, true);
+        }
+    }
+}
+
+impl Widget for Pomodoro {
+    fn draw(&self, screen: &mut OledScreen) -> Result<(), Error> {
+        self.draw_at(screen, Instant::now())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockDevice;
+
+    fn pomodoro() -> Pomodoro {
+        Pomodoro::new(0, 0, 32, 64)
+            .with_durations(Duration::from_secs(60), Duration::from_secs(30))
+            .with_keycodes(1, 2)
+    }
+
+    #[test]
+    fn test_pause_and_resume() {
+        let start = Instant::now();
+        let mut pomodoro = pomodoro();
+
+        pomodoro.toggle_at(start);
+        pomodoro.toggle_at(start + Duration::from_secs(20));
+        assert!(!pomodoro.is_running());
+        assert_eq!(
+            pomodoro.remaining_at(start + Duration::from_secs(100)),
+            Duration::from_secs(40)
+        );
+
+        assert!(pomodoro.handle_event(&KeyboardEvent::Keycode(2)));
+        assert_eq!(pomodoro.remaining_at(start), Duration::from_secs(60));
+    }
+
+    #[test]
+    fn test_start_and_pause() {
+        let start = Instant::now();
+        let mut pomodoro = pomodoro();
+
+        pomodoro.start_at(start);
+        pomodoro.start_at(start + Duration::from_secs(10));
+        assert_eq!(
+            pomodoro.remaining_at(start + Duration::from_secs(10)),
+            Duration::from_secs(50)
+        );
+
+        pomodoro.pause_at(start + Duration::from_secs(15));
+        pomodoro.pause_at(start + Duration::from_secs(30));
+        assert!(!pomodoro.is_running());
+        assert_eq!(pomodoro.remaining(), Duration::from_secs(45));
+    }
+
+    #[test]
+    fn test_readout() {
+        let readout = |seconds| readout(Duration::from_secs(seconds));
+        assert_eq!(readout(59), ("00".to_string(), ":59".to_string()));
+        assert_eq!(readout(90), ("01".to_string(), ":30".to_string()));
+        assert_eq!(readout(25 * 60), ("25".to_string(), ":00".to_string()));
+    }
+
+    #[test]
+    fn test_phases_alternate() {
+        let start = Instant::now();
+        let mut pomodoro = pomodoro();
+        pomodoro.toggle_at(start);
+
+        assert!(!pomodoro.update_at(start + Duration::from_secs(59)));
+        assert!(pomodoro.update_at(start + Duration::from_secs(61)));
+        assert_eq!(pomodoro.phase(), Phase::Break);
+        assert_eq!(
+            pomodoro.remaining_at(start + Duration::from_secs(61)),
+            Duration::from_secs(29)
+        );
+    }
+
+    #[test]
+    fn test_ring_empties() {
+        let start = Instant::now();
+        let mut screen = OledScreen::from_device(MockDevice::new(), 32, 128).unwrap();
+        let mut pomodoro = pomodoro();
+
+        // The top of the ring is lit while the full phase remains, and cleared at the end
+        pomodoro.draw_at(&mut screen, start).unwrap();
+        assert!(screen.get_pixel(16, 63));
+
+        pomodoro.toggle_at(start);
+        pomodoro
+            .draw_at(&mut screen, start + Duration::from_secs(60))
+            .unwrap();
+        assert!(!screen.get_pixel(16, 63));
+    }
+}
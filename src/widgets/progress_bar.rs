@@ -0,0 +1,132 @@
+use crate::error::Error;
+use crate::geometry::Rect;
+use crate::screen::{OledScreen, TextStyle};
+
+use super::Widget;
+
+/// A horizontal bar filled from the left, with an optional label on the line above it
+pub struct ProgressBar {
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+    size: f32,
+    font_path: Option<String>,
+    label: String,
+    fraction: f32,
+}
+
+impl ProgressBar {
+    /// Create an empty bar occupying the given region of the screen. Labels are drawn at the
+    /// given text size, and the bar takes up whatever height is left below them
+    pub fn new(x: usize, y: usize, width: usize, height: usize, size: f32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+            size,
+            font_path: None,
+            label: String::new(),
+            fraction: 0.0,
+        }
+    }
+
+    /// Use a font loaded from the given path instead of the bundled font
+    pub fn with_font(mut self, font_path: &str) -> Self {
+        self.font_path = Some(font_path.to_string());
+        self
+    }
+
+    /// Set how full the bar is, from 0 to 1
+    pub fn set_fraction(&mut self, fraction: f32) {
+        self.fraction = fraction.clamp(0.0, 1.0);
+    }
+
+    /// Set the text shown above the bar. An empty label gives the bar the whole region
+    pub fn set_label(&mut self, label: &str) {
+        self.label = label.to_string();
+    }
+}
+
+impl Widget for ProgressBar {
+    fn draw(&self, screen: &mut OledScreen) -> Result<(), Error> {
+        let bounds = Rect::new(self.x, self.y, self.width, self.height);
+        screen.draw_widget(bounds, |screen, bounds| {
+            let max_y = bounds.max_y();
+            screen.paint_region(bounds, false);
+
+            // Text is drawn upwards from its y coordinate, so the label sits at the top of the
+            // region with the bar below it
+            let bar_max_y = if self.label.is_empty() {
+                max_y
+            } else {
+                let label_y = max_y.saturating_sub(self.size.ceil() as usize + 1);
+                screen.draw_text(
+                    &self.label,
+                    bounds.min_x(),
+                    label_y,
+                    &TextStyle::new(self.size).with_font(self.font_path.as_deref()),
+                )?;
+                label_y.max(bounds.min_y())
+            };
+            if bar_max_y - bounds.min_y() < 3 || bounds.width() < 3 {
+                return Ok(());
+            }
+
+            // A one pixel outline, with the inside filled up to the fraction
+            let bar = Rect::new(
+                bounds.min_x(),
+                bounds.min_y(),
+                bounds.width(),
+                bar_max_y - bounds.min_y(),
+            );
+            screen.paint_region(bar, true);
+            screen.paint_region(bar.inset(1), false);
+
+            let filled = ((bounds.width() - 2) as f32 * self.fraction).round() as usize;
+            screen.paint_region(
+                Rect::new(
+                    bounds.min_x() + 1,
+                    bounds.min_y() + 1,
+                    filled,
+                    bar.height() - 2,
+                ),
+                true,
+            );
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockDevice;
+
+    #[test]
+    fn test_bar_is_filled_to_fraction() {
+        let mut screen = OledScreen::from_device(MockDevice::new(), 32, 128).unwrap();
+        let mut bar = ProgressBar::new(0, 0, 32, 6, 8.0);
+        bar.set_fraction(0.5);
+        bar.draw(&mut screen).unwrap();
+
+        assert!(screen.get_pixel(0, 0));
+        assert!(screen.get_pixel(15, 3));
+        assert!(!screen.get_pixel(16, 3));
+        assert!(screen.get_pixel(31, 3));
+    }
+
+    #[test]
+    fn test_label_above_bar() {
+        let mut screen = OledScreen::from_device(MockDevice::new(), 32, 128).unwrap();
+        let mut bar = ProgressBar::new(0, 0, 32, 20, 8.0);
+        bar.set_label("/home");
+        bar.set_fraction(1.0);
+        bar.draw(&mut screen).unwrap();
+
+        assert!(screen.get_pixel(30, 1));
+        assert!(screen.get_pixel(0, 10));
+        assert!(!screen.get_pixel(0, 11));
+    }
+}
@@ -0,0 +1,164 @@
+use std::collections::VecDeque;
+
+use crate::error::Error;
+use crate::geometry::Rect;
+use crate::screen::{OledScreen, TextStyle};
+
+use super::Widget;
+
+/// A filled line graph of recent values, one pixel column per value with the newest on the
+/// right. Optionally labelled with the lowest and highest value currently shown
+pub struct Sparkline {
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+    values: VecDeque<f32>,
+    range: Option<(f32, f32)>,
+    labels: Option<f32>,
+    font_path: Option<String>,
+}
+
+impl Sparkline {
+    /// Create an empty graph occupying the given region of the screen. It holds as many values
+    /// as it is wide, and is scaled to fit them
+    pub fn new(x: usize, y: usize, width: usize, height: usize) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+            values: VecDeque::with_capacity(width),
+            range: None,
+            labels: None,
+            font_path: None,
+        }
+    }
+
+    /// Use a fixed range for the bottom and top of the graph instead of scaling to fit (e.g. 0 to
+    /// 100 for percentages). Values outside of it are clamped
+    pub fn with_range(mut self, min: f32, max: f32) -> Self {
+        self.range = Some((min, max));
+        self
+    }
+
+    /// Label the graph with the highest value at the top and the lowest at the bottom, using
+    /// text of the given size
+    pub fn with_labels(mut self, size: f32) -> Self {
+        self.labels = Some(size);
+        self
+    }
+
+    /// Use a font loaded from the given path for the labels instead of the bundled font
+    pub fn with_font(mut self, font_path: &str) -> Self {
+        self.font_path = Some(font_path.to_string());
+        self
+    }
+
+    /// Add a value to the right of the graph, dropping the oldest value once it is full
+    pub fn push(&mut self, value: f32) {
+        while self.values.len() >= self.width.max(1) {
+            self.values.pop_front();
+        }
+        self.values.push_back(value);
+    }
+
+    /// The lowest and highest value currently shown
+    pub fn bounds(&self) -> Option<(f32, f32)> {
+        let mut values = self.values.iter().copied();
+        let first = values.next()?;
+        Some(values.fold((first, first), |(min, max), value| {
+            (min.min(value), max.max(value))
+        }))
+    }
+}
+
+impl Widget for Sparkline {
+    fn draw(&self, screen: &mut OledScreen) -> Result<(), Error> {
+        let bounds = Rect::new(self.x, self.y, self.width, self.height);
+        screen.draw_widget(bounds, |screen, bounds| {
+            screen.paint_region(bounds, false);
+
+            let (min, max) = match (self.range, self.bounds()) {
+                (Some(range), _) => range,
+                // Auto-scaled graphs start from 0 unless values go negative, so that a flat line
+                // isn't drawn as an empty graph
+                (None, Some((min, max))) => (min.min(0.0), max),
+                (None, None) => return Ok(()),
+            };
+            let span = max - min;
+
+            // Only as many values as there are columns are drawn, which is none if the graph has
+            // no width
+            let hidden = self.values.len().saturating_sub(bounds.width());
+            let offset = bounds.width().saturating_sub(self.values.len());
+            for (index, value) in self.values.iter().skip(hidden).enumerate() {
+                let fraction = if span > 0.0 {
+                    ((value - min) / span).clamp(0.0, 1.0)
+                } else {
+                    0.0
+                };
+                let column_height = (fraction * bounds.height() as f32).round() as usize;
+                let x = bounds.min_x() + offset + index;
+                screen.paint_region(Rect::new(x, bounds.min_y(), 1, column_height), true);
+            }
+
+            if let (Some(size), Some((lowest, highest))) = (self.labels, self.bounds()) {
+                // Text is drawn upwards from its y coordinate
+                let style = TextStyle::new(size).with_font(self.font_path.as_deref());
+                let top = bounds.max_y().saturating_sub(size.ceil() as usize + 1);
+                screen.draw_text(&format!("{highest:.0}"), bounds.min_x(), top, &style)?;
+                screen.draw_text(
+                    &format!("{lowest:.0}"),
+                    bounds.min_x(),
+                    bounds.min_y(),
+                    &style,
+                )?;
+            }
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockDevice;
+
+    #[test]
+    fn test_zero_width() {
+        let mut sparkline = Sparkline::new(0, 0, 0, 10);
+        sparkline.push(1.0);
+        sparkline.push(2.0);
+        assert_eq!(sparkline.bounds(), Some((2.0, 2.0)));
+
+        let mut screen = OledScreen::from_device(MockDevice::new(), 32, 128).unwrap();
+        sparkline.draw(&mut screen).unwrap();
+        assert!((0..32).all(|x| !screen.get_pixel(x, 0)));
+    }
+
+    #[test]
+    fn test_push_drops_oldest() {
+        let mut sparkline = Sparkline::new(0, 0, 3, 10);
+        for value in [5.0, 1.0, 7.0, 3.0] {
+            sparkline.push(value);
+        }
+
+        assert_eq!(sparkline.values, [1.0, 7.0, 3.0]);
+        assert_eq!(sparkline.bounds(), Some((1.0, 7.0)));
+    }
+
+    #[test]
+    fn test_columns_are_filled() {
+        let mut screen = OledScreen::from_device(MockDevice::new(), 32, 128).unwrap();
+        let mut sparkline = Sparkline::new(0, 0, 32, 20).with_range(0.0, 100.0);
+        sparkline.push(100.0);
+        sparkline.push(50.0);
+        sparkline.draw(&mut screen).unwrap();
+
+        assert!(!screen.get_pixel(29, 0));
+        assert!(screen.get_pixel(30, 19));
+        assert!(screen.get_pixel(31, 9));
+        assert!(!screen.get_pixel(31, 10));
+    }
+}
@@ -0,0 +1,260 @@
+use std::time::{Duration, Instant};
+
+use crate::error::Error;
+use crate::geometry::Rect;
+use crate::screen::{OledScreen, TextStyle};
+
+use super::Widget;
+
+/// The default amount of time each message is shown for
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(3);
+
+/// The default length of the wipe between two messages
+const DEFAULT_TRANSITION: Duration = Duration::from_millis(400);
+
+/// A widget which cycles through a list of short messages, showing one at a time and wiping
+/// between them on a fixed interval
+pub struct Ticker {
+    messages: Vec<String>,
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+    size: f32,
+    font_path: Option<String>,
+    interval: Duration,
+    transition: Duration,
+    started: Instant,
+}
+
+/// What the ticker should be displaying at a given point in time
+#[derive(Debug, PartialEq)]
+enum TickerFrame {
+    Message(usize),
+    /// Wiping from the first message to the second, with the progress of the wipe between 0 and 1
+    Transition(usize, usize, f32),
+}
+
+impl Ticker {
+    /// Create a new ticker occupying the given region of the screen. Messages are drawn with the
+    /// bundled font at the given size and rotate every 3 seconds
+    pub fn new(
+        messages: Vec<String>,
+        x: usize,
+        y: usize,
+        width: usize,
+        height: usize,
+        size: f32,
+    ) -> Self {
+        Self {
+            messages,
+            x,
+            y,
+            width,
+            height,
+            size,
+            font_path: None,
+            interval: DEFAULT_INTERVAL,
+            transition: DEFAULT_TRANSITION,
+            started: Instant::now(),
+        }
+    }
+
+    /// Set how long each message is displayed for (including the transition into it)
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Set how long the wipe between two messages takes. A zero duration switches instantly
+    pub fn with_transition(mut self, transition: Duration) -> Self {
+        self.transition = transition;
+        self
+    }
+
+    /// Use a font loaded from the given path instead of the bundled font
+    pub fn with_font(mut self, font_path: &str) -> Self {
+        self.font_path = Some(font_path.to_string());
+        self
+    }
+
+    /// Replace the messages being cycled through and restart from the first one
+    pub fn set_messages(&mut self, messages: Vec<String>) {
+        self.messages = messages;
+        self.started = Instant::now();
+    }
+
+    /// Work out what should be on display after the ticker has been running for `elapsed`
+    fn frame_at(&self, elapsed: Duration) -> Option<TickerFrame> {
+        let count = self.messages.len();
+        if count == 0 {
+            return None;
+        }
+        if count == 1 || self.interval.is_zero() {
+            return Some(TickerFrame::Message(0));
+        }
+
+        let cycles = elapsed.as_nanos() / self.interval.as_nanos();
+        let index = (cycles % count as u128) as usize;
+        let into_cycle = elapsed.as_nanos() % self.interval.as_nanos();
+
+        if cycles == 0 || into_cycle >= self.transition.as_nanos() {
+            return Some(TickerFrame::Message(index));
+        }
+
+        let previous = (index + count - 1) % count;
+        let progress = into_cycle as f32 / self.transition.as_nanos() as f32;
+        Some(TickerFrame::Transition(previous, index, progress))
+    }
+
+    /// Draw the ticker as it should look once it has been running for `elapsed`, for driving it
+    /// from your own clock rather than the time since it was created
+    pub fn draw_at(&self, screen: &mut OledScreen, elapsed: Duration) -> Result<(), Error> {
+        let bounds = Rect::new(self.x, self.y, self.width, self.height);
+        screen.draw_widget(bounds, |screen, bounds| {
+            self.draw_frame(screen, bounds, elapsed)
+        })
+    }
+
+    fn draw_frame(
+        &self,
+        screen: &mut OledScreen,
+        bounds: Rect,
+        elapsed: Duration,
+    ) -> Result<(), Error> {
+        screen.paint_region(bounds, false);
+
+        match self.frame_at(elapsed) {
+            None => (),
+            Some(TickerFrame::Message(index)) => self.draw_message(screen, bounds, index)?,
+            Some(TickerFrame::Transition(from, to, progress)) => {
+                // The first half of the transition wipes the old message away, the second half
+                // wipes the new one in
+                if progress < 0.5 {
+                    self.draw_message(screen, bounds, from)?;
+                    let wiped = (bounds.width() as f32 * progress * 2.0) as usize;
+                    screen.paint_region(
+                        Rect::new(bounds.min_x(), bounds.min_y(), wiped, bounds.height()),
+                        false,
+                    );
+                } else {
+                    self.draw_message(screen, bounds, to)?;
+                    let revealed = (bounds.width() as f32 * (progress - 0.5) * 2.0) as usize;
+                    let remaining = bounds.width().saturating_sub(revealed);
+                    screen.paint_region(
+                        Rect::new(
+                            bounds.min_x() + revealed,
+                            bounds.min_y(),
+                            remaining,
+                            bounds.height(),
+                        ),
+                        false,
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn draw_message(
+        &self,
+        screen: &mut OledScreen,
+        bounds: Rect,
+        index: usize,
+    ) -> Result<(), Error> {
+        // Long messages are cut off rather than spilling into whatever is beside the ticker
+        screen.draw_clipped(bounds, |screen| {
+            screen.draw_text(
+                &self.messages[index],
+                bounds.min_x(),
+                bounds.min_y(),
+                &TextStyle::new(self.size).with_font(self.font_path.as_deref()),
+            )
+        })
+    }
+}
+
+impl Widget for Ticker {
+    fn draw(&self, screen: &mut OledScreen) -> Result<(), Error> {
+        self.draw_at(screen, self.started.elapsed())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockDevice;
+
+    fn ticker() -> Ticker {
+        Ticker::new(
+            vec!["Artist".into(), "Album".into(), "Title".into()],
+            0,
+            0,
+            32,
+            10,
+            8.0,
+        )
+        .with_interval(Duration::from_secs(2))
+        .with_transition(Duration::from_secs(1))
+    }
+
+    #[test]
+    fn test_frame_at() {
+        let ticker = ticker();
+
+        assert_eq!(
+            ticker.frame_at(Duration::from_millis(500)),
+            Some(TickerFrame::Message(0))
+        );
+        assert_eq!(
+            ticker.frame_at(Duration::from_millis(2500)),
+            Some(TickerFrame::Transition(0, 1, 0.5))
+        );
+        assert_eq!(
+            ticker.frame_at(Duration::from_millis(3500)),
+            Some(TickerFrame::Message(1))
+        );
+        assert_eq!(
+            ticker.frame_at(Duration::from_millis(6250)),
+            Some(TickerFrame::Transition(2, 0, 0.25))
+        );
+    }
+
+    #[test]
+    fn test_frame_at_without_messages() {
+        let ticker = Ticker::new(vec![], 0, 0, 32, 10, 8.0);
+
+        assert_eq!(ticker.frame_at(Duration::from_secs(10)), None);
+    }
+
+    #[test]
+    fn test_draw_stays_within_region() {
+        let mut screen = OledScreen::from_device(MockDevice::new(), 32, 128).unwrap();
+        screen.paint_region(Rect::new(0, 20, 32, 108), true);
+
+        ticker()
+            .draw_at(&mut screen, Duration::from_millis(2200))
+            .unwrap();
+
+        // Mid-wipe a strip of the region is cleared, but nothing outside of it is touched
+        assert!(!screen.get_pixel(0, 5));
+        for y in 20..128 {
+            assert!(screen.get_pixel(10, y));
+        }
+    }
+
+    #[test]
+    fn test_long_messages_are_clipped() {
+        let mut screen = OledScreen::from_device(MockDevice::new(), 32, 128).unwrap();
+        let ticker = Ticker::new(vec!["Far too long to fit".into()], 0, 0, 16, 4, 8.0);
+        ticker.draw_at(&mut screen, Duration::ZERO).unwrap();
+
+        let inside = screen.get_region(Rect::new(0, 0, 16, 4));
+        assert!(inside.iter().any(|byte| *byte != 0));
+        screen.paint_region(Rect::new(0, 0, 16, 4), false);
+        assert_eq!(
+            screen.get_region(Rect::new(0, 0, 32, 128)),
+            vec![0; 4 * 128]
+        );
+    }
+}
@@ -0,0 +1,207 @@
+use std::time::{Duration, Instant};
+
+use crate::error::Error;
+use crate::geometry::Rect;
+use crate::screen::{OledScreen, TextStyle};
+
+use super::Widget;
+
+/// The gap in pixels between the border of the toast and its message
+const PADDING: usize = 2;
+
+/// A message box which temporarily overlays part of the screen, restoring whatever was
+/// underneath once it has been visible for its duration. Only the part of the screen the toast
+/// covers is restored, so anything drawn elsewhere in the meantime is kept
+pub struct Toast {
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+    size: f32,
+    font_path: Option<String>,
+    duration: Duration,
+    active: Option<ActiveToast>,
+}
+
+struct ActiveToast {
+    message: String,
+    shown_at: Instant,
+    /// The part of the screen the toast covers, as read by `OledScreen::get_region`
+    underneath: Vec<u8>,
+}
+
+impl Toast {
+    /// Create a toast occupying the given region of the screen, which stays visible for
+    /// `duration` each time it is shown
+    pub fn new(
+        x: usize,
+        y: usize,
+        width: usize,
+        height: usize,
+        size: f32,
+        duration: Duration,
+    ) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+            size,
+            font_path: None,
+            duration,
+            active: None,
+        }
+    }
+
+    /// Use a font loaded from the given path instead of the bundled font
+    pub fn with_font(mut self, font_path: &str) -> Self {
+        self.font_path = Some(font_path.to_string());
+        self
+    }
+
+    /// Whether the toast is currently covering the screen
+    pub fn is_visible(&self) -> bool {
+        self.active.is_some()
+    }
+
+    /// Show a message, keeping a copy of the part of the screen it covers so that it can be
+    /// restored afterwards. If a toast is already visible its message is replaced and its timer
+    /// restarted
+    pub fn show(&mut self, screen: &mut OledScreen, message: &str) -> Result<(), Error> {
+        let underneath = match self.active.take() {
+            Some(active) => active.underneath,
+            None => screen.get_region(self.bounds()),
+        };
+
+        self.active = Some(ActiveToast {
+            message: message.to_string(),
+            shown_at: Instant::now(),
+            underneath,
+        });
+        self.draw(screen)
+    }
+
+    /// Restore the screen if the toast has been visible for long enough. Returns whether the
+    /// toast is still visible
+    pub fn update(&mut self, screen: &mut OledScreen) -> bool {
+        let expired = self
+            .active
+            .as_ref()
+            .is_some_and(|active| active.shown_at.elapsed() >= self.duration);
+
+        if expired {
+            self.dismiss(screen);
+        }
+        self.is_visible()
+    }
+
+    /// Hide the toast immediately, restoring what was underneath it
+    pub fn dismiss(&mut self, screen: &mut OledScreen) {
+        if let Some(active) = self.active.take() {
+            screen.set_region(self.bounds(), &active.underneath);
+        }
+    }
+
+    fn bounds(&self) -> Rect {
+        Rect::new(self.x, self.y, self.width, self.height)
+    }
+}
+
+impl Widget for Toast {
+    fn draw(&self, screen: &mut OledScreen) -> Result<(), Error> {
+        screen.draw_widget(self.bounds(), |screen, bounds| {
+            let active = match &self.active {
+                Some(active) => active,
+                None => return Ok(()),
+            };
+
+            // A filled box with the inside cleared leaves a one pixel border
+            screen.paint_region(bounds, true);
+            screen.paint_region(bounds.inset(1), false);
+
+            // Keep long messages inside the border, so that only the toast's region needs to be
+            // restored when it is dismissed
+            screen.draw_clipped(bounds.inset(1), |screen| {
+                screen.draw_text(
+                    &active.message,
+                    bounds.min_x() + PADDING,
+                    bounds.min_y() + PADDING,
+                    &TextStyle::new(self.size).with_font(self.font_path.as_deref()),
+                )
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockDevice;
+
+    #[test]
+    fn test_toast_restores_screen() {
+        let mut screen = OledScreen::from_device(MockDevice::new(), 32, 128).unwrap();
+        screen.paint_region(Rect::new(0, 0, 32, 128), true);
+        let before = screen.snapshot();
+
+        let mut toast = Toast::new(0, 40, 32, 16, 8.0, Duration::ZERO);
+        toast.show(&mut screen, "Hi").unwrap();
+
+        assert!(toast.is_visible());
+        assert!(!screen.get_pixel(1, 41));
+        assert_ne!(before, screen.snapshot());
+
+        assert!(!toast.update(&mut screen));
+        assert_eq!(before, screen.snapshot());
+    }
+
+    #[test]
+    fn test_toast_keeps_drawing_elsewhere() {
+        let mut screen = OledScreen::from_device(MockDevice::new(), 32, 128).unwrap();
+        screen.paint_region(Rect::new(0, 40, 32, 16), true);
+
+        let mut toast = Toast::new(0, 40, 32, 16, 8.0, Duration::from_secs(60));
+        toast.show(&mut screen, "Hi").unwrap();
+        screen.paint_region(Rect::new(0, 100, 8, 8), true);
+        toast.dismiss(&mut screen);
+
+        assert!(screen.get_pixel(4, 104));
+        assert!(screen.get_pixel(1, 41));
+        assert!(!screen.get_pixel(4, 60));
+    }
+
+    #[test]
+    fn test_toast_partly_off_screen() {
+        use crate::screen::Origin;
+
+        for origin in [Origin::BottomLeft, Origin::TopLeft] {
+            let mut screen = OledScreen::from_device(MockDevice::new(), 32, 128)
+                .unwrap()
+                .with_origin(origin);
+            screen.paint_region(Rect::new(0, 0, 32, 128), true);
+            let before = screen.snapshot();
+
+            // Past both the right hand edge and the far end of the screen
+            let mut toast = Toast::new(20, 120, 24, 16, 8.0, Duration::from_secs(60));
+            toast.show(&mut screen, "Hi").unwrap();
+            assert!(!screen.get_pixel(21, 121));
+
+            toast.dismiss(&mut screen);
+            assert_eq!(before, screen.snapshot());
+        }
+    }
+
+    #[test]
+    fn test_toast_stays_visible() {
+        let mut screen = OledScreen::from_device(MockDevice::new(), 32, 128).unwrap();
+        let mut toast = Toast::new(0, 40, 32, 16, 8.0, Duration::from_secs(60));
+
+        toast.show(&mut screen, "Hi").unwrap();
+        toast.show(&mut screen, "Hello").unwrap();
+        assert!(toast.update(&mut screen));
+
+        toast.dismiss(&mut screen);
+        assert!(!toast.is_visible());
+        assert!(!screen.get_pixel(0, 40));
+    }
+}
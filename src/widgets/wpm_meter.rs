@@ -0,0 +1,263 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use crate::error::Error;
+use crate::events::KeyboardEvent;
+use crate::geometry::Rect;
+use crate::screen::{OledScreen, TextStyle};
+
+use super::Widget;
+
+/// The conventional number of keypresses in a "word"
+const CHARACTERS_PER_WORD: f32 = 5.0;
+
+/// Keypresses older than this are not counted towards the current WPM
+const DEFAULT_WINDOW: Duration = Duration::from_secs(5);
+
+/// How often the current WPM is added to the history graph
+const DEFAULT_SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A words-per-minute meter showing the current and average typing speed above a graph of recent
+/// history. Keypresses are fed in either from keyboard events or a host-side counter
+pub struct WpmMeter {
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+    size: f32,
+    font_path: Option<String>,
+    window: Duration,
+    sample_interval: Duration,
+    recent: VecDeque<Instant>,
+    history: VecDeque<f32>,
+    first_keypress: Option<Instant>,
+    total_keypresses: u64,
+    last_sample: Option<Instant>,
+}
+
+impl WpmMeter {
+    /// Create a meter occupying the given region of the screen. The graph is one pixel wide per
+    /// sample and fills whatever height is left below the two lines of text
+    pub fn new(x: usize, y: usize, width: usize, height: usize, size: f32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+            size,
+            font_path: None,
+            window: DEFAULT_WINDOW,
+            sample_interval: DEFAULT_SAMPLE_INTERVAL,
+            recent: VecDeque::new(),
+            history: VecDeque::new(),
+            first_keypress: None,
+            total_keypresses: 0,
+            last_sample: None,
+        }
+    }
+
+    /// Use a font loaded from the given path instead of the bundled font
+    pub fn with_font(mut self, font_path: &str) -> Self {
+        self.font_path = Some(font_path.to_string());
+        self
+    }
+
+    /// Set how far back keypresses are counted towards the current WPM. Longer windows give a
+    /// smoother but slower to react reading
+    pub fn with_window(mut self, window: Duration) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// Set how often the current WPM is added to the history graph
+    pub fn with_sample_interval(mut self, sample_interval: Duration) -> Self {
+        self.sample_interval = sample_interval;
+        self
+    }
+
+    /// Count a single keypress
+    pub fn record_keypress(&mut self) {
+        self.record_keypress_at(Instant::now())
+    }
+
+    /// Count keypresses from keyboard events. Returns whether the event was handled
+    pub fn handle_event(&mut self, event: &KeyboardEvent) -> bool {
+        match event {
+            KeyboardEvent::Keypress => {
+                self.record_keypress();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// The typing speed over the last few seconds
+    pub fn current_wpm(&self) -> f32 {
+        self.current_wpm_at(Instant::now())
+    }
+
+    /// The typing speed since the first keypress was recorded
+    pub fn average_wpm(&self) -> f32 {
+        self.average_wpm_at(Instant::now())
+    }
+
+    /// Add the current WPM to the history graph if a sample is due. Call this regularly (e.g.
+    /// once per frame) so that idle periods show up on the graph
+    pub fn update(&mut self) {
+        self.update_at(Instant::now())
+    }
+
+    fn record_keypress_at(&mut self, now: Instant) {
+        self.first_keypress.get_or_insert(now);
+        self.total_keypresses += 1;
+        self.recent.push_back(now);
+        self.prune(now);
+    }
+
+    fn prune(&mut self, now: Instant) {
+        while let Some(oldest) = self.recent.front() {
+            if now.duration_since(*oldest) <= self.window {
+                break;
+            }
+            self.recent.pop_front();
+        }
+    }
+
+    fn current_wpm_at(&self, now: Instant) -> f32 {
+        let keypresses = self
+            .recent
+            .iter()
+            .filter(|keypress| now.duration_since(**keypress) <= self.window)
+            .count();
+
+        words_per_minute(keypresses as f32, self.window)
+    }
+
+    fn average_wpm_at(&self, now: Instant) -> f32 {
+        match self.first_keypress {
+            Some(first) => {
+                let elapsed = now.duration_since(first).max(self.window);
+                words_per_minute(self.total_keypresses as f32, elapsed)
+            }
+            None => 0.0,
+        }
+    }
+
+    fn update_at(&mut self, now: Instant) {
+        let due = self
+            .last_sample
+            .is_none_or(|last| now.duration_since(last) >= self.sample_interval);
+        if !due {
+            return;
+        }
+
+        self.prune(now);
+        self.last_sample = Some(now);
+        self.history.push_back(self.current_wpm_at(now));
+        while self.history.len() > self.width {
+            self.history.pop_front();
+        }
+    }
+
+    fn line_height(&self) -> usize {
+        self.size.ceil() as usize + 1
+    }
+
+    fn draw_at(&self, screen: &mut OledScreen, now: Instant) -> Result<(), Error> {
+        let bounds = Rect::new(self.x, self.y, self.width, self.height);
+        screen.draw_widget(bounds, |screen, bounds| {
+            let max_y = bounds.max_y();
+            screen.paint_region(bounds, false);
+
+            // Text is drawn upwards from its y coordinate, so the two lines sit at the top of the
+            // region with the graph below them
+            let line_height = self.line_height();
+            let lines = [
+                format!("{:.0}", self.current_wpm_at(now)),
+                format!("~{:.0}", self.average_wpm_at(now)),
+            ];
+            for (index, line) in lines.iter().enumerate() {
+                if let Some(line_y) = max_y.checked_sub((index + 1) * line_height) {
+                    screen.draw_text(
+                        line,
+                        bounds.min_x(),
+                        line_y,
+                        &TextStyle::new(self.size).with_font(self.font_path.as_deref()),
+                    )?;
+                }
+            }
+
+            let graph_height = bounds.height().saturating_sub(2 * line_height + 1);
+            let peak = self.history.iter().copied().fold(0.0, f32::max);
+            if graph_height == 0 || peak <= 0.0 {
+                return Ok(());
+            }
+
+            // The newest sample is always drawn at the right hand edge
+            let offset = bounds.width() - self.history.len();
+            for (index, sample) in self.history.iter().enumerate() {
+                let column_height = (sample / peak * graph_height as f32).round() as usize;
+                let x = bounds.min_x() + offset + index;
+                screen.paint_region(Rect::new(x, bounds.min_y(), 1, column_height), true);
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Convert a number of keypresses over a period of time into words per minute
+fn words_per_minute(keypresses: f32, period: Duration) -> f32 {
+    if period.is_zero() {
+        return 0.0;
+    }
+    keypresses / CHARACTERS_PER_WORD * (60.0 / period.as_secs_f32())
+}
+
+impl Widget for WpmMeter {
+    fn draw(&self, screen: &mut OledScreen) -> Result<(), Error> {
+        self.draw_at(screen, Instant::now())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockDevice;
+
+    #[test]
+    fn test_wpm() {
+        let start = Instant::now();
+        let mut meter = WpmMeter::new(0, 0, 32, 64, 8.0);
+
+        // 50 keypresses in 5 seconds is 10 words in 1/12th of a minute
+        for i in 0..50 {
+            meter.record_keypress_at(start + Duration::from_millis(i * 100));
+        }
+        let now = start + Duration::from_secs(5);
+        assert_eq!(meter.current_wpm_at(now).round(), 120.0);
+        assert_eq!(meter.average_wpm_at(now).round(), 120.0);
+
+        let later = start + Duration::from_secs(20);
+        assert_eq!(meter.current_wpm_at(later), 0.0);
+        assert_eq!(meter.average_wpm_at(later).round(), 30.0);
+    }
+
+    #[test]
+    fn test_history_graph() {
+        let start = Instant::now();
+        let mut screen = OledScreen::from_device(MockDevice::new(), 32, 128).unwrap();
+        let mut meter = WpmMeter::new(0, 0, 32, 64, 8.0);
+
+        meter.update_at(start);
+        meter.record_keypress_at(start);
+        meter.update_at(start + Duration::from_millis(500));
+        meter.update_at(start + Duration::from_secs(1));
+        assert_eq!(meter.history.len(), 2);
+
+        meter
+            .draw_at(&mut screen, start + Duration::from_secs(1))
+            .unwrap();
+        assert!(!screen.get_pixel(30, 0));
+        assert!(screen.get_pixel(31, 0));
+    }
+}